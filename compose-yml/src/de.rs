@@ -0,0 +1,165 @@
+//! Helpers to deserialize the different forms the compose spec allows.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer};
+
+/// A yaml scalar, the spec allows numbers and booleans in many places
+/// where a string is expected
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Scalar {
+	String(String),
+	Bool(bool),
+	Int(i64),
+	Float(f64),
+}
+
+impl Scalar {
+	pub fn into_string(self) -> String {
+		match self {
+			Self::String(s) => s,
+			Self::Bool(b) => b.to_string(),
+			Self::Int(i) => i.to_string(),
+			Self::Float(f) => f.to_string(),
+		}
+	}
+}
+
+/// Either a list of `key=value` or a map
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ListOrMap {
+	List(Vec<String>),
+	Map(BTreeMap<String, Option<Scalar>>),
+}
+
+impl ListOrMap {
+	/// A list entry without `=` has no value
+	pub fn into_map(self) -> BTreeMap<String, Option<String>> {
+		match self {
+			Self::List(list) => list
+				.into_iter()
+				.map(|entry| match entry.split_once('=') {
+					Some((k, v)) => (k.to_string(), Some(v.to_string())),
+					None => (entry, None),
+				})
+				.collect(),
+			Self::Map(map) => map
+				.into_iter()
+				.map(|(k, v)| (k, v.map(Scalar::into_string)))
+				.collect(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+	One(T),
+	Many(Vec<T>),
+}
+
+/// Accepts a single value or a list of values
+pub(crate) fn one_or_many<'de, D, T>(d: D) -> Result<Vec<T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de>,
+{
+	Ok(match Option::<OneOrMany<T>>::deserialize(d)? {
+		Some(OneOrMany::One(v)) => vec![v],
+		Some(OneOrMany::Many(v)) => v,
+		None => vec![],
+	})
+}
+
+/// Either a list of names or a map where the value might be null
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum NamesOrMap<T> {
+	Names(Vec<String>),
+	Map(BTreeMap<String, Option<T>>),
+}
+
+/// Accepts a list of names or a map, missing values are replaced with
+/// `T::default()`
+pub(crate) fn names_or_map<'de, D, T>(
+	d: D,
+) -> Result<BTreeMap<String, T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de> + Default,
+{
+	Ok(match Option::<NamesOrMap<T>>::deserialize(d)? {
+		Some(NamesOrMap::Names(names)) => {
+			names.into_iter().map(|n| (n, T::default())).collect()
+		}
+		Some(NamesOrMap::Map(map)) => map
+			.into_iter()
+			.map(|(k, v)| (k, v.unwrap_or_default()))
+			.collect(),
+		None => BTreeMap::new(),
+	})
+}
+
+/// Accepts a map of scalars, for example `driver_opts`
+pub(crate) fn scalar_map<'de, D>(
+	d: D,
+) -> Result<BTreeMap<String, String>, D::Error>
+where
+	D: Deserializer<'de>,
+{
+	Ok(Option::<BTreeMap<String, Scalar>>::deserialize(d)?
+		.unwrap_or_default()
+		.into_iter()
+		.map(|(k, v)| (k, v.into_string()))
+		.collect())
+}
+
+/// Accepts a map where the value might be null
+pub(crate) fn map_with_defaults<'de, D, T>(
+	d: D,
+) -> Result<BTreeMap<String, T>, D::Error>
+where
+	D: Deserializer<'de>,
+	T: Deserialize<'de> + Default,
+{
+	Ok(Option::<BTreeMap<String, Option<T>>>::deserialize(d)?
+		.unwrap_or_default()
+		.into_iter()
+		.map(|(k, v)| (k, v.unwrap_or_default()))
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scalar_into_string() {
+		let scalars: Vec<Scalar> =
+			serde_yaml::from_str("[text, true, 42, 1.5, '007']").unwrap();
+		let strings = scalars
+			.into_iter()
+			.map(Scalar::into_string)
+			.collect::<Vec<_>>();
+
+		assert_eq!(strings, ["text", "true", "42", "1.5", "007"]);
+	}
+
+	#[test]
+	fn list_or_map() {
+		let list: ListOrMap =
+			serde_yaml::from_str("[A=1, B=, C, D=x=y]").unwrap();
+		let map: ListOrMap =
+			serde_yaml::from_str("{A: 1, B: '', C: null, D: x=y}").unwrap();
+
+		for parsed in [list, map] {
+			let parsed = parsed.into_map();
+			assert_eq!(parsed["A"].as_deref(), Some("1"));
+			assert_eq!(parsed["B"].as_deref(), Some(""));
+			assert_eq!(parsed["C"], None);
+			assert_eq!(parsed["D"].as_deref(), Some("x=y"));
+		}
+	}
+}
@@ -0,0 +1,445 @@
+/*!
+Diagnostics for an editor.
+
+Unlike [`Compose::validate_for`] and [`Compose::check_policy`], which stop at
+the first error, every problem of the compose file is collected together with
+its location. Besides errors some lints are reported as warnings.
+
+Serde does not keep the position of a value, so the location of a problem is
+searched in the source by its path of keys. Only keys in block style can be
+found, otherwise the location of the closest parent is used.
+*/
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	Compose, ComposeError, ComposeService, PolicyAction, SecurityPolicy,
+	domains::ClaimedHost,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticSeverity {
+	Error,
+	Warning,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+	pub severity: DiagnosticSeverity,
+	pub message: String,
+	/// Starts at 1
+	pub line: Option<usize>,
+	/// Starts at 1
+	pub column: Option<usize>,
+	/// The keys leading to the problem, for example
+	/// `["services", "web", "image"]`
+	pub path: Vec<String>,
+}
+
+impl Diagnostic {
+	fn new(
+		source: &str,
+		severity: DiagnosticSeverity,
+		message: impl Into<String>,
+		path: Vec<String>,
+	) -> Self {
+		let location = locate(source, &path);
+
+		Self {
+			severity,
+			message: message.into(),
+			line: location.map(|(l, _)| l),
+			column: location.map(|(_, c)| c),
+			path,
+		}
+	}
+
+	fn from_error(
+		source: &str,
+		severity: DiagnosticSeverity,
+		service: &str,
+		error: &ComposeError,
+	) -> Self {
+		let mut path = vec!["services".to_string(), service.to_string()];
+		path.extend(error_key(error));
+
+		Self::new(source, severity, error.to_string(), path)
+	}
+
+	pub fn is_error(&self) -> bool {
+		self.severity == DiagnosticSeverity::Error
+	}
+}
+
+/// Returns the key of the service which caused the error
+fn error_key(error: &ComposeError) -> Option<String> {
+	let key = match error {
+		ComposeError::UnexpectedTraefikRouterName { .. }
+		| ComposeError::InvalidTraefikRule { .. }
+		| ComposeError::StartFirstWithoutTraefikService { .. }
+		| ComposeError::DomainConflict { .. } => "labels",
+		ComposeError::InvalidImage { .. }
+		| ComposeError::InvalidImageReference { .. } => "image",
+		ComposeError::StartFirstWithContainerName { .. } => "container_name",
+		ComposeError::StartFirstWithPublishedPort { .. } => "ports",
+		ComposeError::PrivilegedService { .. } => "privileged",
+		ComposeError::HostNamespace { namespace, .. } => namespace,
+		ComposeError::ForeignContainer { key, .. } => key,
+		ComposeError::CapabilityAdded { .. } => "cap_add",
+		ComposeError::DeviceMapped { .. } => "devices",
+		ComposeError::UnsafeSecurityOpt { .. } => "security_opt",
+		ComposeError::BindMountOutsideApp { .. } => "volumes",
+		ComposeError::EnvFileOutsideApp { .. } => "env_file",
+		ComposeError::Parsing(_)
+		| ComposeError::FileOutsideApp { .. }
+		| ComposeError::IncludeNotSupported
+		| ComposeError::Config { .. } => return None,
+	};
+
+	Some(key.to_string())
+}
+
+/// Returns the key if the line starts with one
+fn key_of(line: &str) -> Option<&str> {
+	let (key, rest) = match line.chars().next()? {
+		q @ ('"' | '\'') => {
+			let end = line[1..].find(q)? + 1;
+			(&line[1..end], &line[end + 1..])
+		}
+		_ => {
+			let end = line.find(':')?;
+			(line[..end].trim_end(), &line[end..])
+		}
+	};
+
+	let rest = rest.strip_prefix(':')?;
+	(rest.is_empty() || rest.starts_with([' ', '\t'])).then_some(key)
+}
+
+/// Returns the line and column of the deepest key of the path which could
+/// be found
+fn locate(source: &str, path: &[String]) -> Option<(usize, usize)> {
+	let lines = source.lines().collect::<Vec<_>>();
+
+	let mut location = None;
+	let mut start = 0;
+	let mut parent_indent = None;
+
+	for segment in path {
+		// the indentation of the keys in the block of the parent
+		let mut child_indent = None;
+		let mut found = None;
+
+		for (i, line) in lines.iter().enumerate().skip(start) {
+			let trimmed = line.trim_start();
+			if trimmed.is_empty() || trimmed.starts_with('#') {
+				continue;
+			}
+
+			let indent = line.len() - trimmed.len();
+			// the block of the parent ended
+			if parent_indent.is_some_and(|p| indent <= p) {
+				break;
+			}
+
+			let child_indent = *child_indent.get_or_insert(indent);
+			if indent == child_indent && key_of(trimmed) == Some(segment) {
+				found = Some((i, indent));
+				break;
+			}
+		}
+
+		let Some((i, indent)) = found else {
+			break;
+		};
+
+		location = Some((i + 1, indent + 1));
+		start = i + 1;
+		parent_indent = Some(indent);
+	}
+
+	location
+}
+
+/// Returns the warnings which don't prevent the compose file from working
+fn lint_service(name: &str, service: &ComposeService) -> Vec<(String, String)> {
+	let mut lints = vec![];
+
+	if service.traefik_enabled()
+		&& service.network_mode.is_none()
+		&& !service.networks.contains_key("traefik")
+	{
+		lints.push((
+			format!(
+				"Service {name} is routed by traefik but is not in the traefik network"
+			),
+			"networks".into(),
+		));
+	}
+
+	if service.restart.is_none() {
+		lints.push((
+			format!(
+				"Service {name} has no restart policy and will not be started \
+				after a reboot"
+			),
+			"restart".into(),
+		));
+	}
+
+	for router in service.traefik_router_rules().into_keys() {
+		let key = format!("traefik.http.routers.{router}.tls.certresolver");
+		if service.labels.get(&key).is_none() {
+			lints.push((
+				format!("Router {router} is missing {key}"),
+				"labels".into(),
+			));
+		}
+	}
+
+	lints
+}
+
+/// Returns every problem of the compose file, errors first
+///
+/// `claimed` are the hosts used by other apps or routers, see
+/// [`Compose::check_domain_conflicts`].
+pub fn diagnose(
+	source: &str,
+	registry: &str,
+	app_id: &str,
+	policy: &SecurityPolicy,
+	app_dir: impl AsRef<Path>,
+	claimed: &[ClaimedHost],
+) -> Vec<Diagnostic> {
+	use DiagnosticSeverity::*;
+
+	let compose = match serde_yaml::from_str::<Compose>(source) {
+		Ok(c) => c,
+		Err(e) => {
+			let location = e.location();
+
+			return vec![Diagnostic {
+				severity: Error,
+				message: ComposeError::from(e).to_string(),
+				line: location.as_ref().map(|l| l.line()),
+				column: location.as_ref().map(|l| l.column()),
+				path: vec![],
+			}];
+		}
+	};
+
+	let mut services = compose.services.iter().collect::<Vec<_>>();
+	services.sort_by_key(|(name, _)| *name);
+
+	let mut errors = vec![];
+	let mut warnings = vec![];
+
+	if !compose.include.is_empty() {
+		let e = ComposeError::IncludeNotSupported;
+		errors.push(Diagnostic::new(
+			source,
+			Error,
+			e.to_string(),
+			vec!["include".into()],
+		));
+	}
+
+	for (name, service) in &services {
+		for e in service.errors_for(registry, app_id, name) {
+			errors.push(Diagnostic::from_error(source, Error, name, &e));
+		}
+	}
+
+	for (name, e) in compose.domain_conflicts(claimed) {
+		errors.push(Diagnostic::from_error(source, Error, &name, &e));
+	}
+
+	for (name, action, e) in compose.policy_violations(policy, &app_dir) {
+		match action {
+			PolicyAction::Allow => {}
+			PolicyAction::Warn => warnings
+				.push(Diagnostic::from_error(source, Warning, &name, &e)),
+			PolicyAction::Deny => {
+				errors.push(Diagnostic::from_error(source, Error, &name, &e))
+			}
+		}
+	}
+
+	for (key, name, action, e) in compose.file_violations(policy, &app_dir) {
+		let path = vec![key, name, "file".into()];
+		let severity = match action {
+			PolicyAction::Allow => continue,
+			PolicyAction::Warn => Warning,
+			PolicyAction::Deny => Error,
+		};
+		let diagnostic = Diagnostic::new(source, severity, e.to_string(), path);
+		match severity {
+			Error => errors.push(diagnostic),
+			Warning => warnings.push(diagnostic),
+		}
+	}
+
+	for (name, service) in &services {
+		for (message, key) in lint_service(name, service) {
+			let path = vec!["services".into(), name.to_string(), key];
+			warnings.push(Diagnostic::new(source, Warning, message, path));
+		}
+	}
+
+	errors.extend(warnings);
+	errors
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn path(keys: &[&str]) -> Vec<String> {
+		keys.iter().map(|k| k.to_string()).collect()
+	}
+
+	#[test]
+	fn key_of_plain_and_quoted() {
+		assert_eq!(key_of("image: nginx"), Some("image"));
+		assert_eq!(key_of("labels:"), Some("labels"));
+		assert_eq!(key_of("restart:\tunless-stopped"), Some("restart"));
+		assert_eq!(key_of("url: http://localhost:80"), Some("url"));
+		assert_eq!(key_of("\"my key\": value"), Some("my key"));
+		assert_eq!(key_of("'a:b': value"), Some("a:b"));
+		assert_eq!(key_of("image :nginx"), None);
+		assert_eq!(key_of("image:nginx"), None);
+		assert_eq!(key_of("- nginx"), None);
+		assert_eq!(key_of("\"unterminated: value"), None);
+	}
+
+	#[test]
+	fn locate_block_style() {
+		let source = "\
+# the app
+services:
+  # the web server
+  web:
+    image: nginx # the image
+    labels:
+      - traefik.enable=true
+  'db':
+    image: postgres
+    restart: always
+";
+
+		assert_eq!(locate(source, &path(&["services"])), Some((2, 1)));
+		assert_eq!(
+			locate(source, &path(&["services", "web", "image"])),
+			Some((5, 5))
+		);
+		assert_eq!(
+			locate(source, &path(&["services", "db", "restart"])),
+			Some((10, 5))
+		);
+		// web has no restart, the one of db must not be found
+		assert_eq!(
+			locate(source, &path(&["services", "web", "restart"])),
+			Some((4, 3))
+		);
+		assert_eq!(locate(source, &path(&["volumes"])), None);
+	}
+
+	#[test]
+	fn locate_flow_style() {
+		let source = "\
+services:
+  web: { image: nginx, restart: always }
+";
+
+		// keys inside a flow mapping are not found, the parent is used
+		assert_eq!(
+			locate(source, &path(&["services", "web", "image"])),
+			Some((2, 3))
+		);
+	}
+
+	#[test]
+	fn diagnose_domain_conflicts() {
+		let source = "\
+services:
+  web:
+    image: nginx
+    restart: always
+    networks:
+      - traefik
+    labels:
+      - traefik.enable=true
+      - traefik.http.routers.myapp-web.rule=Host(`example.com`)
+      - traefik.http.routers.myapp-web.tls.certresolver=default
+";
+		let claimed = [ClaimedHost {
+			host: "example.com".into(),
+			router: "other-web".into(),
+			app_id: Some("other".into()),
+		}];
+
+		let diagnostics = diagnose(
+			source,
+			"registry.local",
+			"myapp",
+			&SecurityPolicy::default(),
+			"/apps/myapp",
+			&claimed,
+		);
+
+		assert_eq!(diagnostics.len(), 1);
+		assert!(diagnostics[0].is_error());
+		assert_eq!(
+			diagnostics[0].message,
+			"Domain example.com is already used by the router other-web of the app other"
+		);
+		assert_eq!(diagnostics[0].line, Some(7));
+	}
+
+	#[test]
+	fn diagnose_every_error_of_a_service() {
+		let source = "\
+services:
+  web:
+    image: registry.local/other/web
+    restart: always
+    container_name: web
+    ports:
+      - 8080:80
+    x-hostdinghy:
+      deploy: start-first
+    labels:
+      - traefik.http.routers.web.rule=Host(`example.com`
+";
+
+		let diagnostics = diagnose(
+			source,
+			"registry.local",
+			"myapp",
+			&SecurityPolicy::default(),
+			"/apps/myapp",
+			&[],
+		);
+
+		let errors = diagnostics
+			.iter()
+			.filter(|d| d.is_error())
+			.map(|d| (d.path.last().unwrap().as_str(), d.line))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			errors,
+			[
+				("image", Some(3)),
+				("container_name", Some(5)),
+				("ports", Some(6)),
+				("labels", Some(10)),
+				("labels", Some(10)),
+			]
+		);
+	}
+}
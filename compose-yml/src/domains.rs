@@ -0,0 +1,94 @@
+/*!
+Hosts claimed by traefik routers.
+
+Traefik silently picks one router if two routers match the same host, so a
+host may only be claimed by one app.
+*/
+
+use crate::{Compose, ComposeError, TraefikRule};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClaimedHost {
+	/// Always lowercase
+	pub host: String,
+	/// The name of the router without the provider
+	pub router: String,
+	/// None if the router does not belong to an app
+	pub app_id: Option<String>,
+}
+
+impl Compose {
+	/// Returns the hosts of the `Host` matchers of all routers, services
+	/// which are not enabled in traefik are ignored
+	pub fn claimed_hosts(&self, app_id: Option<&str>) -> Vec<ClaimedHost> {
+		self.service_hosts()
+			.into_iter()
+			.map(|(_, router, host)| ClaimedHost {
+				host,
+				router,
+				app_id: app_id.map(Into::into),
+			})
+			.collect()
+	}
+
+	/// Returns the service, the router and the lowercase host of every
+	/// `Host` matcher, sorted by the service
+	fn service_hosts(&self) -> Vec<(&str, String, String)> {
+		let mut services = self.services.iter().collect::<Vec<_>>();
+		services.sort_by_key(|(name, _)| *name);
+
+		services
+			.into_iter()
+			.filter(|(_, service)| service.traefik_enabled())
+			.flat_map(|(name, service)| {
+				service
+					.traefik_router_rules()
+					.into_iter()
+					.map(move |(router, rule)| (name.as_str(), router, rule))
+			})
+			.filter_map(|(name, router, rule)| {
+				let rule = rule.parse::<TraefikRule>().ok()?;
+				Some((name, router, rule.hosts()))
+			})
+			.flat_map(|(name, router, hosts)| {
+				hosts.into_iter().map(move |host| {
+					(name, router.clone(), host.to_ascii_lowercase())
+				})
+			})
+			.collect()
+	}
+
+	/// Returns every host of this compose file which is already claimed by
+	/// someone else together with the service claiming it
+	pub(crate) fn domain_conflicts(
+		&self,
+		others: &[ClaimedHost],
+	) -> Vec<(String, ComposeError)> {
+		self.service_hosts()
+			.into_iter()
+			.filter_map(|(name, _, host)| {
+				let other = others.iter().find(|o| o.host == host)?;
+				Some((
+					name.to_string(),
+					ComposeError::DomainConflict {
+						domain: host,
+						router: other.router.clone(),
+						app_id: other.app_id.clone(),
+					},
+				))
+			})
+			.collect()
+	}
+
+	/// Returns an error if one of the hosts of this compose file is already
+	/// claimed by someone else
+	pub fn check_domain_conflicts(
+		&self,
+		others: &[ClaimedHost],
+	) -> Result<(), ComposeError> {
+		match self.domain_conflicts(others).into_iter().next() {
+			Some((_, e)) => Err(e),
+			None => Ok(()),
+		}
+	}
+}
@@ -12,8 +12,73 @@ pub enum ComposeError {
 		unexpected: Vec<String>,
 		expected: String,
 	},
+	#[error("Traefik rule {rule} is not valid: {reason}")]
+	InvalidTraefikRule { rule: String, reason: String },
 	#[error("Image {image} is not valid, expected {expected}")]
 	InvalidImage { image: String, expected: String },
+	#[error("Image {image} is not a valid reference: {reason}")]
+	InvalidImageReference { image: String, reason: String },
+	#[error(
+		"Service {service} uses the start-first deploy strategy and cannot have a container_name"
+	)]
+	StartFirstWithContainerName { service: String },
+	#[error(
+		"Service {service} uses the start-first deploy strategy and cannot publish the port {port}"
+	)]
+	StartFirstWithPublishedPort { service: String, port: String },
+	#[error(
+		"Service {service} uses the start-first deploy strategy and needs a label traefik.http.services.{expected}..."
+	)]
+	StartFirstWithoutTraefikService { service: String, expected: String },
+	#[error("Service {service} is privileged")]
+	PrivilegedService { service: String },
+	#[error("Service {service} sets {namespace} to host")]
+	HostNamespace { service: String, namespace: String },
+	#[error(
+		"Service {service} uses {key} of the container {container}, which is not part of the app"
+	)]
+	ForeignContainer {
+		service: String,
+		key: String,
+		container: String,
+	},
+	#[error("Service {service} adds the capability {capability}")]
+	CapabilityAdded { service: String, capability: String },
+	#[error("Service {service} has access to the device {device}")]
+	DeviceMapped { service: String, device: String },
+	#[error("Service {service} uses the security option {option}")]
+	UnsafeSecurityOpt { service: String, option: String },
+	#[error(
+		"Service {service} mounts {path}, which is outside of the app directory"
+	)]
+	BindMountOutsideApp { service: String, path: String },
+	#[error(
+		"Service {service} reads the env file {path}, which is outside of the app directory"
+	)]
+	EnvFileOutsideApp { service: String, path: String },
+	#[error(
+		"The {key} entry {name} reads {path}, which is outside of the app directory"
+	)]
+	FileOutsideApp {
+		key: String,
+		name: String,
+		path: String,
+	},
+	#[error(
+		"include is not supported, every service has to be in the compose file"
+	)]
+	IncludeNotSupported,
+	#[error(
+		"Domain {domain} is already used by the router {router}{}",
+		app_id.as_ref().map(|a| format!(" of the app {a}")).unwrap_or_default()
+	)]
+	DomainConflict {
+		domain: String,
+		router: String,
+		app_id: Option<String>,
+	},
+	#[error("Compose file was rejected by docker compose: {}", errors.join(", "))]
+	Config { errors: Vec<String> },
 }
 
 impl From<serde_yaml::Error> for ComposeError {
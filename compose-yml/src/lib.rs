@@ -1,20 +1,44 @@
+mod de;
+pub mod diagnostics;
+pub mod domains;
 pub mod error;
+pub mod policy;
+pub mod reference;
+pub mod service;
+pub mod traefik_rule;
 
 pub use error::ComposeError;
+pub use policy::{PolicyAction, SecurityPolicy};
+pub use reference::ImageReference;
+pub use service::{
+	ComposeService, DeployStrategy, HostdinghyExtension, Labels,
+};
+pub use traefik_rule::TraefikRule;
 
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeMap, HashMap},
 	convert::Infallible,
 	str::FromStr,
-	sync::LazyLock,
 };
 
-use regex::Regex;
 use serde::Deserialize;
 
+use crate::de::{map_with_defaults, scalar_map};
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Compose {
 	pub services: HashMap<String, ComposeService>,
+	#[serde(default, deserialize_with = "map_with_defaults")]
+	pub volumes: BTreeMap<String, ComposeVolume>,
+	#[serde(default, deserialize_with = "map_with_defaults")]
+	pub networks: BTreeMap<String, ComposeNetwork>,
+	#[serde(default, deserialize_with = "map_with_defaults")]
+	pub secrets: BTreeMap<String, ComposeSecret>,
+	#[serde(default, deserialize_with = "map_with_defaults")]
+	pub configs: BTreeMap<String, ComposeConfig>,
+	/// Other compose files are not supported, they would bypass every check
+	#[serde(default)]
+	pub include: Vec<serde_yaml::Value>,
 }
 
 impl Compose {
@@ -23,84 +47,94 @@ impl Compose {
 		registry: &str,
 		app_id: &str,
 	) -> Result<(), ComposeError> {
+		if !self.include.is_empty() {
+			return Err(ComposeError::IncludeNotSupported);
+		}
+
 		for (name, service) in &self.services {
-			// validate the the image is correct
-			let image = service.parse_image();
-			image.validate_for(registry, app_id, name)?;
-
-			// make sure all traefik router names are correct
-			let valid_route = format!("{app_id}-{name}");
-			let mut parsed_names = service.traefik_router_names();
-			// remove the correct one so we have only unexpected ones left
-			parsed_names.remove(&valid_route);
-			if !parsed_names.is_empty() {
-				return Err(ComposeError::UnexpectedTraefikRouterName {
-					unexpected: parsed_names.into_iter().collect(),
-					expected: valid_route,
-				}
-				.into());
-			}
+			service.validate_for(registry, app_id, name)?;
 		}
 
 		Ok(())
 	}
 }
 
-impl FromStr for Compose {
-	type Err = ComposeError;
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ComposeVolume {
+	pub driver: Option<String>,
+	#[serde(default, deserialize_with = "scalar_map")]
+	pub driver_opts: BTreeMap<String, String>,
+	#[serde(default)]
+	pub external: bool,
+	pub name: Option<String>,
+	#[serde(default)]
+	pub labels: Labels,
+}
 
-	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		serde_yaml::from_str(s).map_err(Into::into)
-	}
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ComposeNetwork {
+	pub driver: Option<String>,
+	#[serde(default)]
+	pub external: bool,
+	pub name: Option<String>,
+	#[serde(default)]
+	pub internal: bool,
+	#[serde(default)]
+	pub labels: Labels,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct ComposeService {
-	pub image: String,
-	pub container_name: Option<String>,
+/// A top-level secret, only `file` reads from the host
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ComposeSecret {
+	pub file: Option<String>,
+	pub environment: Option<String>,
 	#[serde(default)]
-	pub labels: Vec<String>,
+	pub external: bool,
+	pub name: Option<String>,
 }
 
-static TRAEFIK_ROUTER_NAMES: LazyLock<Regex> = LazyLock::new(|| {
-	Regex::new(r"traefik\.http\.(?:routers|services)\.([^.]+)\.").unwrap()
-});
+/// A top-level config, only `file` reads from the host
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ComposeConfig {
+	pub file: Option<String>,
+	pub environment: Option<String>,
+	pub content: Option<String>,
+	#[serde(default)]
+	pub external: bool,
+	pub name: Option<String>,
+}
 
-impl ComposeService {
-	pub fn parse_image(&self) -> ComposeImage {
-		self.image.parse().unwrap()
-	}
+impl FromStr for Compose {
+	type Err = ComposeError;
 
-	pub fn traefik_router_names(&self) -> HashSet<String> {
-		self.labels
-			.iter()
-			.filter_map(|label| {
-				TRAEFIK_ROUTER_NAMES
-					.captures(label)
-					.map(|c| c[1].to_string())
-			})
-			.collect()
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		serde_yaml::from_str(s).map_err(Into::into)
 	}
 }
 
-const VALID_IMAGE_REGEX: LazyLock<Regex> =
-	LazyLock::new(|| Regex::new(r"^(.*?\..*?)/([^/]+)/([^/]+)$").unwrap());
-
 #[derive(Debug, Clone)]
 pub enum ComposeImage {
 	// ex: registry.example.com/appid/service:tag
 	Valid {
 		/// `registry/app_id/service
 		image: String,
+		/// The domain with the port
 		registry: String,
 		app_id: String,
 		service: String,
 		tag: Option<String>,
+		digest: Option<String>,
 	},
 	Unknown {
+		/// The reference without tag and digest
 		image: String,
+		/// The domain with the port
+		registry: Option<String>,
 		tag: Option<String>,
+		digest: Option<String>,
 	},
+	/// Not a valid image reference
+	Invalid { image: String, reason: String },
 }
 
 impl ComposeImage {
@@ -108,6 +142,7 @@ impl ComposeImage {
 		match self {
 			Self::Valid { image, .. } => image,
 			Self::Unknown { image, .. } => image,
+			Self::Invalid { image, .. } => image,
 		}
 	}
 
@@ -115,15 +150,31 @@ impl ComposeImage {
 		match self {
 			Self::Valid { tag, .. } => tag.as_deref(),
 			Self::Unknown { tag, .. } => tag.as_deref(),
+			Self::Invalid { .. } => None,
+		}
+	}
+
+	pub fn digest(&self) -> Option<&str> {
+		match self {
+			Self::Valid { digest, .. } => digest.as_deref(),
+			Self::Unknown { digest, .. } => digest.as_deref(),
+			Self::Invalid { .. } => None,
 		}
 	}
 
+	/// Images on the host of our registry need to be
+	/// `registry/app_id/service`, a different port on the same host is not
+	/// allowed either
 	pub fn validate_for(
 		&self,
 		registry: &str,
 		app_id: &str,
 		service: &str,
 	) -> Result<(), ComposeError> {
+		let (host, port) = split_registry(registry);
+		let same_host =
+			|reg: &str| split_registry(reg).0.eq_ignore_ascii_case(host);
+
 		match self {
 			ComposeImage::Valid {
 				image,
@@ -131,23 +182,33 @@ impl ComposeImage {
 				app_id: id,
 				service: name,
 				..
-			} if reg == registry => {
-				if id != app_id || name != service {
+			} if same_host(reg) => {
+				if split_registry(reg).1 != port
+					|| id != app_id || name != service
+				{
 					return Err(ComposeError::InvalidImage {
 						image: image.clone(),
 						expected: format!("{registry}/{app_id}/{service}"),
-					}
-					.into());
+					});
 				}
 			}
-			ComposeImage::Unknown { image, .. }
-				if image.starts_with(registry) =>
-			{
+			// for example a nested path in our registry
+			ComposeImage::Unknown {
+				image,
+				registry: Some(reg),
+				..
+			} if same_host(reg) => {
 				return Err(ComposeError::InvalidImage {
 					image: image.clone(),
 					expected: format!("{registry}/{app_id}/{service}"),
 				});
 			}
+			ComposeImage::Invalid { image, reason } => {
+				return Err(ComposeError::InvalidImageReference {
+					image: image.clone(),
+					reason: reason.clone(),
+				});
+			}
 			_ => {}
 		}
 
@@ -155,28 +216,59 @@ impl ComposeImage {
 	}
 }
 
+/// Returns true if both registries have the same host and port
+///
+/// No port is the same as 443, like docker does for https.
+pub fn same_registry(a: &str, b: &str) -> bool {
+	let (a_host, a_port) = split_registry(a);
+	let (b_host, b_port) = split_registry(b);
+
+	a_host.eq_ignore_ascii_case(b_host) && a_port == b_port
+}
+
+/// Splits the registry into the host and the port, the port defaults to 443
+fn split_registry(registry: &str) -> (&str, u16) {
+	// the colons of an ipv6 address are inside the brackets
+	let port = registry
+		.rsplit_once(':')
+		.filter(|(_, port)| !port.contains(']'))
+		.and_then(|(host, port)| Some((host, port.parse().ok()?)));
+
+	port.unwrap_or((registry, 443))
+}
+
 impl FromStr for ComposeImage {
 	type Err = Infallible;
 
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		// lets first split by last colon
-		let (image, tag) = s
-			.rsplit_once(':')
-			.map_or((s, None), |(i, t)| (i, Some(t.to_string())));
-
-		if let Some(caps) = VALID_IMAGE_REGEX.captures(image) {
-			Ok(Self::Valid {
-				image: image.to_string(),
-				registry: caps[1].to_string(),
-				app_id: caps[2].to_string(),
-				service: caps[3].to_string(),
-				tag,
-			})
-		} else {
-			Ok(Self::Unknown {
-				image: image.to_string(),
-				tag,
-			})
+		let reference = match reference::parse(s) {
+			Ok(r) => r,
+			Err(reason) => {
+				return Ok(Self::Invalid {
+					image: s.to_string(),
+					reason,
+				});
+			}
+		};
+
+		let image = reference.name();
+		let registry = reference.registry();
+
+		match (registry, &reference.path[..]) {
+			(Some(registry), [app_id, service]) => Ok(Self::Valid {
+				image,
+				registry,
+				app_id: app_id.clone(),
+				service: service.clone(),
+				tag: reference.tag,
+				digest: reference.digest,
+			}),
+			(registry, _) => Ok(Self::Unknown {
+				image,
+				registry,
+				tag: reference.tag,
+				digest: reference.digest,
+			}),
 		}
 	}
 }
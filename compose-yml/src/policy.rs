@@ -0,0 +1,625 @@
+/*!
+A security policy for the settings of a compose file which give a service
+access to the host.
+
+Every rule can either allow, warn about or deny the setting. Bind mounts and
+files like `env_file` are only checked lexically, symlinks inside the app
+directory are not followed.
+*/
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	Compose, ComposeError, ComposeService,
+	service::{ServiceVolume, VolumeType},
+};
+
+/// Security options which disable a confinement of the container
+const UNSAFE_SECURITY_OPTS: &[&str] = &[
+	"seccomp:unconfined",
+	"seccomp=unconfined",
+	"apparmor:unconfined",
+	"apparmor=unconfined",
+	"label:disable",
+	"label=disable",
+	"systempaths=unconfined",
+];
+
+#[derive(
+	Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyAction {
+	Allow,
+	Warn,
+	#[default]
+	Deny,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct SecurityPolicy {
+	/// `privileged: true`
+	pub privileged: PolicyAction,
+	/// `network_mode`, `pid`, `ipc`, `uts`, `cgroup` or `userns_mode` set to
+	/// `host`
+	pub host_namespaces: PolicyAction,
+	/// `network_mode`, `pid`, `ipc` or `volumes_from` referencing a container
+	/// which is not part of the app
+	pub foreign_containers: PolicyAction,
+	/// Capabilities in `cap_add` which are not in `allowed_capabilities`
+	pub capabilities: PolicyAction,
+	pub allowed_capabilities: Vec<String>,
+	/// Any entry in `devices`
+	pub devices: PolicyAction,
+	/// A `security_opt` which disables seccomp, apparmor or selinux
+	pub security_opt: PolicyAction,
+	/// Bind mounts, env files and the files of secrets and configs outside of
+	/// the app directory and outside of `allowed_bind_mounts`
+	pub bind_mounts: PolicyAction,
+	pub allowed_bind_mounts: Vec<PathBuf>,
+}
+
+impl Default for SecurityPolicy {
+	fn default() -> Self {
+		Self {
+			privileged: PolicyAction::Deny,
+			host_namespaces: PolicyAction::Deny,
+			foreign_containers: PolicyAction::Deny,
+			capabilities: PolicyAction::Deny,
+			allowed_capabilities: vec!["NET_BIND_SERVICE".into()],
+			devices: PolicyAction::Deny,
+			security_opt: PolicyAction::Deny,
+			bind_mounts: PolicyAction::Deny,
+			allowed_bind_mounts: vec![],
+		}
+	}
+}
+
+impl SecurityPolicy {
+	fn capability_allowed(&self, cap: &str) -> bool {
+		let cap = cap.trim_start_matches("CAP_");
+		self.allowed_capabilities
+			.iter()
+			.any(|c| c.trim_start_matches("CAP_").eq_ignore_ascii_case(cap))
+	}
+
+	fn bind_allowed(&self, app_dir: &Path, source: &str) -> bool {
+		let Some(path) = resolve(app_dir, source) else {
+			return false;
+		};
+
+		path.starts_with(app_dir)
+			|| self.allowed_bind_mounts.iter().any(|p| path.starts_with(p))
+	}
+
+	/// Returns the violations of a single service
+	fn service_violations(
+		&self,
+		compose: &Compose,
+		app_dir: &Path,
+		name: &str,
+		service: &ComposeService,
+	) -> Vec<(PolicyAction, ComposeError)> {
+		let mut violations = vec![];
+		let service_name = || name.to_string();
+
+		if service.privileged {
+			violations.push((
+				self.privileged,
+				ComposeError::PrivilegedService {
+					service: service_name(),
+				},
+			));
+		}
+
+		let namespaces = [
+			("network_mode", &service.network_mode),
+			("pid", &service.pid),
+			("ipc", &service.ipc),
+			("uts", &service.uts),
+			("cgroup", &service.cgroup),
+			("userns_mode", &service.userns_mode),
+		];
+		for (namespace, mode) in namespaces {
+			let Some(mode) = mode.as_deref() else {
+				continue;
+			};
+
+			if mode == "host" {
+				violations.push((
+					self.host_namespaces,
+					ComposeError::HostNamespace {
+						service: service_name(),
+						namespace: namespace.into(),
+					},
+				));
+			} else if let Some(container) = foreign_container(compose, mode) {
+				violations.push((
+					self.foreign_containers,
+					ComposeError::ForeignContainer {
+						service: service_name(),
+						key: namespace.into(),
+						container: container.into(),
+					},
+				));
+			}
+		}
+
+		for volumes_from in &service.volumes_from {
+			// the mode is optional and separated by a colon
+			let source = volumes_from
+				.strip_suffix(":ro")
+				.or_else(|| volumes_from.strip_suffix(":rw"))
+				.unwrap_or(volumes_from);
+			let reference = if source.starts_with("container:") {
+				source.to_string()
+			} else {
+				format!("service:{source}")
+			};
+
+			if let Some(container) = foreign_container(compose, &reference) {
+				violations.push((
+					self.foreign_containers,
+					ComposeError::ForeignContainer {
+						service: service_name(),
+						key: "volumes_from".into(),
+						container: container.into(),
+					},
+				));
+			}
+		}
+
+		for cap in &service.cap_add {
+			if !self.capability_allowed(cap) {
+				violations.push((
+					self.capabilities,
+					ComposeError::CapabilityAdded {
+						service: service_name(),
+						capability: cap.clone(),
+					},
+				));
+			}
+		}
+
+		for device in &service.devices {
+			violations.push((
+				self.devices,
+				ComposeError::DeviceMapped {
+					service: service_name(),
+					device: device.source.clone(),
+				},
+			));
+		}
+
+		for opt in &service.security_opt {
+			if UNSAFE_SECURITY_OPTS.contains(&opt.as_str()) {
+				violations.push((
+					self.security_opt,
+					ComposeError::UnsafeSecurityOpt {
+						service: service_name(),
+						option: opt.clone(),
+					},
+				));
+			}
+		}
+
+		for volume in &service.volumes {
+			let Some(source) = bind_source(compose, volume) else {
+				continue;
+			};
+
+			if !self.bind_allowed(app_dir, source) {
+				violations.push((
+					self.bind_mounts,
+					ComposeError::BindMountOutsideApp {
+						service: service_name(),
+						path: source.to_string(),
+					},
+				));
+			}
+		}
+
+		for env_file in &service.env_file {
+			if !self.bind_allowed(app_dir, &env_file.path) {
+				violations.push((
+					self.bind_mounts,
+					ComposeError::EnvFileOutsideApp {
+						service: service_name(),
+						path: env_file.path.clone(),
+					},
+				));
+			}
+		}
+
+		violations
+	}
+}
+
+/// Returns the container if `container:name` or `service:name` does not
+/// reference a service of the app
+///
+/// Containers created by compose are not known before they are created, so
+/// only a `container_name` of a service counts as part of the app.
+fn foreign_container<'a>(
+	compose: &Compose,
+	reference: &'a str,
+) -> Option<&'a str> {
+	if let Some(service) = reference.strip_prefix("service:") {
+		return (!compose.services.contains_key(service)).then_some(service);
+	}
+
+	let container = reference.strip_prefix("container:")?;
+	let in_app = compose
+		.services
+		.values()
+		.any(|s| s.container_name.as_deref() == Some(container));
+
+	(!in_app).then_some(container)
+}
+
+/// Returns the host path of a bind mount, a named volume can also be a bind
+/// mount through the options of the local driver
+fn bind_source<'a>(
+	compose: &'a Compose,
+	volume: &'a ServiceVolume,
+) -> Option<&'a str> {
+	let source = volume.source.as_deref()?;
+
+	match volume.kind {
+		VolumeType::Bind => Some(source),
+		VolumeType::Volume => {
+			let volume = compose.volumes.get(source)?;
+			let is_bind = volume
+				.driver_opts
+				.get("o")
+				.is_some_and(|o| o.split(',').any(|o| o == "bind"));
+
+			is_bind
+				.then(|| volume.driver_opts.get("device").map(String::as_str))
+				.flatten()
+		}
+		_ => None,
+	}
+}
+
+/// Resolves the path without touching the filesystem
+///
+/// Returns None if the path cannot be known before compose interpolates
+/// or expands it.
+fn resolve(app_dir: &Path, source: &str) -> Option<PathBuf> {
+	if source.starts_with('~') || source.contains('$') {
+		return None;
+	}
+
+	let mut resolved = PathBuf::new();
+	for component in app_dir.join(source).components() {
+		match component {
+			Component::ParentDir => {
+				resolved.pop();
+			}
+			Component::CurDir => {}
+			c => resolved.push(c),
+		}
+	}
+
+	Some(resolved)
+}
+
+impl Compose {
+	/// Checks the compose file against the policy
+	///
+	/// Returns the first violation which is denied or all violations which
+	/// should be warned about.
+	pub fn check_policy(
+		&self,
+		policy: &SecurityPolicy,
+		app_dir: impl AsRef<Path>,
+	) -> Result<Vec<ComposeError>, ComposeError> {
+		let app_dir = app_dir.as_ref();
+		let files = self
+			.file_violations(policy, app_dir)
+			.into_iter()
+			.map(|(_, _, action, error)| (action, error));
+		let services = self
+			.policy_violations(policy, app_dir)
+			.into_iter()
+			.map(|(_, action, error)| (action, error));
+
+		let mut warnings = vec![];
+		for (action, error) in services.chain(files) {
+			match action {
+				PolicyAction::Allow => {}
+				PolicyAction::Warn => warnings.push(error),
+				PolicyAction::Deny => return Err(error),
+			}
+		}
+
+		Ok(warnings)
+	}
+
+	/// Returns every violation together with the name of the service, sorted
+	/// by the service so the result is always the same
+	pub(crate) fn policy_violations(
+		&self,
+		policy: &SecurityPolicy,
+		app_dir: impl AsRef<Path>,
+	) -> Vec<(String, PolicyAction, ComposeError)> {
+		let app_dir = app_dir.as_ref();
+
+		let mut services = self.services.iter().collect::<Vec<_>>();
+		services.sort_by_key(|(name, _)| *name);
+
+		services
+			.into_iter()
+			.flat_map(|(name, service)| {
+				policy
+					.service_violations(self, app_dir, name, service)
+					.into_iter()
+					.map(|(action, error)| (name.clone(), action, error))
+			})
+			.collect()
+	}
+
+	/// Returns the top-level secrets and configs which read a file outside
+	/// of the app directory, together with their key and name
+	pub(crate) fn file_violations(
+		&self,
+		policy: &SecurityPolicy,
+		app_dir: impl AsRef<Path>,
+	) -> Vec<(String, String, PolicyAction, ComposeError)> {
+		let app_dir = app_dir.as_ref();
+
+		let secrets = self
+			.secrets
+			.iter()
+			.map(|(name, s)| ("secrets", name, s.file.as_deref()));
+		let configs = self
+			.configs
+			.iter()
+			.map(|(name, c)| ("configs", name, c.file.as_deref()));
+
+		secrets
+			.chain(configs)
+			.filter_map(|(key, name, file)| Some((key, name, file?)))
+			.filter(|(_, _, file)| !policy.bind_allowed(app_dir, file))
+			.map(|(key, name, file)| {
+				(
+					key.to_string(),
+					name.clone(),
+					policy.bind_mounts,
+					ComposeError::FileOutsideApp {
+						key: key.into(),
+						name: name.clone(),
+						path: file.into(),
+					},
+				)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn violations(compose: &str) -> Vec<ComposeError> {
+		let compose: Compose = compose.parse().unwrap();
+		compose
+			.policy_violations(&SecurityPolicy::default(), "/apps/myapp")
+			.into_iter()
+			.map(|(_, _, e)| e)
+			.collect()
+	}
+
+	#[test]
+	fn host_uts_and_cgroup() {
+		let errors = violations(
+			"services:
+  web:
+    image: nginx
+    uts: host
+    cgroup: host
+    sysctls:
+      - net.core.somaxconn=1024
+",
+		);
+
+		let namespaces = errors
+			.iter()
+			.map(|e| match e {
+				ComposeError::HostNamespace { namespace, .. } => namespace,
+				e => panic!("unexpected {e}"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(namespaces, ["uts", "cgroup"]);
+	}
+
+	#[test]
+	fn foreign_containers() {
+		let errors = violations(
+			"services:
+  web:
+    image: nginx
+    network_mode: container:other-app-db
+    pid: service:db
+    ipc: container:myapp-db
+    volumes_from:
+      - db:ro
+      - container:other-app-db:rw
+      - missing
+  db:
+    image: postgres
+    container_name: myapp-db
+",
+		);
+
+		let foreign = errors
+			.iter()
+			.map(|e| match e {
+				ComposeError::ForeignContainer { key, container, .. } => {
+					(key.as_str(), container.as_str())
+				}
+				e => panic!("unexpected {e}"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			foreign,
+			[
+				("network_mode", "other-app-db"),
+				("volumes_from", "other-app-db"),
+				("volumes_from", "missing"),
+			]
+		);
+	}
+
+	#[test]
+	fn privileged_capabilities_and_devices() {
+		let errors = violations(
+			"services:
+  web:
+    image: nginx
+    privileged: true
+    cap_add:
+      - NET_BIND_SERVICE
+      - CAP_SYS_ADMIN
+    devices:
+      - /dev/fuse:/dev/fuse
+",
+		);
+
+		assert_eq!(errors.len(), 3);
+		assert!(matches!(errors[0], ComposeError::PrivilegedService { .. }));
+		assert!(matches!(
+			&errors[1],
+			ComposeError::CapabilityAdded { capability, .. }
+				if capability == "CAP_SYS_ADMIN"
+		));
+		assert!(matches!(
+			&errors[2],
+			ComposeError::DeviceMapped { device, .. } if device == "/dev/fuse"
+		));
+	}
+
+	#[test]
+	fn bind_mounts() {
+		let errors = violations(
+			"services:
+  web:
+    image: nginx
+    volumes:
+      - ./data:/data
+      - data:/var/lib/data
+      - /apps/myapp/uploads:/uploads
+      - /var/run/docker.sock:/var/run/docker.sock
+      - /:/host
+      - ../other-app:/other
+      - ./data/../../other-app/secrets:/secrets
+volumes:
+  data:
+",
+		);
+
+		let paths = errors
+			.iter()
+			.map(|e| match e {
+				ComposeError::BindMountOutsideApp { path, .. } => path,
+				e => panic!("unexpected {e}"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			paths,
+			[
+				"/var/run/docker.sock",
+				"/",
+				"../other-app",
+				"./data/../../other-app/secrets",
+			]
+		);
+	}
+
+	#[test]
+	fn env_files() {
+		let errors = violations(
+			"services:
+  web:
+    image: nginx
+    env_file:
+      - .env
+      - /root/.hostdinghy/config.toml
+      - path: ../other-app/.env
+        required: false
+",
+		);
+
+		let paths = errors
+			.iter()
+			.map(|e| match e {
+				ComposeError::EnvFileOutsideApp { path, .. } => path,
+				e => panic!("unexpected {e}"),
+			})
+			.collect::<Vec<_>>();
+		assert_eq!(
+			paths,
+			["/root/.hostdinghy/config.toml", "../other-app/.env"]
+		);
+	}
+
+	#[test]
+	fn secret_and_config_files() {
+		let compose: Compose = "services:
+  web:
+    image: nginx
+secrets:
+  token:
+    file: ./token.txt
+  config:
+    file: /root/.hostdinghy/config.toml
+  env:
+    environment: TOKEN
+configs:
+  nginx:
+    file: ../other-app/nginx.conf
+"
+		.parse()
+		.unwrap();
+
+		let policy = SecurityPolicy::default();
+		let files = compose
+			.file_violations(&policy, "/apps/myapp")
+			.into_iter()
+			.map(|(key, name, _, _)| (key, name))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			files,
+			[
+				("secrets".to_string(), "config".to_string()),
+				("configs".to_string(), "nginx".to_string()),
+			]
+		);
+
+		assert!(matches!(
+			compose.check_policy(&policy, "/apps/myapp"),
+			Err(ComposeError::FileOutsideApp { .. })
+		));
+	}
+
+	#[test]
+	fn include_is_rejected() {
+		let compose: Compose = "include:
+  - ../other-app/compose.yml
+services:
+  web:
+    image: nginx
+"
+		.parse()
+		.unwrap();
+
+		assert!(matches!(
+			compose.validate_for("registry.local", "myapp"),
+			Err(ComposeError::IncludeNotSupported)
+		));
+	}
+}
@@ -0,0 +1,384 @@
+/*!
+Image references as defined by the distribution spec.
+
+```text
+reference   := name [ ":" tag ] [ "@" digest ]
+name        := [ domain "/" ] path-component [ "/" path-component ]*
+domain      := host [ ":" port ]
+```
+
+Like docker, the first component is only treated as domain if it contains a
+`.` or a `:`, is `localhost` or contains an uppercase letter. Otherwise the
+image is on the docker hub.
+*/
+
+use std::{fmt, str::FromStr, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::ComposeError;
+
+const NAME_MAX_LEN: usize = 255;
+
+static DOMAIN_COMPONENT: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"^(?:[a-zA-Z0-9]|[a-zA-Z0-9][a-zA-Z0-9-]*[a-zA-Z0-9])$")
+		.unwrap()
+});
+
+static IPV6: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r"^\[[a-fA-F0-9:]+\]$").unwrap());
+
+static PATH_COMPONENT: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"^[a-z0-9]+(?:(?:[._]|__|-+)[a-z0-9]+)*$").unwrap()
+});
+
+static TAG: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r"^\w[\w.-]{0,127}$").unwrap());
+
+static DIGEST: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"^[a-z0-9]+(?:[.+_-][a-z0-9]+)*:[a-fA-F0-9]{32,}$").unwrap()
+});
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageReference {
+	/// The host of the registry, None for the docker hub
+	pub domain: Option<String>,
+	pub port: Option<u16>,
+	/// Never empty
+	pub path: Vec<String>,
+	pub tag: Option<String>,
+	/// For example `sha256:<hex>`
+	pub digest: Option<String>,
+}
+
+impl ImageReference {
+	/// Returns the domain with the port
+	pub fn registry(&self) -> Option<String> {
+		let domain = self.domain.as_ref()?;
+
+		Some(match self.port {
+			Some(port) => format!("{domain}:{port}"),
+			None => domain.clone(),
+		})
+	}
+
+	/// Returns the reference without tag and digest
+	pub fn name(&self) -> String {
+		let path = self.path.join("/");
+
+		match self.registry() {
+			Some(registry) => format!("{registry}/{path}"),
+			None => path,
+		}
+	}
+}
+
+fn is_domain(component: &str) -> bool {
+	component.contains(['.', ':'])
+		|| component == "localhost"
+		|| component.starts_with('[')
+		|| component.chars().any(|c| c.is_ascii_uppercase())
+}
+
+/// Parses `host[:port]`
+fn parse_domain(s: &str) -> Result<(String, Option<u16>), String> {
+	// the port is behind the last colon, unless the colon is part of an ipv6
+	let (host, port) = match s.rsplit_once(':') {
+		Some((host, port)) if !port.ends_with(']') => (host, Some(port)),
+		_ => (s, None),
+	};
+
+	let valid_host = IPV6.is_match(host)
+		|| host.split('.').all(|c| DOMAIN_COMPONENT.is_match(c));
+	if !valid_host {
+		return Err(format!("invalid domain {host}"));
+	}
+
+	let port = port
+		.map(|p| {
+			// u16 parsing would also accept a leading +
+			if p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()) {
+				return Err(format!("invalid port {p}"));
+			}
+
+			p.parse::<u16>().map_err(|_| format!("invalid port {p}"))
+		})
+		.transpose()?;
+
+	Ok((host.to_string(), port))
+}
+
+pub(crate) fn parse(s: &str) -> Result<ImageReference, String> {
+	let (rest, digest) = match s.split_once('@') {
+		Some((rest, digest)) => {
+			if !DIGEST.is_match(digest) {
+				return Err(format!("invalid digest {digest}"));
+			}
+			if let Some(hex) = digest.strip_prefix("sha256:")
+				&& hex.len() != 64
+			{
+				return Err(format!("invalid digest {digest}"));
+			}
+
+			(rest, Some(digest.to_string()))
+		}
+		None => (s, None),
+	};
+
+	// the tag is after the last colon, as long as it is not part of the
+	// domain
+	let last_slash = rest.rfind('/').map_or(0, |i| i + 1);
+	let (name, tag) = match rest[last_slash..].rfind(':') {
+		Some(i) => {
+			let i = last_slash + i;
+			let tag = &rest[i + 1..];
+			if !TAG.is_match(tag) {
+				return Err(format!("invalid tag {tag}"));
+			}
+
+			(&rest[..i], Some(tag.to_string()))
+		}
+		None => (rest, None),
+	};
+
+	if name.is_empty() {
+		return Err("the name is empty".into());
+	}
+	if name.len() > NAME_MAX_LEN {
+		return Err(format!(
+			"the name is longer than {NAME_MAX_LEN} characters"
+		));
+	}
+
+	let (domain, path) = match name.split_once('/') {
+		Some((first, path)) if is_domain(first) => {
+			let (host, port) = parse_domain(first)?;
+			(Some((host, port)), path)
+		}
+		_ => (None, name),
+	};
+
+	let path = path.split('/').map(str::to_string).collect::<Vec<_>>();
+	if let Some(c) = path.iter().find(|c| !PATH_COMPONENT.is_match(c)) {
+		return Err(format!("invalid path component {c:?}"));
+	}
+
+	let (domain, port) = domain.unzip();
+
+	Ok(ImageReference {
+		domain,
+		port: port.flatten(),
+		path,
+		tag,
+		digest,
+	})
+}
+
+impl FromStr for ImageReference {
+	type Err = ComposeError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse(s).map_err(|reason| ComposeError::InvalidImageReference {
+			image: s.to_string(),
+			reason,
+		})
+	}
+}
+
+impl fmt::Display for ImageReference {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.name())?;
+		if let Some(tag) = &self.tag {
+			write!(f, ":{tag}")?;
+		}
+		if let Some(digest) = &self.digest {
+			write!(f, "@{digest}")?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ComposeImage;
+
+	const SHA: &str = "sha256:0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+
+	fn reference(s: &str) -> ImageReference {
+		parse(s).unwrap_or_else(|e| panic!("{s} should be valid: {e}"))
+	}
+
+	#[test]
+	fn registry_with_port() {
+		let r = reference("registry.local:5000/a/b:1.0");
+		assert_eq!(r.domain.as_deref(), Some("registry.local"));
+		assert_eq!(r.port, Some(5000));
+		assert_eq!(r.path, ["a", "b"]);
+		assert_eq!(r.tag.as_deref(), Some("1.0"));
+		assert_eq!(r.registry().as_deref(), Some("registry.local:5000"));
+		assert_eq!(r.to_string(), "registry.local:5000/a/b:1.0");
+	}
+
+	#[test]
+	fn localhost_and_ipv6() {
+		let r = reference("localhost/x");
+		assert_eq!(r.domain.as_deref(), Some("localhost"));
+		assert_eq!(r.path, ["x"]);
+
+		let r = reference("[::1]:5000/x");
+		assert_eq!(r.domain.as_deref(), Some("[::1]"));
+		assert_eq!(r.port, Some(5000));
+		assert_eq!(r.path, ["x"]);
+
+		let r = reference("[::1]/x:latest");
+		assert_eq!(r.domain.as_deref(), Some("[::1]"));
+		assert_eq!(r.port, None);
+		assert_eq!(r.tag.as_deref(), Some("latest"));
+	}
+
+	#[test]
+	fn tag_and_digest() {
+		let r = reference(&format!("nginx:1.27@{SHA}"));
+		assert_eq!(r.tag.as_deref(), Some("1.27"));
+		assert_eq!(r.digest.as_deref(), Some(SHA));
+
+		let r = reference(&format!("registry.local:5000/a/b@{SHA}"));
+		assert_eq!(r.port, Some(5000));
+		assert_eq!(r.tag, None);
+		assert_eq!(r.digest.as_deref(), Some(SHA));
+	}
+
+	#[test]
+	fn docker_hub_and_nested_paths() {
+		let r = reference("nginx");
+		assert_eq!(r.domain, None);
+		assert_eq!(r.path, ["nginx"]);
+
+		let r = reference("library/postgres:17");
+		assert_eq!(r.domain, None);
+		assert_eq!(r.path, ["library", "postgres"]);
+
+		let r = reference("ghcr.io/org/team/image_name-2:v1");
+		assert_eq!(r.domain.as_deref(), Some("ghcr.io"));
+		assert_eq!(r.path, ["org", "team", "image_name-2"]);
+	}
+
+	#[test]
+	fn uppercase_domain_and_path() {
+		// an uppercase first component is a domain
+		let r = reference("Registry.Local/a/b");
+		assert_eq!(r.domain.as_deref(), Some("Registry.Local"));
+		let r = reference("Registry/a");
+		assert_eq!(r.domain.as_deref(), Some("Registry"));
+
+		// but the path needs to be lowercase
+		assert!(parse("registry.local/App/b").is_err());
+		assert!(parse("Nginx").is_err());
+	}
+
+	#[test]
+	fn invalid() {
+		assert!(parse("registry.local:99999/a/b").is_err());
+		assert!(parse("registry.local:+50/a/b").is_err());
+		assert!(parse("registry.local:/a/b").is_err());
+		assert!(parse(&format!("a/{}", "b".repeat(NAME_MAX_LEN))).is_err());
+		assert!(
+			parse("nginx@sha256:0123456789abcdef0123456789abcdef").is_err()
+		);
+		assert!(parse(&format!("nginx@{SHA}0")).is_err());
+		assert!(parse("nginx:-latest").is_err());
+		assert!(parse("").is_err());
+		assert!(parse("a//b").is_err());
+	}
+
+	#[test]
+	fn validate_compose_image() {
+		let registry = "registry.local:5000";
+		let validate = |image: &str| {
+			image
+				.parse::<ComposeImage>()
+				.unwrap()
+				.validate_for(registry, "myapp", "web")
+		};
+
+		let image = "registry.local:5000/myapp/web:1.0"
+			.parse::<ComposeImage>()
+			.unwrap();
+		assert!(matches!(image, ComposeImage::Valid { .. }));
+		assert!(validate("registry.local:5000/myapp/web:1.0").is_ok());
+		assert!(validate("REGISTRY.local:5000/myapp/web").is_ok());
+		assert!(matches!(
+			validate("registry.local:5000/other/web"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+		assert!(matches!(
+			validate("registry.local:5000/myapp/db"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+
+		// a nested path in our registry is not allowed
+		let image = "registry.local:5000/myapp/web/x"
+			.parse::<ComposeImage>()
+			.unwrap();
+		assert!(matches!(image, ComposeImage::Unknown { .. }));
+		assert!(matches!(
+			validate("registry.local:5000/myapp/web/x"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+
+		// images of other registries are not checked
+		let image = "nginx:latest".parse::<ComposeImage>().unwrap();
+		assert!(matches!(image, ComposeImage::Unknown { .. }));
+		assert!(validate("nginx:latest").is_ok());
+		assert!(validate("other.local:5000/myapp/web").is_ok());
+
+		// our host on another port is not our registry
+		assert!(matches!(
+			validate("registry.local/myapp/web"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+		assert!(matches!(
+			validate("registry.local:5001/myapp/web"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+		assert!(matches!(
+			validate("registry.local:5001/other/web/x"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+
+		// the default port is the same as none
+		let validate_default = |image: &str| {
+			image.parse::<ComposeImage>().unwrap().validate_for(
+				"registry.local",
+				"myapp",
+				"web",
+			)
+		};
+		assert!(validate_default("registry.local:443/myapp/web").is_ok());
+		assert!(validate_default("registry.local/myapp/web").is_ok());
+		assert!(matches!(
+			validate_default("registry.local:5000/myapp/web"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+
+		let validate_ipv6 = |image: &str| {
+			image.parse::<ComposeImage>().unwrap().validate_for(
+				"[::1]:5000",
+				"myapp",
+				"web",
+			)
+		};
+		assert!(validate_ipv6("[::1]:5000/myapp/web").is_ok());
+		assert!(matches!(
+			validate_ipv6("[::1]/myapp/web"),
+			Err(ComposeError::InvalidImage { .. })
+		));
+
+		assert!(matches!(
+			validate("registry.local:5000/MyApp/web"),
+			Err(ComposeError::InvalidImageReference { .. })
+		));
+	}
+}
@@ -0,0 +1,814 @@
+/*!
+The service definition of the compose spec.
+
+Wherever the spec allows a short and a long form (for example a list of
+`key=value` or a map for labels) both are accepted and normalized into the
+same type. Fields which are not modelled are ignored.
+*/
+
+use std::{
+	collections::{BTreeMap, HashSet},
+	ops::Deref,
+	sync::LazyLock,
+};
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{
+	ComposeError, ComposeImage, TraefikRule,
+	de::{ListOrMap, Scalar, names_or_map, one_or_many},
+};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ComposeService {
+	pub image: String,
+	pub container_name: Option<String>,
+	#[serde(default)]
+	pub labels: Labels,
+	#[serde(default)]
+	pub environment: Environment,
+	#[serde(default, deserialize_with = "one_or_many")]
+	pub env_file: Vec<EnvFile>,
+	#[serde(default)]
+	pub ports: Vec<Port>,
+	#[serde(default)]
+	pub volumes: Vec<ServiceVolume>,
+	#[serde(default, deserialize_with = "names_or_map")]
+	pub networks: BTreeMap<String, ServiceNetwork>,
+	#[serde(default, deserialize_with = "names_or_map")]
+	pub depends_on: BTreeMap<String, Dependency>,
+	pub healthcheck: Option<Healthcheck>,
+	pub command: Option<Command>,
+	pub entrypoint: Option<Command>,
+	pub restart: Option<String>,
+	pub user: Option<String>,
+	pub working_dir: Option<String>,
+	pub network_mode: Option<String>,
+	pub pid: Option<String>,
+	pub ipc: Option<String>,
+	pub uts: Option<String>,
+	pub cgroup: Option<String>,
+	pub userns_mode: Option<String>,
+	/// `service[:mode]` or `container:name[:mode]`
+	#[serde(default)]
+	pub volumes_from: Vec<String>,
+	#[serde(default)]
+	pub sysctls: Sysctls,
+	#[serde(default)]
+	pub privileged: bool,
+	#[serde(default)]
+	pub read_only: bool,
+	#[serde(default)]
+	pub cap_add: Vec<String>,
+	#[serde(default)]
+	pub cap_drop: Vec<String>,
+	#[serde(default)]
+	pub security_opt: Vec<String>,
+	#[serde(default)]
+	pub devices: Vec<Device>,
+	/// Settings only used by hostdinghy
+	#[serde(default, rename = "x-hostdinghy")]
+	pub hostdinghy: HostdinghyExtension,
+}
+
+static TRAEFIK_ROUTER_NAMES: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"^traefik\.http\.(?:routers|services)\.([^.]+)\.").unwrap()
+});
+
+static TRAEFIK_SERVICE_NAMES: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"^traefik\.http\.services\.([^.]+)\.").unwrap()
+});
+
+static TRAEFIK_ROUTER_RULES: LazyLock<Regex> = LazyLock::new(|| {
+	Regex::new(r"^traefik\.http\.routers\.([^.]+)\.rule$").unwrap()
+});
+
+impl ComposeService {
+	pub fn parse_image(&self) -> ComposeImage {
+		self.image.parse().unwrap()
+	}
+
+	pub fn traefik_router_names(&self) -> HashSet<String> {
+		self.labels
+			.keys()
+			.filter_map(|key| {
+				TRAEFIK_ROUTER_NAMES.captures(key).map(|c| c[1].to_string())
+			})
+			.collect()
+	}
+
+	/// Returns the first error of [`Self::errors_for`]
+	pub fn validate_for(
+		&self,
+		registry: &str,
+		app_id: &str,
+		name: &str,
+	) -> Result<(), ComposeError> {
+		match self.errors_for(registry, app_id, name).into_iter().next() {
+			Some(e) => Err(e),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns every error of the service
+	pub fn errors_for(
+		&self,
+		registry: &str,
+		app_id: &str,
+		name: &str,
+	) -> Vec<ComposeError> {
+		let mut errors = vec![];
+
+		// validate the the image is correct
+		let image = self.parse_image();
+		errors.extend(image.validate_for(registry, app_id, name).err());
+
+		if self.hostdinghy.deploy == DeployStrategy::StartFirst {
+			self.start_first_errors(app_id, name, &mut errors);
+		}
+
+		// make sure all traefik router names are correct
+		let valid_route = format!("{app_id}-{name}");
+		let mut parsed_names = self.traefik_router_names();
+		// remove the correct one so we have only unexpected ones left
+		parsed_names.remove(&valid_route);
+		if !parsed_names.is_empty() {
+			errors.push(ComposeError::UnexpectedTraefikRouterName {
+				unexpected: parsed_names.into_iter().collect(),
+				expected: valid_route,
+			});
+		}
+
+		for rule in self.traefik_router_rules().into_values() {
+			errors.extend(rule.parse::<TraefikRule>().err());
+		}
+
+		errors
+	}
+
+	/// The old and the new containers run at the same time
+	fn start_first_errors(
+		&self,
+		app_id: &str,
+		name: &str,
+		errors: &mut Vec<ComposeError>,
+	) {
+		// two containers cannot have the same name
+		if self.container_name.is_some() {
+			errors.push(ComposeError::StartFirstWithContainerName {
+				service: name.to_string(),
+			});
+		}
+
+		// or bind the same port on the host
+		for published in self.ports.iter().filter_map(|p| p.published.as_ref())
+		{
+			errors.push(ComposeError::StartFirstWithPublishedPort {
+				service: name.to_string(),
+				port: published.clone(),
+			});
+		}
+
+		// the deployment waits until traefik routes to the new containers,
+		// which needs the name of the traefik service. Without a label
+		// traefik would name it after the container
+		let expected = format!("{app_id}-{name}");
+		let has_service = self.labels.keys().any(|key| {
+			TRAEFIK_SERVICE_NAMES
+				.captures(key)
+				.is_some_and(|c| c[1] == expected)
+		});
+		if self.traefik_enabled() && !has_service {
+			errors.push(ComposeError::StartFirstWithoutTraefikService {
+				service: name.to_string(),
+				expected,
+			});
+		}
+	}
+
+	/// Returns the rule of every http router
+	pub fn traefik_router_rules(&self) -> BTreeMap<String, &str> {
+		self.labels
+			.iter()
+			.filter_map(|(key, rule)| {
+				let caps = TRAEFIK_ROUTER_RULES.captures(key)?;
+				Some((caps[1].to_string(), rule.as_str()))
+			})
+			.collect()
+	}
+
+	/// Returns true if the label `traefik.enable` is set to true
+	pub fn traefik_enabled(&self) -> bool {
+		self.labels
+			.get("traefik.enable")
+			.is_some_and(|v| v.eq_ignore_ascii_case("true"))
+	}
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct HostdinghyExtension {
+	#[serde(default)]
+	pub deploy: DeployStrategy,
+}
+
+/// How a service gets updated when a new image is deployed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeployStrategy {
+	/// The container gets stopped and recreated
+	#[default]
+	StopFirst,
+	/// The new container is started next to the old one, which only gets
+	/// removed once the new one is healthy and routed by traefik
+	StartFirst,
+}
+
+/// Labels written as a list of `key=value` or as a map
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Labels(pub BTreeMap<String, String>);
+
+impl Labels {
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.0.get(key).map(String::as_str)
+	}
+}
+
+impl Deref for Labels {
+	type Target = BTreeMap<String, String>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl<'de> Deserialize<'de> for Labels {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		let map = Option::<ListOrMap>::deserialize(d)?
+			.map(ListOrMap::into_map)
+			.unwrap_or_default();
+
+		// a label without a value is an empty string
+		Ok(Self(
+			map.into_iter()
+				.map(|(k, v)| (k, v.unwrap_or_default()))
+				.collect(),
+		))
+	}
+}
+
+/// Environment variables written as a list of `KEY=value` or as a map
+///
+/// A variable without a value is taken from the environment of docker
+/// compose.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment(pub BTreeMap<String, Option<String>>);
+
+impl Deref for Environment {
+	type Target = BTreeMap<String, Option<String>>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl<'de> Deserialize<'de> for Environment {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		Ok(Self(
+			Option::<ListOrMap>::deserialize(d)?
+				.map(ListOrMap::into_map)
+				.unwrap_or_default(),
+		))
+	}
+}
+
+/// Kernel parameters written as a list of `key=value` or as a map
+///
+/// Docker only accepts parameters which are namespaced, so they don't change
+/// the host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sysctls(pub BTreeMap<String, String>);
+
+impl Deref for Sysctls {
+	type Target = BTreeMap<String, String>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.0
+	}
+}
+
+impl<'de> Deserialize<'de> for Sysctls {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		let map = Option::<ListOrMap>::deserialize(d)?
+			.map(ListOrMap::into_map)
+			.unwrap_or_default();
+
+		Ok(Self(
+			map.into_iter()
+				.map(|(k, v)| (k, v.unwrap_or_default()))
+				.collect(),
+		))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvFile {
+	pub path: String,
+	pub required: bool,
+}
+
+impl<'de> Deserialize<'de> for EnvFile {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Raw {
+			Short(String),
+			Long {
+				path: String,
+				#[serde(default = "default_true")]
+				required: bool,
+			},
+		}
+
+		Ok(match Raw::deserialize(d)? {
+			Raw::Short(path) => Self {
+				path,
+				required: true,
+			},
+			Raw::Long { path, required } => Self { path, required },
+		})
+	}
+}
+
+fn default_true() -> bool {
+	true
+}
+
+/// A published port, from `[[host_ip:]published:]target[/protocol]` or the
+/// long syntax
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+	pub host_ip: Option<String>,
+	/// The port or range on the host, None if docker picks a random one
+	pub published: Option<String>,
+	/// The port or range inside the container
+	pub target: String,
+	pub protocol: Option<String>,
+}
+
+impl Port {
+	fn parse_short(s: &str) -> Result<Self, String> {
+		let (rest, protocol) = match s.rsplit_once('/') {
+			Some((rest, protocol)) => (rest, Some(protocol.to_string())),
+			None => (s, None),
+		};
+
+		let (host, target) = match rest.rsplit_once(':') {
+			Some((host, target)) => (Some(host), target),
+			None => (None, rest),
+		};
+
+		if target.is_empty() {
+			return Err(format!("invalid port {s}"));
+		}
+
+		let (host_ip, published) = match host {
+			Some(host) => match host.rsplit_once(':') {
+				Some((ip, published)) => (
+					Some(
+						ip.trim_start_matches('[')
+							.trim_end_matches(']')
+							.to_string(),
+					),
+					published,
+				),
+				None => (None, host),
+			},
+			None => (None, ""),
+		};
+
+		Ok(Self {
+			host_ip,
+			published: (!published.is_empty()).then(|| published.to_string()),
+			target: target.to_string(),
+			protocol,
+		})
+	}
+}
+
+impl<'de> Deserialize<'de> for Port {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Raw {
+			Short(Scalar),
+			Long {
+				target: Scalar,
+				published: Option<Scalar>,
+				host_ip: Option<String>,
+				protocol: Option<String>,
+			},
+		}
+
+		match Raw::deserialize(d)? {
+			Raw::Short(s) => {
+				Self::parse_short(&s.into_string()).map_err(de::Error::custom)
+			}
+			Raw::Long {
+				target,
+				published,
+				host_ip,
+				protocol,
+			} => Ok(Self {
+				host_ip,
+				published: published.map(Scalar::into_string),
+				target: target.into_string(),
+				protocol,
+			}),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VolumeType {
+	Bind,
+	Volume,
+	Tmpfs,
+	Npipe,
+	Cluster,
+	Image,
+}
+
+/// A mount of a service, from `[source:]target[:mode]` or the long syntax
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceVolume {
+	pub kind: VolumeType,
+	/// A host path for binds, the volume name for volumes and None for
+	/// anonymous volumes
+	pub source: Option<String>,
+	pub target: String,
+	pub read_only: bool,
+}
+
+impl ServiceVolume {
+	fn parse_short(s: &str) -> Result<Self, String> {
+		let parts = s.split(':').collect::<Vec<_>>();
+		let (source, target, mode) = match parts[..] {
+			[target] => (None, target, None),
+			[source, target] => (Some(source), target, None),
+			[source, target, mode] => (Some(source), target, Some(mode)),
+			_ => return Err(format!("invalid volume {s}")),
+		};
+
+		if target.is_empty() {
+			return Err(format!("invalid volume {s}"));
+		}
+
+		// a path is either absolute or relative, everything else is the
+		// name of a volume. A variable is interpolated by compose, so it
+		// could be any path
+		let kind = match source {
+			Some(s) if s.starts_with(['/', '.', '~']) || s.contains('$') => {
+				VolumeType::Bind
+			}
+			_ => VolumeType::Volume,
+		};
+
+		Ok(Self {
+			kind,
+			source: source.map(Into::into),
+			target: target.to_string(),
+			read_only: mode.is_some_and(|m| m.split(',').any(|m| m == "ro")),
+		})
+	}
+}
+
+impl<'de> Deserialize<'de> for ServiceVolume {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Raw {
+			Short(String),
+			Long {
+				#[serde(rename = "type")]
+				kind: VolumeType,
+				source: Option<String>,
+				target: String,
+				#[serde(default)]
+				read_only: bool,
+			},
+		}
+
+		match Raw::deserialize(d)? {
+			Raw::Short(s) => Self::parse_short(&s).map_err(de::Error::custom),
+			Raw::Long {
+				kind,
+				source,
+				target,
+				read_only,
+			} => Ok(Self {
+				kind,
+				source,
+				target,
+				read_only,
+			}),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ServiceNetwork {
+	#[serde(default)]
+	pub aliases: Vec<String>,
+	pub ipv4_address: Option<String>,
+	pub ipv6_address: Option<String>,
+	pub priority: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+	#[default]
+	ServiceStarted,
+	ServiceHealthy,
+	ServiceCompletedSuccessfully,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Dependency {
+	#[serde(default)]
+	pub condition: DependencyCondition,
+	#[serde(default)]
+	pub restart: bool,
+	#[serde(default = "default_true")]
+	pub required: bool,
+}
+
+impl Default for Dependency {
+	fn default() -> Self {
+		Self {
+			condition: DependencyCondition::default(),
+			restart: false,
+			required: true,
+		}
+	}
+}
+
+/// A command either run by a shell or directly
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Command {
+	Shell(String),
+	Exec(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Healthcheck {
+	/// Either a shell command or `["CMD", ...]`, `["CMD-SHELL", ...]` or
+	/// `["NONE"]`
+	pub test: Option<Command>,
+	/// Durations like `1m30s`
+	pub interval: Option<String>,
+	pub timeout: Option<String>,
+	pub start_period: Option<String>,
+	pub start_interval: Option<String>,
+	pub retries: Option<u64>,
+	#[serde(default)]
+	pub disable: bool,
+}
+
+/// A device mapping, from `host[:container[:permissions]]` or the long syntax
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+	pub source: String,
+	pub target: Option<String>,
+	pub permissions: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Device {
+	fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		#[serde(untagged)]
+		enum Raw {
+			Short(String),
+			Long {
+				source: String,
+				target: Option<String>,
+				permissions: Option<String>,
+			},
+		}
+
+		Ok(match Raw::deserialize(d)? {
+			Raw::Short(s) => {
+				let mut parts = s.splitn(3, ':').map(str::to_string);
+				Self {
+					source: parts.next().unwrap_or_default(),
+					target: parts.next(),
+					permissions: parts.next(),
+				}
+			}
+			Raw::Long {
+				source,
+				target,
+				permissions,
+			} => Self {
+				source,
+				target,
+				permissions,
+			},
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn service(yaml: &str) -> ComposeService {
+		serde_yaml::from_str(yaml).unwrap()
+	}
+
+	fn port(
+		host_ip: Option<&str>,
+		published: Option<&str>,
+		target: &str,
+	) -> Port {
+		Port {
+			host_ip: host_ip.map(Into::into),
+			published: published.map(Into::into),
+			target: target.into(),
+			protocol: None,
+		}
+	}
+
+	#[test]
+	fn labels_and_environment() {
+		let list = service(
+			"image: nginx
+labels:
+  - traefik.enable=true
+  - empty
+environment:
+  - A=1
+  - B=a=b
+  - FROM_HOST
+",
+		);
+		let map = service(
+			"image: nginx
+labels:
+  traefik.enable: true
+  empty:
+environment:
+  A: 1
+  B: a=b
+  FROM_HOST:
+",
+		);
+
+		for s in [&list, &map] {
+			assert_eq!(s.labels.get("traefik.enable"), Some("true"));
+			assert_eq!(s.labels.get("empty"), Some(""));
+			assert!(s.traefik_enabled());
+
+			assert_eq!(s.environment["A"].as_deref(), Some("1"));
+			assert_eq!(s.environment["B"].as_deref(), Some("a=b"));
+			assert_eq!(s.environment["FROM_HOST"], None);
+		}
+	}
+
+	#[test]
+	fn short_ports() {
+		let parse = |s| Port::parse_short(s).unwrap();
+
+		assert_eq!(parse("80"), port(None, None, "80"));
+		assert_eq!(parse("8080:80"), port(None, Some("8080"), "80"));
+		assert_eq!(
+			parse("127.0.0.1:8080:80"),
+			port(Some("127.0.0.1"), Some("8080"), "80")
+		);
+		assert_eq!(parse("127.0.0.1::80"), port(Some("127.0.0.1"), None, "80"));
+		assert_eq!(
+			parse("[::1]:8080:80"),
+			port(Some("::1"), Some("8080"), "80")
+		);
+		assert_eq!(
+			parse("9000-9010:9000-9010"),
+			port(None, Some("9000-9010"), "9000-9010")
+		);
+
+		let udp = parse("53:53/udp");
+		assert_eq!(udp.protocol.as_deref(), Some("udp"));
+		assert_eq!(udp.target, "53");
+
+		assert!(Port::parse_short("8080:").is_err());
+	}
+
+	#[test]
+	fn long_and_numeric_ports() {
+		let s = service(
+			"image: nginx
+ports:
+  - 80
+  - target: 443
+    published: 8443
+    host_ip: \"::1\"
+    protocol: tcp
+  - target: 8080
+",
+		);
+
+		assert_eq!(s.ports[0], port(None, None, "80"));
+		assert_eq!(
+			s.ports[1],
+			Port {
+				protocol: Some("tcp".into()),
+				..port(Some("::1"), Some("8443"), "443")
+			}
+		);
+		assert_eq!(s.ports[2], port(None, None, "8080"));
+	}
+
+	#[test]
+	fn short_volumes() {
+		let parse = |s| ServiceVolume::parse_short(s).unwrap();
+
+		let v = parse("/data");
+		assert_eq!((v.kind, v.source), (VolumeType::Volume, None));
+
+		let v = parse("./data:/data:ro");
+		assert_eq!(v.kind, VolumeType::Bind);
+		assert_eq!(v.source.as_deref(), Some("./data"));
+		assert!(v.read_only);
+
+		let v = parse("/srv/data:/data:rw,z");
+		assert_eq!(v.kind, VolumeType::Bind);
+		assert!(!v.read_only);
+
+		assert_eq!(parse("~/data:/data").kind, VolumeType::Bind);
+		assert_eq!(parse("${DATA_DIR}:/data").kind, VolumeType::Bind);
+		assert_eq!(parse("$HOME/data:/data").kind, VolumeType::Bind);
+
+		let v = parse("db-data:/var/lib/postgresql/data");
+		assert_eq!(v.kind, VolumeType::Volume);
+		assert_eq!(v.source.as_deref(), Some("db-data"));
+
+		assert!(ServiceVolume::parse_short("a:b:c:d").is_err());
+		assert!(ServiceVolume::parse_short("data:").is_err());
+	}
+
+	#[test]
+	fn long_volumes() {
+		let s = service(
+			"image: nginx
+volumes:
+  - type: bind
+    source: ./config
+    target: /etc/nginx
+    read_only: true
+  - type: volume
+    source: data
+    target: /data
+  - type: tmpfs
+    target: /tmp
+",
+		);
+
+		assert_eq!(s.volumes[0].kind, VolumeType::Bind);
+		assert_eq!(s.volumes[0].source.as_deref(), Some("./config"));
+		assert!(s.volumes[0].read_only);
+		assert_eq!(s.volumes[1].kind, VolumeType::Volume);
+		assert_eq!(s.volumes[2].kind, VolumeType::Tmpfs);
+		assert_eq!(s.volumes[2].source, None);
+	}
+
+	#[test]
+	fn depends_on_list_and_map() {
+		let list = service(
+			"image: nginx
+depends_on:
+  - db
+",
+		);
+		assert_eq!(list.depends_on["db"], Dependency::default());
+
+		let map = service(
+			"image: nginx
+depends_on:
+  db:
+    condition: service_healthy
+    restart: true
+  cache:
+",
+		);
+		assert_eq!(
+			map.depends_on["db"].condition,
+			DependencyCondition::ServiceHealthy
+		);
+		assert!(map.depends_on["db"].restart);
+		assert!(map.depends_on["db"].required);
+		assert_eq!(map.depends_on["cache"], Dependency::default());
+	}
+}
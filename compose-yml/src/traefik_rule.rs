@@ -0,0 +1,461 @@
+/*!
+Parser for the rule syntax of traefik v3 routers.
+
+```text
+or      := and ( "||" and )*
+and     := unary ( "&&" unary )*
+unary   := "!" unary | "(" or ")" | matcher
+matcher := name "(" string ( "," string )* ")"
+```
+
+Strings are quoted with backticks or double quotes.
+*/
+
+use std::{
+	fmt,
+	iter::Peekable,
+	str::{CharIndices, FromStr},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ComposeError;
+
+/// The matchers traefik knows with the allowed number of arguments
+const MATCHERS: &[(&str, usize, usize)] = &[
+	("Host", 1, 1),
+	("HostRegexp", 1, 1),
+	("Path", 1, 1),
+	("PathPrefix", 1, 1),
+	("PathRegexp", 1, 1),
+	("Method", 1, 1),
+	("Header", 2, 2),
+	("HeaderRegexp", 2, 2),
+	("Query", 1, 2),
+	("QueryRegexp", 1, 2),
+	("ClientIP", 1, 1),
+	("HostSNI", 1, 1),
+	("HostSNIRegexp", 1, 1),
+	("ALPN", 1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TraefikRule {
+	/// For example `Host(`example.com`)`
+	Matcher {
+		name: String,
+		args: Vec<String>,
+	},
+	Not {
+		rule: Box<TraefikRule>,
+	},
+	And {
+		rules: Vec<TraefikRule>,
+	},
+	Or {
+		rules: Vec<TraefikRule>,
+	},
+}
+
+impl TraefikRule {
+	/// Calls `f` with every matcher which is not negated
+	fn visit_positive<'a>(&'a self, f: &mut impl FnMut(&'a str, &'a [String])) {
+		match self {
+			Self::Matcher { name, args } => f(name, args),
+			Self::Not { .. } => {}
+			Self::And { rules } | Self::Or { rules } => {
+				for rule in rules {
+					rule.visit_positive(f);
+				}
+			}
+		}
+	}
+
+	/// Returns the first argument of every positive matcher with the name
+	fn positive_args(&self, matcher: &str) -> Vec<String> {
+		let mut values = vec![];
+		self.visit_positive(&mut |name, args| {
+			if name == matcher && !values.contains(&args[0]) {
+				values.push(args[0].clone());
+			}
+		});
+
+		values
+	}
+
+	/// Returns the hosts of all `Host` matchers which are not negated
+	pub fn hosts(&self) -> Vec<String> {
+		self.positive_args("Host")
+	}
+
+	/// Returns the regexes of all `HostRegexp` matchers which are not negated
+	pub fn host_regexps(&self) -> Vec<String> {
+		self.positive_args("HostRegexp")
+	}
+
+	/// Returns the paths of all `Path` and `PathPrefix` matchers which are
+	/// not negated
+	pub fn paths(&self) -> Vec<String> {
+		let mut paths = self.positive_args("Path");
+		for prefix in self.positive_args("PathPrefix") {
+			if !paths.contains(&prefix) {
+				paths.push(prefix);
+			}
+		}
+
+		paths
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	Name(String),
+	Str(String),
+	Open,
+	Close,
+	Comma,
+	And,
+	Or,
+	Not,
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Name(n) => f.write_str(n),
+			Self::Str(s) => write!(f, "`{s}`"),
+			Self::Open => f.write_str("("),
+			Self::Close => f.write_str(")"),
+			Self::Comma => f.write_str(","),
+			Self::And => f.write_str("&&"),
+			Self::Or => f.write_str("||"),
+			Self::Not => f.write_str("!"),
+		}
+	}
+}
+
+struct Lexer<'a> {
+	src: &'a str,
+	chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+	fn new(src: &'a str) -> Self {
+		Self {
+			src,
+			chars: src.char_indices().peekable(),
+		}
+	}
+
+	fn expect(&mut self, c: char) -> Result<(), String> {
+		match self.chars.next() {
+			Some((_, n)) if n == c => Ok(()),
+			Some((i, n)) => Err(format!("expected {c} at {i} but found {n}")),
+			None => Err(format!("expected {c} but the rule ended")),
+		}
+	}
+
+	fn string(&mut self, quote: char, start: usize) -> Result<String, String> {
+		let mut s = String::new();
+		while let Some((_, c)) = self.chars.next() {
+			match c {
+				c if c == quote => return Ok(s),
+				'\\' if quote == '"' => match self.chars.next() {
+					Some((_, c)) => s.push(c),
+					None => break,
+				},
+				c => s.push(c),
+			}
+		}
+
+		Err(format!("string starting at {start} is not closed"))
+	}
+
+	fn tokens(mut self) -> Result<Vec<Token>, String> {
+		let mut tokens = vec![];
+
+		while let Some((i, c)) = self.chars.next() {
+			let token = match c {
+				c if c.is_whitespace() => continue,
+				'(' => Token::Open,
+				')' => Token::Close,
+				',' => Token::Comma,
+				'!' => Token::Not,
+				'&' => {
+					self.expect('&')?;
+					Token::And
+				}
+				'|' => {
+					self.expect('|')?;
+					Token::Or
+				}
+				'`' | '"' => Token::Str(self.string(c, i)?),
+				c if c.is_ascii_alphabetic() => {
+					let mut end = i + c.len_utf8();
+					while let Some((j, c)) =
+						self.chars.next_if(|(_, c)| c.is_ascii_alphanumeric())
+					{
+						end = j + c.len_utf8();
+					}
+					Token::Name(self.src[i..end].to_string())
+				}
+				c => return Err(format!("unexpected {c} at {i}")),
+			};
+			tokens.push(token);
+		}
+
+		Ok(tokens)
+	}
+}
+
+struct Parser {
+	tokens: Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+	fn next(&mut self, expected: &str) -> Result<Token, String> {
+		self.tokens
+			.next()
+			.ok_or_else(|| format!("expected {expected} but the rule ended"))
+	}
+
+	fn or(&mut self) -> Result<TraefikRule, String> {
+		let mut rules = vec![self.and()?];
+		while self.tokens.next_if_eq(&Token::Or).is_some() {
+			rules.push(self.and()?);
+		}
+
+		Ok(match rules.len() {
+			1 => rules.pop().unwrap(),
+			_ => TraefikRule::Or { rules },
+		})
+	}
+
+	fn and(&mut self) -> Result<TraefikRule, String> {
+		let mut rules = vec![self.unary()?];
+		while self.tokens.next_if_eq(&Token::And).is_some() {
+			rules.push(self.unary()?);
+		}
+
+		Ok(match rules.len() {
+			1 => rules.pop().unwrap(),
+			_ => TraefikRule::And { rules },
+		})
+	}
+
+	fn unary(&mut self) -> Result<TraefikRule, String> {
+		match self.next("a matcher")? {
+			Token::Not => Ok(TraefikRule::Not {
+				rule: Box::new(self.unary()?),
+			}),
+			Token::Open => {
+				let rule = self.or()?;
+				match self.next(")")? {
+					Token::Close => Ok(rule),
+					t => Err(format!("expected ) but found {t}")),
+				}
+			}
+			Token::Name(name) => self.matcher(name),
+			t => Err(format!("expected a matcher but found {t}")),
+		}
+	}
+
+	fn matcher(&mut self, name: String) -> Result<TraefikRule, String> {
+		let Some((_, min, max)) = MATCHERS.iter().find(|(n, ..)| *n == name)
+		else {
+			return Err(format!("unknown matcher {name}"));
+		};
+
+		match self.next("(")? {
+			Token::Open => {}
+			t => return Err(format!("expected ( but found {t}")),
+		}
+
+		let mut args = vec![];
+		loop {
+			match self.next("a string")? {
+				Token::Str(s) => args.push(s),
+				t => return Err(format!("expected a string but found {t}")),
+			}
+
+			match self.next(")")? {
+				Token::Comma => {}
+				Token::Close => break,
+				t => return Err(format!("expected ) but found {t}")),
+			}
+		}
+
+		if args.len() < *min || args.len() > *max {
+			let expected = match min == max {
+				true => min.to_string(),
+				false => format!("{min} to {max}"),
+			};
+			return Err(format!(
+				"{name} expects {expected} arguments but found {}",
+				args.len()
+			));
+		}
+
+		Ok(TraefikRule::Matcher { name, args })
+	}
+}
+
+fn parse(s: &str) -> Result<TraefikRule, String> {
+	let tokens = Lexer::new(s).tokens()?;
+	let mut parser = Parser {
+		tokens: tokens.into_iter().peekable(),
+	};
+
+	let rule = parser.or()?;
+	match parser.tokens.next() {
+		Some(t) => Err(format!("unexpected {t} after the rule")),
+		None => Ok(rule),
+	}
+}
+
+impl FromStr for TraefikRule {
+	type Err = ComposeError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		parse(s).map_err(|reason| ComposeError::InvalidTraefikRule {
+			rule: s.to_string(),
+			reason,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn matcher(name: &str, args: &[&str]) -> TraefikRule {
+		TraefikRule::Matcher {
+			name: name.into(),
+			args: args.iter().map(|a| a.to_string()).collect(),
+		}
+	}
+
+	fn rule(s: &str) -> TraefikRule {
+		s.parse().unwrap()
+	}
+
+	fn reason(s: &str) -> String {
+		match s.parse::<TraefikRule>() {
+			Err(ComposeError::InvalidTraefikRule { reason, .. }) => reason,
+			r => panic!("expected an invalid rule but got {r:?}"),
+		}
+	}
+
+	#[test]
+	fn and_binds_tighter_than_or() {
+		assert_eq!(
+			rule("Host(`a.com`) || Host(`b.com`) && PathPrefix(`/api`)"),
+			TraefikRule::Or {
+				rules: vec![
+					matcher("Host", &["a.com"]),
+					TraefikRule::And {
+						rules: vec![
+							matcher("Host", &["b.com"]),
+							matcher("PathPrefix", &["/api"]),
+						],
+					},
+				],
+			}
+		);
+	}
+
+	#[test]
+	fn not_and_parentheses() {
+		assert_eq!(
+			rule("(Host(`a.com`) || Host(`b.com`)) && !Path(`/admin`)"),
+			TraefikRule::And {
+				rules: vec![
+					TraefikRule::Or {
+						rules: vec![
+							matcher("Host", &["a.com"]),
+							matcher("Host", &["b.com"]),
+						],
+					},
+					TraefikRule::Not {
+						rule: Box::new(matcher("Path", &["/admin"])),
+					},
+				],
+			}
+		);
+
+		assert_eq!(
+			rule("!!(Method(`GET`))"),
+			TraefikRule::Not {
+				rule: Box::new(TraefikRule::Not {
+					rule: Box::new(matcher("Method", &["GET"])),
+				}),
+			}
+		);
+	}
+
+	#[test]
+	fn quoted_arguments() {
+		assert_eq!(
+			rule(r#"Header("X-Name", `a "b"`)"#),
+			matcher("Header", &["X-Name", "a \"b\""])
+		);
+		assert_eq!(
+			rule(r#"PathRegexp("^/a\"b\\c$")"#),
+			matcher("PathRegexp", &["^/a\"b\\c$"])
+		);
+		// backticks don't know escapes
+		assert_eq!(
+			rule(r"PathRegexp(`^/\d+$`)"),
+			matcher("PathRegexp", &[r"^/\d+$"])
+		);
+		assert_eq!(rule("Query(`a`)"), matcher("Query", &["a"]));
+		assert_eq!(rule("Query(`a`, `b`)"), matcher("Query", &["a", "b"]));
+	}
+
+	#[test]
+	fn invalid_rules() {
+		assert_eq!(reason("Host()"), "expected a string but found )");
+		assert_eq!(
+			reason("Host(`a.com`, `b.com`)"),
+			"Host expects 1 arguments but found 2"
+		);
+		assert_eq!(
+			reason("Header(`X-Name`)"),
+			"Header expects 2 arguments but found 1"
+		);
+		assert_eq!(
+			reason("Query(`a`, `b`, `c`)"),
+			"Query expects 1 to 2 arguments but found 3"
+		);
+		assert_eq!(reason("Domain(`a.com`)"), "unknown matcher Domain");
+		assert_eq!(reason("host(`a.com`)"), "unknown matcher host");
+		assert_eq!(reason("Host(`a.com`"), "expected ) but the rule ended");
+		assert_eq!(
+			reason("Host(`a.com)"),
+			"string starting at 5 is not closed"
+		);
+		assert_eq!(
+			reason("Host(`a.com`) & Path(`/`)"),
+			"expected & at 15 but found  "
+		);
+		assert_eq!(
+			reason("Host(`a.com`) Path(`/`)"),
+			"unexpected Path after the rule"
+		);
+		assert_eq!(reason(""), "expected a matcher but the rule ended");
+	}
+
+	#[test]
+	fn hosts_and_paths() {
+		let rule = rule(
+			"(Host(`a.com`) || Host(`b.com`) || HostRegexp(`^.+\\.c\\.com$`)) \
+			&& !Host(`d.com`) && (Path(`/api`) || PathPrefix(`/static`)) \
+			&& !PathPrefix(`/admin`) && Host(`a.com`)",
+		);
+
+		assert_eq!(rule.hosts(), ["a.com", "b.com"]);
+		assert_eq!(rule.host_regexps(), [r"^.+\.c\.com$"]);
+		assert_eq!(rule.paths(), ["/api", "/static"]);
+	}
+}
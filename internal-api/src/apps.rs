@@ -1,7 +1,16 @@
+use chuchi_postgres::time::DateTime;
 use serde::{Deserialize, Serialize};
 use serde_plain::derive_display_from_serialize;
 
 pub use crate::app_id::AppId;
+use crate::{
+	database_name::DatabaseName, error::ComposeError,
+	registry_username::RegistryUsername,
+};
+pub use compose_yml::{
+	TraefikRule,
+	diagnostics::{Diagnostic, DiagnosticSeverity},
+};
 
 /// A request to get information about the application.
 ///
@@ -26,6 +35,32 @@ pub struct AppService {
 	// human readable state
 	pub state_hr: String,
 	pub routes: Vec<ServiceRoute>,
+	/// None if the container has no healthcheck or does not exist
+	#[serde(default)]
+	pub health: Option<ServiceHealth>,
+	/// how many times docker restarted the container
+	#[serde(default)]
+	pub restart_count: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceHealth {
+	pub status: HealthStatus,
+	/// how many probes failed in a row
+	pub failing_streak: u64,
+	/// the output of the last probe
+	pub last_output: Option<String>,
+	pub last_exit_code: Option<i64>,
+	pub last_probe_on: Option<DateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HealthStatus {
+	Starting,
+	Healthy,
+	Unhealthy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,10 +83,27 @@ pub enum ServiceState {
 #[serde(rename_all = "camelCase")]
 pub struct ServiceRoute {
 	pub rule: String,
-	// a list of all domains aka Host(`example.com`) rules
-	// this needs to be a parsed manually, also all operands
-	// need to be considered
+	/// All hosts of `Host` matchers which are not negated
 	pub domains: Vec<String>,
+	/// All paths of `Path` and `PathPrefix` matchers which are not negated
+	#[serde(default)]
+	pub paths: Vec<String>,
+	/// None if the rule could not be parsed
+	#[serde(default)]
+	pub parsed: Option<TraefikRule>,
+}
+
+impl ServiceRoute {
+	pub fn from_rule(rule: String) -> Self {
+		let parsed = rule.parse::<TraefikRule>().ok();
+
+		Self {
+			domains: parsed.as_ref().map(|r| r.hosts()).unwrap_or_default(),
+			paths: parsed.as_ref().map(|r| r.paths()).unwrap_or_default(),
+			parsed,
+			rule,
+		}
+	}
 }
 
 /// Get the compose.yml content.
@@ -83,6 +135,108 @@ pub struct GetComposeRes {
 #[serde(rename_all = "camelCase")]
 pub struct SaveComposeReq {
 	pub compose: String,
+	/// Gets stored with the revision of the compose file
+	#[serde(default)]
+	pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveComposeRes {
+	/// Settings the security policy of the server warns about
+	pub warnings: Vec<ComposeError>,
+}
+
+/// Validate a compose.yml without saving it
+///
+/// URL: `/apps/:id/compose/validate`
+/// Method: `POST`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateComposeReq {
+	pub compose: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateComposeRes {
+	/// Errors first, then warnings
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Get all revisions of the compose.yml, the newest first.
+///
+/// Each save of the compose file creates a new revision.
+///
+/// URL: `/apps/:id/compose/revisions`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct ComposeRevisionsReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", transparent)]
+pub struct ComposeRevisionsRes(pub Vec<ComposeRevision>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeRevision {
+	/// milliseconds since the unix epoch when the revision was created
+	pub id: u64,
+	pub author: Option<String>,
+	pub created_on: DateTime,
+}
+
+/// Get a single revision of the compose.yml.
+///
+/// URL: `/apps/:id/compose/revisions/:revision`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct ComposeRevisionReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeRevisionRes {
+	pub revision: ComposeRevision,
+	pub compose: String,
+}
+
+/// Get the difference between a revision and another revision.
+///
+/// If `against` is None the revision is compared to the current compose.yml.
+///
+/// URL: `/apps/:id/compose/revisions/:revision/diff?against=<revision>`
+/// Method: `GET`
+/// Authentication: Yes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeRevisionDiffReq {
+	pub against: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposeRevisionDiffRes {
+	/// the content of the requested revision
+	pub original: String,
+	/// the content of the revision in `against` or the current compose.yml
+	pub modified: String,
+	/// a unified diff from original to modified
+	pub diff: String,
+}
+
+/// Restore a revision of the compose.yml and run `up`.
+///
+/// The restored content is stored as a new revision.
+///
+/// URL: `/apps/:id/compose/revisions/:revision/rollback`
+/// Method: `POST`
+/// Authentication: Yes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackComposeReq {
+	#[serde(default)]
+	pub author: Option<String>,
 }
 
 /// A request to execute a composer command.
@@ -118,13 +272,305 @@ derive_display_from_serialize!(ComposeCommand);
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppLogsReq;
 
-// /// A request to delete an application.
-// ///
-// /// This will remove the application and all of its data.
-// ///
-// /// URL: `/apps/:id`
-// /// Method: `DELETE`
-// /// Authentication: Yes
-// #[derive(Debug, Clone, Serialize, Deserialize)]
-// #[serde(rename_all = "camelCase")]
-// pub struct DeleteAppReq;
+/// A request to stream the logs of an app or of a single service.
+///
+/// URL: `/apps/:id/logs/stream?service=<service>&follow=<bool>&lines=<lines>&since=<since>&until=<until>&stdout=<bool>&stderr=<bool>`
+/// Method: `GET`
+/// Return Body: `text/event-stream`, each event contains a json [`LogLine`]
+/// or an [`Error`](crate::Error) if the event is called `error`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLogsStreamReq {
+	/// If None the logs of all services are returned
+	pub service: Option<String>,
+	/// Keep the stream open and send new lines as they get written
+	#[serde(default)]
+	pub follow: bool,
+	/// How many lines to return per service, if None all lines are returned
+	pub lines: Option<u32>,
+	pub since: Option<DateTime>,
+	pub until: Option<DateTime>,
+	#[serde(default = "default_true")]
+	pub stdout: bool,
+	#[serde(default = "default_true")]
+	pub stderr: bool,
+}
+
+fn default_true() -> bool {
+	true
+}
+
+impl Default for AppLogsStreamReq {
+	fn default() -> Self {
+		Self {
+			service: None,
+			follow: false,
+			lines: None,
+			since: None,
+			until: None,
+			stdout: true,
+			stderr: true,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogLine {
+	pub service: String,
+	pub stream: LogStream,
+	pub timestamp: Option<DateTime>,
+	/// the line without the trailing newline
+	pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+	Stdout,
+	Stderr,
+}
+
+/// A request to delete an application.
+///
+/// This will stop the application and remove its folder including the
+/// compose file.
+///
+/// URL: `/apps/:id`
+/// Method: `DELETE`
+/// Authentication: Yes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteAppReq {
+	/// Also remove the volumes declared in the compose file
+	#[serde(default)]
+	pub volumes: bool,
+	/// The database (and its user with the same name) to drop
+	pub database: Option<DatabaseName>,
+	/// Registry users to remove
+	#[serde(default)]
+	pub registry_users: Vec<RegistryUsername>,
+}
+
+/// Get the resource usage of all running containers of an app.
+///
+/// URL: `/apps/:id/stats`
+/// Method: `GET`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppStatsReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppStatsRes {
+	pub services: Vec<ServiceStats>,
+}
+
+/// A request to stream the resource usage of all running containers of an
+/// app.
+///
+/// URL: `/apps/:id/stats/stream`
+/// Method: `GET`
+/// Return Body: `text/event-stream`, each event contains a json
+/// [`ServiceStats`] or an [`Error`](crate::Error) if the event is called
+/// `error`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppStatsStreamReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceStats {
+	pub name: String,
+	pub container_name: String,
+	/// 100% equals one fully used cpu core
+	pub cpu_percent: f64,
+	/// bytes used without the page cache
+	pub memory_usage: u64,
+	pub memory_limit: u64,
+	/// bytes received, None if docker did not report any network stats
+	pub network_rx: Option<u64>,
+	/// bytes sent, None if docker did not report any network stats
+	pub network_tx: Option<u64>,
+	pub block_read: u64,
+	pub block_write: u64,
+}
+
+/// List the names of all secrets of an app, the values are never returned.
+///
+/// URL: `/apps/:id/secrets`
+/// Method: `GET`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppSecretsReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppSecretsRes {
+	pub names: Vec<String>,
+}
+
+/// Set a secret of an app.
+///
+/// The secret is stored encrypted and can be used in the compose.yml with
+/// interpolation, for example `DB_PASSWORD: ${DB_PASSWORD}`. Changes take
+/// effect on the next `up`.
+///
+/// URL: `/apps/:id/secrets/:name`
+/// Method: `PUT`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetSecretReq {
+	pub value: String,
+}
+
+/// Remove a secret of an app.
+///
+/// URL: `/apps/:id/secrets/:name`
+/// Method: `DELETE`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsetSecretReq;
+
+/// Opens an interactive shell in the running container of a service.
+///
+/// The connection gets upgraded to a WebSocket. Binary messages contain the
+/// raw terminal input and output, text messages contain a json
+/// [`ShellControl`].
+///
+/// URL: `/apps/:id/service/:service/shell?shell=<shell>&cols=<cols>&rows=<rows>`
+/// Method: `GET`
+/// Authentication: Yes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellReq {
+	/// The program to run, if None bash is used if available otherwise sh
+	pub shell: Option<String>,
+	pub cols: Option<u16>,
+	pub rows: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ShellControl {
+	/// Sent by the client when the terminal size changes
+	Resize { cols: u16, rows: u16 },
+	/// Sent by the server after the shell exited, the connection gets closed
+	/// afterwards
+	Exit { code: Option<i64> },
+}
+
+/// List the named volumes of an app.
+///
+/// URL: `/apps/:id/volumes`
+/// Method: `GET`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppVolumesReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppVolumesRes {
+	pub volumes: Vec<AppVolume>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppVolume {
+	/// The name of the volume in the compose file
+	pub name: String,
+	/// The name of the Docker volume
+	pub volume_name: String,
+}
+
+#[derive(
+	Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotCompression {
+	#[default]
+	None,
+	Zstd,
+}
+
+/// A request to get a snapshot of all named volumes of an app.
+///
+/// Returns a tar archive directly (no json), every volume is stored in a
+/// folder with the name of the volume in the compose file.
+///
+/// URL: `/apps/:id/volumes/snapshot?compression=<compression>`
+/// Method: `GET`
+/// Authentication: Yes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumesSnapshotReq {
+	#[serde(default)]
+	pub compression: SnapshotCompression,
+}
+
+/// A request to restore the named volumes of an app from a snapshot.
+///
+/// Provide the tar archive directly (no json). The app gets stopped while
+/// the volumes are replaced and is started again afterwards. Volumes which
+/// are not contained in the archive stay untouched.
+///
+/// URL: `/apps/:id/volumes/restore?compression=<compression>`
+/// Method: `PUT`
+/// Authentication: Yes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumesRestoreReq {
+	#[serde(default)]
+	pub compression: SnapshotCompression,
+}
+
+/// Get the deployments triggered by the registry webhook, the newest first.
+///
+/// After a deployment the services are watched for a grace period, if one
+/// of them exits or becomes unhealthy the previous images are restored.
+///
+/// URL: `/apps/:id/deployments`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct AppDeploymentsReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", transparent)]
+pub struct AppDeploymentsRes(pub Vec<AppDeployment>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppDeployment {
+	pub created_on: DateTime,
+	pub services: Vec<DeployedService>,
+	pub status: DeploymentStatus,
+	/// Why the deployment or the rollback failed
+	pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployedService {
+	pub name: String,
+	/// The image id which was running before the deployment
+	pub previous_image_id: Option<String>,
+	/// The image id after the deployment, None if the container was not
+	/// created
+	pub image_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DeploymentStatus {
+	/// All services stayed up during the grace period
+	Succeeded,
+	/// The deployment failed and there was nothing to roll back to
+	Failed,
+	/// The deployment failed and the previous images are running again
+	RolledBack,
+	RollbackFailed,
+}
+
+derive_display_from_serialize!(DeploymentStatus);
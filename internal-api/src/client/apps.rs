@@ -1,8 +1,20 @@
+use bytes::Bytes;
+use futures::{StreamExt as _, TryStream, stream::BoxStream};
+use reqwest::Body;
+
 use crate::{
 	app_id::AppId,
-	apps::{AppInfoRes, ComposeCommand, GetComposeRes, SaveComposeReq},
-	client::{ApiServerClient, Result},
-	error::WithMessage,
+	apps::{
+		AppDeploymentsRes, AppInfoRes, AppLogsStreamReq, AppSecretsRes,
+		AppStatsRes, AppVolumesRes, ComposeCommand, ComposeRevisionDiffReq,
+		ComposeRevisionDiffRes, ComposeRevisionRes, ComposeRevisionsRes,
+		DeleteAppReq, GetComposeRes, LogLine, RollbackComposeReq,
+		SaveComposeReq, SaveComposeRes, ServiceStats, SetSecretReq, ShellReq,
+		ValidateComposeReq, ValidateComposeRes, VolumesRestoreReq,
+		VolumesSnapshotReq,
+	},
+	client::{ApiServerClient, Result, ShellConnection, sse},
+	error::{Error, WithMessage},
 };
 
 #[derive(Debug, Clone)]
@@ -21,6 +33,17 @@ impl<'a> ApiServerAppsClient<'a> {
 			.await
 	}
 
+	pub async fn delete_app(
+		&self,
+		id: &AppId,
+		req: &DeleteAppReq,
+	) -> Result<()> {
+		self.inner
+			.send(self.inner.delete(&format!("/apps/{id}")).json(req))
+			.await
+			.map(|_| ())
+	}
+
 	pub async fn get_compose(&self, id: &AppId) -> Result<GetComposeRes> {
 		self.inner
 			.send_json(self.inner.get(&format!("/apps/{id}/compose")))
@@ -31,9 +54,81 @@ impl<'a> ApiServerAppsClient<'a> {
 		&self,
 		id: &AppId,
 		req: &SaveComposeReq,
+	) -> Result<SaveComposeRes> {
+		self.inner
+			.send_json(
+				self.inner.post(&format!("/apps/{id}/compose")).json(req),
+			)
+			.await
+	}
+
+	pub async fn validate_compose(
+		&self,
+		id: &AppId,
+		req: &ValidateComposeReq,
+	) -> Result<ValidateComposeRes> {
+		self.inner
+			.send_json(
+				self.inner
+					.post(&format!("/apps/{id}/compose/validate"))
+					.json(req),
+			)
+			.await
+	}
+
+	pub async fn compose_revisions(
+		&self,
+		id: &AppId,
+	) -> Result<ComposeRevisionsRes> {
+		self.inner
+			.send_json(self.inner.get(&format!("/apps/{id}/compose/revisions")))
+			.await
+	}
+
+	pub async fn compose_revision(
+		&self,
+		id: &AppId,
+		revision: u64,
+	) -> Result<ComposeRevisionRes> {
+		self.inner
+			.send_json(
+				self.inner
+					.get(&format!("/apps/{id}/compose/revisions/{revision}")),
+			)
+			.await
+	}
+
+	pub async fn compose_revision_diff(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &ComposeRevisionDiffReq,
+	) -> Result<ComposeRevisionDiffRes> {
+		self.inner
+			.send_json(
+				self.inner
+					.get(&format!(
+						"/apps/{id}/compose/revisions/{revision}/diff"
+					))
+					.query(req),
+			)
+			.await
+	}
+
+	pub async fn rollback_compose(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &RollbackComposeReq,
 	) -> Result<()> {
 		self.inner
-			.send(self.inner.post(&format!("/apps/{id}/compose")).json(req))
+			.send(
+				self.inner
+					.post(&format!(
+						"/apps/{id}/compose/revisions/{revision}/rollback"
+					))
+					.json(req),
+			)
 			.await
 			.map(|_| ())
 	}
@@ -83,4 +178,133 @@ impl<'a> ApiServerAppsClient<'a> {
 			.await
 			.with_message("failed to parse logs response")
 	}
+
+	pub async fn app_logs_stream(
+		&self,
+		id: &AppId,
+		req: &AppLogsStreamReq,
+	) -> Result<BoxStream<'static, Result<LogLine>>> {
+		self.inner
+			.send(
+				self.inner
+					.get(&format!("/apps/{id}/logs/stream"))
+					.query(req),
+			)
+			.await
+			.map(sse::json_events)
+	}
+
+	pub async fn secrets(&self, id: &AppId) -> Result<AppSecretsRes> {
+		self.inner
+			.send_json(self.inner.get(&format!("/apps/{id}/secrets")))
+			.await
+	}
+
+	pub async fn set_secret(
+		&self,
+		id: &AppId,
+		name: &str,
+		req: &SetSecretReq,
+	) -> Result<()> {
+		self.inner
+			.send(
+				self.inner
+					.put(&format!("/apps/{id}/secrets/{name}"))
+					.json(req),
+			)
+			.await
+			.map(|_| ())
+	}
+
+	pub async fn unset_secret(&self, id: &AppId, name: &str) -> Result<()> {
+		self.inner
+			.send(self.inner.delete(&format!("/apps/{id}/secrets/{name}")))
+			.await
+			.map(|_| ())
+	}
+
+	pub async fn volumes(&self, id: &AppId) -> Result<AppVolumesRes> {
+		self.inner
+			.send_json(self.inner.get(&format!("/apps/{id}/volumes")))
+			.await
+	}
+
+	pub async fn volumes_snapshot(
+		&self,
+		id: &AppId,
+		req: &VolumesSnapshotReq,
+	) -> Result<BoxStream<'static, Result<Bytes>>> {
+		self.inner
+			.send(
+				self.inner
+					.get(&format!("/apps/{id}/volumes/snapshot"))
+					.query(req),
+			)
+			.await
+			.map(|res| {
+				res.bytes_stream()
+					.map(|r| r.with_message("snapshot failed"))
+					.boxed()
+			})
+	}
+
+	pub async fn volumes_restore<S>(
+		&self,
+		id: &AppId,
+		req: &VolumesRestoreReq,
+		stream: S,
+	) -> Result<()>
+	where
+		S: TryStream<Ok = Bytes, Error = Error> + Send + 'static,
+	{
+		self.inner
+			.send(
+				self.inner
+					.put(&format!("/apps/{id}/volumes/restore"))
+					.query(req)
+					.body(Body::wrap_stream(stream)),
+			)
+			.await
+			.map(|_| ())
+	}
+
+	pub async fn deployments(&self, id: &AppId) -> Result<AppDeploymentsRes> {
+		self.inner
+			.send_json(self.inner.get(&format!("/apps/{id}/deployments")))
+			.await
+	}
+
+	pub async fn service_shell(
+		&self,
+		id: &AppId,
+		service: &str,
+		req: &ShellReq,
+	) -> Result<ShellConnection> {
+		let upgraded = self
+			.inner
+			.upgrade(
+				self.inner
+					.ws_get(&format!("/apps/{id}/service/{service}/shell"))
+					.query(req),
+			)
+			.await?;
+
+		Ok(ShellConnection::from_upgraded(upgraded).await)
+	}
+
+	pub async fn app_stats(&self, id: &AppId) -> Result<AppStatsRes> {
+		self.inner
+			.send_json(self.inner.get(&format!("/apps/{id}/stats")))
+			.await
+	}
+
+	pub async fn app_stats_stream(
+		&self,
+		id: &AppId,
+	) -> Result<BoxStream<'static, Result<ServiceStats>>> {
+		self.inner
+			.send(self.inner.get(&format!("/apps/{id}/stats/stream")))
+			.await
+			.map(sse::json_events)
+	}
 }
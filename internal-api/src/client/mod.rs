@@ -1,19 +1,27 @@
 mod apps;
 mod postgres;
 mod registry;
+mod shell;
+mod sse;
+mod tls;
 
-use http::Method;
-use reqwest::{Certificate, RequestBuilder, Response};
+use std::sync::Arc;
+
+use http::{Method, StatusCode, header};
+use reqwest::{Certificate, ClientBuilder, RequestBuilder, Response, Upgraded};
 use serde::de::DeserializeOwned;
+use tokio_tungstenite::tungstenite::handshake::client::generate_key;
 
 pub use apps::ApiServerAppsClient;
+pub use shell::{ShellConnection, ShellMessage, ShellSink};
 
 use crate::{
 	client::{
 		postgres::ApiServerPostgresClient, registry::ApiServerRegistryClient,
+		tls::PinnedVerifier,
 	},
 	error::{Error, WithMessage},
-	requests::{ApiToken, InfoRes, PingRes},
+	requests::{ApiToken, InfoRes, PingRes, RotateApiTokenRes, SystemInfoRes},
 };
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -32,33 +40,82 @@ impl ApiClient {
 		}
 	}
 
+	/// Trusts the certificate of the server
 	pub fn connect(
 		&self,
 		domain: impl Into<String>,
 		cert: &str,
 		token: ApiToken,
 	) -> Result<ApiServerClient> {
-		let addr = format!("https://{}:4242", domain.into());
 		let cert = Certificate::from_pem(cert.as_bytes())
 			.map_err(|_| Error::InvalidCertificate)?;
 
-		let inner = reqwest::Client::builder()
-			.add_root_certificate(cert)
-			.build()
-			.with_message("Failed to build reqwest client")?;
+		ApiServerClient::new(domain.into(), token, |builder, _| {
+			Ok(builder.add_root_certificate(cert.clone()))
+		})
+	}
+
+	/// Trusts every certificate with one of the public keys, see
+	/// [`spki_fingerprint`](crate::tls::spki_fingerprint)
+	///
+	/// Unlike [`Self::connect`] this keeps working when the server renews its
+	/// certificate.
+	pub fn connect_pinned(
+		&self,
+		domain: impl Into<String>,
+		pins: Vec<String>,
+		token: ApiToken,
+	) -> Result<ApiServerClient> {
+		let verifier = Arc::new(PinnedVerifier::new(pins));
+
+		ApiServerClient::new(domain.into(), token, |builder, http1_only| {
+			let alpn: &[&[u8]] = if http1_only {
+				&[b"http/1.1"]
+			} else {
+				&[b"h2", b"http/1.1"]
+			};
 
-		Ok(ApiServerClient { inner, addr, token })
+			Ok(builder.use_preconfigured_tls(verifier.client_config(alpn)?))
+		})
 	}
 }
 
 #[derive(Debug, Clone)]
 pub struct ApiServerClient {
 	inner: reqwest::Client,
+	ws_inner: reqwest::Client,
 	addr: String,
 	token: ApiToken,
 }
 
 impl ApiServerClient {
+	/// `tls` gets called with true for the client which is used for
+	/// websockets
+	fn new(
+		domain: String,
+		token: ApiToken,
+		tls: impl Fn(ClientBuilder, bool) -> Result<ClientBuilder>,
+	) -> Result<Self> {
+		let addr = format!("https://{domain}:4242");
+
+		let inner = tls(reqwest::Client::builder(), false)?
+			.build()
+			.with_message("Failed to build reqwest client")?;
+
+		// websockets need an http/1.1 upgrade which does not exist in http2
+		let ws_inner = tls(reqwest::Client::builder(), true)?
+			.http1_only()
+			.build()
+			.with_message("Failed to build reqwest client")?;
+
+		Ok(Self {
+			inner,
+			ws_inner,
+			addr,
+			token,
+		})
+	}
+
 	fn request(&self, method: Method, uri: &str) -> RequestBuilder {
 		self.inner
 			.request(method, format!("{}{}", self.addr, uri))
@@ -84,6 +141,37 @@ impl ApiServerClient {
 		self.request(Method::DELETE, uri)
 	}
 
+	/// A GET request which asks for a WebSocket upgrade
+	pub(crate) fn ws_get(&self, uri: &str) -> RequestBuilder {
+		self.ws_inner
+			.get(format!("{}{}", self.addr, uri))
+			.bearer_auth(&self.token)
+			.header(header::CONNECTION, "Upgrade")
+			.header(header::UPGRADE, "websocket")
+			.header(header::SEC_WEBSOCKET_VERSION, "13")
+			.header(header::SEC_WEBSOCKET_KEY, generate_key())
+	}
+
+	pub(crate) async fn upgrade(
+		&self,
+		req: RequestBuilder,
+	) -> Result<Upgraded> {
+		let response =
+			req.send().await.with_message("failed to send request")?;
+
+		if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+			return Err(response
+				.json()
+				.await
+				.with_message("failed to parse error response")?);
+		}
+
+		response
+			.upgrade()
+			.await
+			.with_message("failed to upgrade connection")
+	}
+
 	// todo should probably improve the errors
 	pub(crate) async fn send(&self, req: RequestBuilder) -> Result<Response> {
 		let response =
@@ -123,6 +211,14 @@ impl ApiServerClient {
 		self.send_json(self.get("/info")).await
 	}
 
+	pub async fn rotate_token(&self) -> Result<RotateApiTokenRes> {
+		self.send_json(self.post("/tokens/rotate")).await
+	}
+
+	pub async fn system(&self) -> Result<SystemInfoRes> {
+		self.send_json(self.get("/system")).await
+	}
+
 	pub fn apps(&self) -> ApiServerAppsClient<'_> {
 		ApiServerAppsClient::new(&self)
 	}
@@ -7,7 +7,9 @@ use crate::{
 	client::{ApiServerClient, Result},
 	database_name::DatabaseName,
 	error::WithMessage,
-	postgres::{CreateDatabaseReq, CreateDatabaseRes, NewPasswordRes},
+	postgres::{
+		CreateDatabaseReq, CreateDatabaseRes, NewPasswordRes, PostgresBackup,
+	},
 };
 
 #[derive(Debug, Clone)]
@@ -83,6 +85,62 @@ impl<'a> ApiServerPostgresClient<'a> {
 			})
 	}
 
+	pub async fn backups(
+		&self,
+		name: &DatabaseName,
+	) -> Result<Vec<PostgresBackup>> {
+		self.inner
+			.send_json(
+				self.inner
+					.get(&format!("/postgres/databases/{name}/backups")),
+			)
+			.await
+	}
+
+	pub async fn create_backup(
+		&self,
+		name: &DatabaseName,
+	) -> Result<PostgresBackup> {
+		self.inner
+			.send_json(
+				self.inner
+					.post(&format!("/postgres/databases/{name}/backups")),
+			)
+			.await
+	}
+
+	pub async fn download_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<BoxStream<'static, Result<Bytes>>> {
+		self.inner
+			.send(
+				self.inner.get(&format!(
+					"/postgres/databases/{name}/backups/{backup}"
+				)),
+			)
+			.await
+			.map(|res| {
+				res.bytes_stream()
+					.map(|r| r.with_message("backup download failed"))
+					.boxed()
+			})
+	}
+
+	pub async fn restore_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<()> {
+		self.inner
+			.send(self.inner.post(&format!(
+				"/postgres/databases/{name}/backups/{backup}/restore"
+			)))
+			.await
+			.map(|_| ())
+	}
+
 	// pub async fn delete_user(&self, username: &str) -> Result<()> {
 	// 	self.inner
 	// 		.send_json(
@@ -1,6 +1,10 @@
 use crate::{
+	app_id::AppId,
 	client::{ApiServerClient, Result},
-	registry::{CreateUserReq, CreateUserRes},
+	registry::{
+		CreateUserReq, CreateUserRes, RegistryGcReq, RegistryGcRes,
+		RegistryRepository, RegistryTag,
+	},
 	registry_username::RegistryUsername,
 };
 
@@ -40,4 +44,28 @@ impl<'a> ApiServerRegistryClient<'a> {
 			)
 			.await
 	}
+
+	pub async fn repositories(&self) -> Result<Vec<RegistryRepository>> {
+		self.inner
+			.send_json(self.inner.get("/registry/repositories"))
+			.await
+	}
+
+	pub async fn tags(
+		&self,
+		app_id: &AppId,
+		service: &str,
+	) -> Result<Vec<RegistryTag>> {
+		self.inner
+			.send_json(self.inner.get(&format!(
+				"/registry/repositories/{app_id}/{service}/tags"
+			)))
+			.await
+	}
+
+	pub async fn gc(&self, req: &RegistryGcReq) -> Result<RegistryGcRes> {
+		self.inner
+			.send_json(self.inner.post("/registry/gc").json(req))
+			.await
+	}
 }
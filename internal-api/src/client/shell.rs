@@ -0,0 +1,81 @@
+use std::pin::Pin;
+
+use bytes::Bytes;
+use futures::{Sink, SinkExt as _, StreamExt as _, future, stream::BoxStream};
+use reqwest::Upgraded;
+use tokio_tungstenite::{
+	WebSocketStream,
+	tungstenite::{Message, protocol::Role},
+};
+
+use crate::{
+	apps::ShellControl,
+	client::Result,
+	error::{Error, WithMessage as _},
+};
+
+/// A message sent or received over a shell connection
+#[derive(Debug, Clone)]
+pub enum ShellMessage {
+	/// Raw terminal input or output
+	Data(Bytes),
+	Control(ShellControl),
+}
+
+pub type ShellSink = Pin<Box<dyn Sink<ShellMessage, Error = Error> + Send>>;
+
+pub struct ShellConnection {
+	sink: ShellSink,
+	stream: BoxStream<'static, Result<ShellMessage>>,
+}
+
+impl ShellConnection {
+	pub fn new(
+		sink: ShellSink,
+		stream: BoxStream<'static, Result<ShellMessage>>,
+	) -> Self {
+		Self { sink, stream }
+	}
+
+	pub(crate) async fn from_upgraded(upgraded: Upgraded) -> Self {
+		let ws = WebSocketStream::from_raw_socket(upgraded, Role::Client, None)
+			.await;
+		let (sink, stream) = ws.split();
+
+		let sink = sink
+			.sink_map_err(|e| Error::any("Failed to send shell message", e))
+			.with(|msg: ShellMessage| future::ready(msg_to_ws(msg)));
+
+		let stream = stream.filter_map(|r| {
+			future::ready(match r {
+				Ok(Message::Binary(b)) => Some(Ok(ShellMessage::Data(b))),
+				Ok(Message::Text(t)) => Some(
+					serde_json::from_str(&t)
+						.map(ShellMessage::Control)
+						.with_message("Failed to parse shell message"),
+				),
+				Ok(_) => None,
+				Err(e) => {
+					Some(Err(Error::any("Failed to read shell message", e)))
+				}
+			})
+		});
+
+		Self::new(Box::pin(sink), stream.boxed())
+	}
+
+	pub fn split(
+		self,
+	) -> (ShellSink, BoxStream<'static, Result<ShellMessage>>) {
+		(self.sink, self.stream)
+	}
+}
+
+fn msg_to_ws(msg: ShellMessage) -> Result<Message> {
+	match msg {
+		ShellMessage::Data(b) => Ok(Message::Binary(b)),
+		ShellMessage::Control(c) => serde_json::to_string(&c)
+			.map(|t| Message::Text(t.into()))
+			.with_message("Failed to serialize shell message"),
+	}
+}
@@ -0,0 +1,101 @@
+use bytes::Bytes;
+use futures::{
+	StreamExt as _,
+	stream::{self, BoxStream},
+};
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+
+use crate::{
+	client::Result,
+	error::{Error, WithMessage as _},
+};
+
+struct EventReader {
+	inner: BoxStream<'static, reqwest::Result<Bytes>>,
+	buf: Vec<u8>,
+	done: bool,
+}
+
+/// Reads a `text/event-stream` response where the data of each event
+/// is json.
+///
+/// Events named `error` contain an [`Error`] which will be returned as such.
+pub(crate) fn json_events<T>(
+	response: Response,
+) -> BoxStream<'static, Result<T>>
+where
+	T: DeserializeOwned + Send + 'static,
+{
+	let reader = EventReader {
+		inner: response.bytes_stream().boxed(),
+		buf: vec![],
+		done: false,
+	};
+
+	stream::unfold(reader, |mut reader| async move {
+		loop {
+			// each event is terminated by an empty line
+			if let Some(pos) = reader.buf.windows(2).position(|w| w == b"\n\n")
+			{
+				let raw: Vec<u8> = reader.buf.drain(..pos + 2).collect();
+
+				match parse_event(&String::from_utf8_lossy(&raw)) {
+					Some(item) => return Some((item, reader)),
+					// comments or keep alive messages
+					None => continue,
+				}
+			}
+
+			if reader.done {
+				return None;
+			}
+
+			match reader.inner.next().await {
+				Some(Ok(bytes)) => reader.buf.extend_from_slice(&bytes),
+				Some(Err(e)) => {
+					reader.done = true;
+					return Some((
+						Err(Error::any("failed to read event stream", e)),
+						reader,
+					));
+				}
+				None => reader.done = true,
+			}
+		}
+	})
+	.boxed()
+}
+
+fn parse_event<T>(raw: &str) -> Option<Result<T>>
+where
+	T: DeserializeOwned,
+{
+	let mut name = None;
+	let mut data: Option<String> = None;
+
+	for line in raw.lines() {
+		if let Some(n) = line.strip_prefix("event:") {
+			name = Some(n.trim());
+		} else if let Some(d) = line.strip_prefix("data:") {
+			let d = d.strip_prefix(' ').unwrap_or(d);
+			match &mut data {
+				Some(data) => {
+					data.push('\n');
+					data.push_str(d);
+				}
+				None => data = Some(d.to_string()),
+			}
+		}
+	}
+
+	let data = data?;
+
+	Some(match name {
+		Some("error") => match serde_json::from_str::<Error>(&data) {
+			Ok(e) => Err(e),
+			Err(e) => Err(Error::any("failed to parse error event", e)),
+		},
+		_ => serde_json::from_str(&data).with_message("failed to parse event"),
+	})
+}
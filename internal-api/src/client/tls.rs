@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use rustls::{
+	CertificateError, ClientConfig, DigitallySignedStruct, SignatureScheme,
+	client::danger::{
+		HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+	},
+	crypto::{
+		CryptoProvider, aws_lc_rs, verify_tls12_signature,
+		verify_tls13_signature,
+	},
+	pki_types::{CertificateDer, ServerName, UnixTime},
+	server::ParsedCertificate,
+};
+
+use crate::{
+	error::{Error, WithMessage as _},
+	tls::spki_fingerprint,
+};
+
+/// Accepts every certificate with one of the pinned public keys
+///
+/// The name and the expiry of the certificate are not checked, the pinned
+/// key is all that is trusted.
+#[derive(Debug)]
+pub(super) struct PinnedVerifier {
+	pins: Vec<String>,
+	provider: Arc<CryptoProvider>,
+}
+
+impl PinnedVerifier {
+	pub fn new(pins: Vec<String>) -> Self {
+		Self {
+			pins,
+			provider: Arc::new(aws_lc_rs::default_provider()),
+		}
+	}
+
+	pub fn client_config(
+		self: &Arc<Self>,
+		alpn_protocols: &[&[u8]],
+	) -> Result<ClientConfig, Error> {
+		let mut config =
+			ClientConfig::builder_with_provider(self.provider.clone())
+				.with_safe_default_protocol_versions()
+				.with_message("Failed to build tls config")?
+				.dangerous()
+				.with_custom_certificate_verifier(self.clone())
+				.with_no_client_auth();
+
+		config.alpn_protocols =
+			alpn_protocols.iter().map(|p| p.to_vec()).collect();
+
+		Ok(config)
+	}
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &CertificateDer<'_>,
+		_intermediates: &[CertificateDer<'_>],
+		_server_name: &ServerName<'_>,
+		_ocsp_response: &[u8],
+		_now: UnixTime,
+	) -> Result<ServerCertVerified, rustls::Error> {
+		let cert = ParsedCertificate::try_from(end_entity)?;
+		let fingerprint = spki_fingerprint(&cert.subject_public_key_info());
+
+		if self.pins.contains(&fingerprint) {
+			Ok(ServerCertVerified::assertion())
+		} else {
+			Err(rustls::Error::InvalidCertificate(
+				CertificateError::ApplicationVerificationFailure,
+			))
+		}
+	}
+
+	fn verify_tls12_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		verify_tls12_signature(
+			message,
+			cert,
+			dss,
+			&self.provider.signature_verification_algorithms,
+		)
+	}
+
+	fn verify_tls13_signature(
+		&self,
+		message: &[u8],
+		cert: &CertificateDer<'_>,
+		dss: &DigitallySignedStruct,
+	) -> Result<HandshakeSignatureValid, rustls::Error> {
+		verify_tls13_signature(
+			message,
+			cert,
+			dss,
+			&self.provider.signature_verification_algorithms,
+		)
+	}
+
+	fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+		self.provider
+			.signature_verification_algorithms
+			.supported_schemes()
+	}
+}
@@ -22,10 +22,33 @@ pub enum Error {
 	/// Gets returned if the app folder or the compose file (if required) could not be found
 	#[error("Could not find app folder")]
 	AppNotFound,
+	/// Gets returned if no container exists for the given service
+	#[error("Could not find service")]
+	ServiceNotFound,
+	#[error("Could not find compose revision")]
+	RevisionNotFound,
+	#[error("Could not find repository")]
+	RepositoryNotFound,
+	#[error("Could not find backup")]
+	BackupNotFound,
+	#[error("Could not find secret")]
+	SecretNotFound,
+	/// Secret names need to be valid environment variable names which are not
+	/// used by docker compose itself
+	#[error("Invalid secret name")]
+	InvalidSecretName,
 	#[error("Missing bearer token in request")]
 	MissingApiToken,
 	#[error("Invalid bearer token in request")]
 	InvalidApiToken,
+	#[error("The bearer token has expired")]
+	ExpiredApiToken,
+	/// The token is valid but does not have the scope for this request
+	#[error("The bearer token is missing the scope {scope}")]
+	InsufficientScope { scope: String },
+	/// A database or a registry user which is named after another app
+	#[error("{name} does not belong to the app")]
+	NotOwnedByApp { name: String },
 	#[error("Failed to run command: {command}, message: {message}")]
 	Command { command: String, message: String },
 	#[error("HOSTDINGHY_DIR environment variable is not set")]
@@ -54,10 +77,21 @@ impl Error {
 			Self::UserAlreadyExists
 			| Self::DatabaseAlreadyExists
 			| Self::Compose(_)
-			| Self::InvalidCertificate => StatusCode::BAD_REQUEST,
-			Self::DatabaseNotFound | Self::AppNotFound => StatusCode::NOT_FOUND,
-			Self::MissingApiToken => StatusCode::UNAUTHORIZED,
-			Self::InvalidApiToken => StatusCode::FORBIDDEN,
+			| Self::InvalidCertificate
+			| Self::InvalidSecretName => StatusCode::BAD_REQUEST,
+			Self::DatabaseNotFound
+			| Self::AppNotFound
+			| Self::ServiceNotFound
+			| Self::RevisionNotFound
+			| Self::SecretNotFound
+			| Self::BackupNotFound
+			| Self::RepositoryNotFound => StatusCode::NOT_FOUND,
+			Self::MissingApiToken | Self::ExpiredApiToken => {
+				StatusCode::UNAUTHORIZED
+			}
+			Self::InvalidApiToken
+			| Self::InsufficientScope { .. }
+			| Self::NotOwnedByApp { .. } => StatusCode::FORBIDDEN,
 			Self::Command { .. }
 			| Self::HostdinghyDirNotPresent
 			| Self::Any { .. } => StatusCode::INTERNAL_SERVER_ERROR,
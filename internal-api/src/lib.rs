@@ -8,5 +8,6 @@ pub mod postgres;
 pub mod registry;
 mod registry_username;
 pub mod requests;
+pub mod tls;
 
 pub use error::Error;
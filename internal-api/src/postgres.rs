@@ -1,3 +1,4 @@
+use chuchi_postgres::time::DateTime;
 use serde::{Deserialize, Serialize};
 
 pub use crate::database_name::DatabaseName;
@@ -71,6 +72,56 @@ pub struct PostgresDatabaseRestoreReq;
 /// Authentication: Yes
 pub struct PostgresDatabaseDumpReq;
 
+/// A request to list the backups of a database.
+///
+/// Backups are created by the scheduler configured in the server config or
+/// manually, they are returned sorted by the newest first.
+///
+/// URL: `/postgres/databases/:database/backups`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct PostgresBackupsReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", transparent)]
+pub struct PostgresBackupsRes(pub Vec<PostgresBackup>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostgresBackup {
+	/// The time of the backup in milliseconds since the unix epoch
+	pub id: u64,
+	pub database: String,
+	pub created_on: DateTime,
+	/// The size of the dump in bytes
+	pub size: u64,
+}
+
+/// A request to create a backup of the database now.
+///
+/// URL: `/postgres/databases/:database/backups`
+/// Method: `POST`
+/// Response: [`PostgresBackup`]
+/// Authentication: Yes
+pub struct CreatePostgresBackupReq;
+
+/// A request to download a backup.
+///
+/// Returns the dump directly (no json), the same format as
+/// [`PostgresDatabaseDumpReq`].
+///
+/// URL: `/postgres/databases/:database/backups/:backup`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct PostgresBackupDownloadReq;
+
+/// A request to restore the database from a backup.
+///
+/// URL: `/postgres/databases/:database/backups/:backup/restore`
+/// Method: `POST`
+/// Authentication: Yes
+pub struct PostgresBackupRestoreReq;
+
 // todo implement once we have a better security model
 // /// A request to delete a database and its user
 // ///
@@ -1,5 +1,7 @@
+use chuchi_postgres::time::DateTime;
 use serde::{Deserialize, Serialize};
 
+use crate::app_id::AppId;
 pub use crate::registry_username::RegistryUsername;
 
 /// A request to get a list of all registry users.
@@ -40,3 +42,79 @@ pub struct CreateUserRes {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeleteUserReq;
+
+/// A request to list all repositories in the registry.
+///
+/// Repositories which don't follow the `app_id/service` naming are skipped.
+///
+/// URL: `/registry/repositories`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct RegistryRepositoriesReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", transparent)]
+pub struct RegistryRepositoriesRes(pub Vec<RegistryRepository>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryRepository {
+	pub app_id: AppId,
+	pub service: String,
+}
+
+/// A request to list all tags of a repository.
+///
+/// The tags are sorted by the newest first.
+///
+/// URL: `/registry/repositories/:app_id/:service/tags`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct RegistryTagsReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", transparent)]
+pub struct RegistryTagsRes(pub Vec<RegistryTag>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryTag {
+	pub name: String,
+	/// The digest of the manifest
+	pub digest: String,
+	/// None if the image config does not contain a creation date
+	pub created_on: Option<DateTime>,
+	/// The compressed size of the config and all layers in bytes
+	pub size: u64,
+}
+
+/// A request to delete all tags outside of the retention and free the space
+/// used by them.
+///
+/// Tags referenced by the compose file of an app are never deleted.
+///
+/// URL: `/registry/gc`
+/// Method: `POST`
+/// Authentication: Yes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryGcReq {
+	/// How many of the newest tags to keep per repository
+	pub keep_last: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryGcRes {
+	pub deleted: Vec<RegistryDeletedTag>,
+	/// The space freed on the disk in bytes
+	pub freed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryDeletedTag {
+	pub app_id: AppId,
+	pub service: String,
+	pub tag: String,
+}
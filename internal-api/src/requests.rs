@@ -2,9 +2,48 @@ use chuchi_crypto::token::Token;
 use chuchi_postgres::time::DateTime;
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use serde_plain::{
+	derive_display_from_serialize, derive_fromstr_from_deserialize,
+};
 
 pub type ApiToken = Token<42>;
 
+/// What a token is allowed to do
+///
+/// Every scope allows reading, `admin` allows everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiTokenScope {
+	ReadOnly,
+	/// Changing apps, their secrets and volumes and opening a shell
+	Apps,
+	/// Managing registry users and the garbage collection
+	Registry,
+	/// Managing databases and downloading backups
+	Postgres,
+	Admin,
+}
+
+derive_display_from_serialize!(ApiTokenScope);
+derive_fromstr_from_deserialize!(ApiTokenScope);
+
+impl ApiTokenScope {
+	/// Returns true if this scope allows a request requiring `required`
+	pub fn allows(&self, required: ApiTokenScope) -> bool {
+		*self == Self::Admin || *self == required || required == Self::ReadOnly
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenInfo {
+	pub name: String,
+	pub scopes: Vec<ApiTokenScope>,
+	pub created_on: DateTime,
+	/// If None the token never expires
+	pub expires_on: Option<DateTime>,
+}
+
 /// A simple request to check if the server is running.
 ///
 /// URL: `/ping`
@@ -35,4 +74,108 @@ pub struct InfoRes {
 	pub commit: Option<String>,
 	// on prod this should never be None
 	pub build_date: Option<DateTime>,
+	/// The token which was used for this request, older servers don't
+	/// return it
+	#[serde(default)]
+	pub token: Option<ApiTokenInfo>,
+	/// Older servers don't return it
+	#[serde(default)]
+	pub tls: Option<TlsInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsInfo {
+	/// See [`spki_fingerprint`](crate::tls::spki_fingerprint)
+	pub spki_fingerprint: String,
+	/// The key which gets used after the next renewal, if the server rotates
+	/// its key
+	pub next_spki_fingerprint: Option<String>,
+	pub expires_on: DateTime,
+}
+
+impl TlsInfo {
+	/// Returns the fingerprints a client should pin
+	pub fn pins(&self) -> Vec<String> {
+		let mut pins = vec![self.spki_fingerprint.clone()];
+		pins.extend(self.next_spki_fingerprint.clone());
+		pins
+	}
+}
+
+/// Replaces the token used for this request with a new one
+///
+/// The old token keeps working until `previous_valid_until` so clients
+/// can switch without downtime.
+///
+/// URL: `/tokens/rotate`
+/// Method: `POST`
+/// Authentication: Yes
+pub struct RotateApiTokenReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateApiTokenRes {
+	pub token: ApiToken,
+	pub info: ApiTokenInfo,
+	pub previous_valid_until: DateTime,
+}
+
+/// Information about the host the server is running on
+///
+/// URL: `/system`
+/// Method: `GET`
+/// Authentication: Yes
+pub struct SystemInfoReq;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemInfoRes {
+	/// The pretty name from `/etc/os-release`
+	pub os: Option<String>,
+	pub kernel: String,
+	/// In seconds
+	pub uptime: u64,
+	pub cpus: u32,
+	/// Over the last 1, 5 and 15 minutes
+	pub load_average: [f64; 3],
+	pub memory: MemoryUsage,
+	/// The disk containing `$HOSTDINGHY_DIR`
+	pub hostdinghy_disk: DiskUsage,
+	/// The disk containing the docker data root, None if docker could not be
+	/// reached
+	pub docker_disk: Option<DiskUsage>,
+	pub docker_version: Option<String>,
+	pub compose_version: Option<String>,
+	pub postgres_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+	/// In bytes
+	pub total: u64,
+	/// In bytes, memory which can be used without swapping
+	pub available: u64,
+}
+
+/// Below this share of free space a disk is running out of space
+pub const LOW_DISK_SPACE: f64 = 0.1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+	pub path: String,
+	/// In bytes
+	pub total: u64,
+	/// In bytes, what can be used by an unprivileged user
+	pub available: u64,
+}
+
+impl DiskUsage {
+	/// Returns true if less than [`LOW_DISK_SPACE`] is available
+	pub fn is_low(&self) -> bool {
+		self.total > 0
+			&& (self.available as f64) < self.total as f64 * LOW_DISK_SPACE
+	}
 }
@@ -0,0 +1,14 @@
+/*!
+The internal api uses a self-signed certificate. Instead of trusting the
+certificate a client can pin the public key of the server, which does not
+change when the certificate gets renewed.
+*/
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use sha2::{Digest as _, Sha256};
+
+/// Returns `sha256/<base64>` of the DER encoded SubjectPublicKeyInfo
+pub fn spki_fingerprint(spki: &[u8]) -> String {
+	let hash = Sha256::digest(spki);
+	format!("sha256/{}", BASE64_STANDARD.encode(hash))
+}
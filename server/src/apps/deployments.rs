@@ -0,0 +1,478 @@
+/*!
+Deployments triggered by the registry webhook are recorded in
+`$HOSTDINGHY_DIR/<id>/deployments.toml`, only the newest ones are kept.
+
+Services using the `start-first` deploy strategy are replaced without
+downtime, all others get recreated.
+
+Before the new images get pulled the image ids of the running containers are
+remembered. If a service exits, restarts or becomes unhealthy during the grace
+period, the previous image ids get tagged again and the services are
+recreated without pulling.
+
+A rolled back service keeps its previous image until a new image gets
+deployed, [`up`] does not pull it again.
+
+Only one deployment or compose command per app runs at a time, see
+[`DeployLocks`].
+*/
+
+use std::{
+	collections::{HashMap, HashSet},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use api::{
+	apps::{AppDeployment, AppId, DeployedService, DeploymentStatus},
+	error::{Error, WithMessage as _},
+};
+use bollard::secret::{ContainerStateStatusEnum, HealthStatusEnum};
+use chuchi_postgres::time::DateTime;
+use compose_yml::{Compose, DeployStrategy};
+use serde::{Deserialize, Serialize};
+use tokio::{
+	fs,
+	sync::{Mutex as AsyncMutex, OwnedMutexGuard},
+	time::{Instant, sleep},
+};
+use tracing::{error, warn};
+
+use crate::{
+	apps::start_first,
+	docker::Docker,
+	traefik::client::Traefik,
+	utils::{cmd::cmd, compose, is_file, read_toml, write_toml},
+};
+
+const MAX_DEPLOYMENTS: usize = 50;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DeploymentsFile {
+	/// The oldest first
+	#[serde(default)]
+	deployments: Vec<AppDeployment>,
+}
+
+fn deployments_path(app_dir: impl AsRef<Path>) -> PathBuf {
+	app_dir.as_ref().join("deployments.toml")
+}
+
+async fn read_file(
+	app_dir: impl AsRef<Path>,
+) -> Result<DeploymentsFile, Error> {
+	let path = deployments_path(app_dir);
+	if !is_file(&path).await {
+		return Ok(DeploymentsFile::default());
+	}
+
+	read_toml(&path).await.map_err(Into::into)
+}
+
+/// Returns the deployments sorted by the newest first
+pub async fn list(
+	app_dir: impl AsRef<Path>,
+) -> Result<Vec<AppDeployment>, Error> {
+	let mut deployments = read_file(app_dir).await?.deployments;
+	deployments.reverse();
+
+	Ok(deployments)
+}
+
+async fn record(
+	app_dir: impl AsRef<Path>,
+	deployment: &AppDeployment,
+) -> Result<(), Error> {
+	let mut file = read_file(&app_dir).await?;
+	file.deployments.push(deployment.clone());
+
+	let len = file.deployments.len();
+	if len > MAX_DEPLOYMENTS {
+		file.deployments.drain(..len - MAX_DEPLOYMENTS);
+	}
+
+	write_toml(&file, deployments_path(&app_dir))
+		.await
+		.map_err(Into::into)
+}
+
+/// Makes sure only one deployment or compose command of an app runs at a time
+#[derive(Debug, Clone, Default)]
+pub struct DeployLocks {
+	inner: Arc<Mutex<HashMap<AppId, Arc<AsyncMutex<()>>>>>,
+}
+
+impl DeployLocks {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Waits until no other deployment of the app runs
+	pub async fn lock(&self, id: &AppId) -> OwnedMutexGuard<()> {
+		let lock = self
+			.inner
+			.lock()
+			.unwrap()
+			.entry(id.clone())
+			.or_default()
+			.clone();
+
+		lock.lock_owned().await
+	}
+}
+
+/// Returns the services which were rolled back by their last deployment
+async fn rolled_back_services(
+	app_dir: impl AsRef<Path>,
+) -> Result<HashSet<String>, Error> {
+	let mut rolled_back = HashSet::new();
+
+	// the oldest first, so a newer deployment overrides an older one
+	for deployment in read_file(app_dir).await?.deployments {
+		let is_rolled_back = deployment.status == DeploymentStatus::RolledBack;
+		for service in deployment.services {
+			if is_rolled_back {
+				rolled_back.insert(service.name);
+			} else {
+				rolled_back.remove(&service.name);
+			}
+		}
+	}
+
+	Ok(rolled_back)
+}
+
+/// Runs `docker compose up` but keeps the images of rolled back services
+///
+/// Pulling them again would bring back the image which failed.
+pub async fn up(
+	app_dir: &Path,
+	service: Option<&str>,
+	env: &[(String, String)],
+) -> Result<(), Error> {
+	let compose_file = app_dir.join("compose.yml");
+
+	let rolled_back = rolled_back_services(app_dir).await?;
+	if rolled_back.is_empty() {
+		return compose::up_with_env(&compose_file, service, true, env)
+			.await
+			.map_err(Into::into);
+	}
+
+	let compose = fs::read_to_string(&compose_file)
+		.await
+		.with_message("Failed to read compose file")?
+		.parse::<Compose>()?;
+
+	let pull = compose
+		.services
+		.keys()
+		.map(String::as_str)
+		.filter(|s| !rolled_back.contains(*s))
+		.filter(|s| service.is_none_or(|service| service == *s))
+		.collect::<Vec<_>>();
+
+	if !pull.is_empty() {
+		compose::pull(&compose_file, &pull, env).await?;
+	}
+
+	compose::up_with_env(&compose_file, service, false, env)
+		.await
+		.map_err(Into::into)
+}
+
+/// Returns the image id of the container of every service
+async fn image_ids(
+	docker: &Docker,
+	id: &AppId,
+) -> Result<HashMap<String, String>, Error> {
+	let containers = docker.containers_by_composer_project(id.as_ref()).await?;
+
+	Ok(containers
+		.into_iter()
+		.filter_map(|c| {
+			let service = c.labels?.remove("com.docker.compose.service")?;
+			Some((service, c.image_id?))
+		})
+		.collect())
+}
+
+/// Returns the restart count of every container of the app
+async fn restart_counts(
+	docker: &Docker,
+	id: &AppId,
+) -> Result<HashMap<String, i64>, Error> {
+	let containers = docker.containers_by_composer_project(id.as_ref()).await?;
+
+	let mut counts = HashMap::new();
+	for container in containers.into_iter().filter_map(|c| c.id) {
+		let inspect = docker.inspect_container(&container).await?;
+		counts.insert(container, inspect.restart_count.unwrap_or_default());
+	}
+
+	Ok(counts)
+}
+
+/// Returns why the service is not running correctly
+///
+/// A container which was not recreated only fails if it restarted more often
+/// than before the deployment.
+async fn check_service(
+	docker: &Docker,
+	id: &AppId,
+	service: &str,
+	restarts_before: &HashMap<String, i64>,
+) -> Result<(), String> {
+	let containers = docker
+		.containers_by_composer_project(id.as_ref())
+		.await
+		.map_err(|e| e.to_string())?;
+
+	let containers = containers
+		.into_iter()
+		.filter(|c| {
+			c.labels.as_ref().is_some_and(|l| {
+				l.get("com.docker.compose.service")
+					.is_some_and(|s| s == service)
+			})
+		})
+		.filter_map(|c| c.id)
+		.collect::<Vec<_>>();
+
+	if containers.is_empty() {
+		return Err(format!("service {service} has no container"));
+	}
+
+	for container in containers {
+		let inspect = docker
+			.inspect_container(&container)
+			.await
+			.map_err(|e| e.to_string())?;
+
+		let before = restarts_before.get(&container).copied().unwrap_or(0);
+		if inspect.restart_count.is_some_and(|c| c > before) {
+			return Err(format!("service {service} restarted"));
+		}
+
+		let Some(state) = inspect.state else {
+			continue;
+		};
+
+		match state.status {
+			Some(ContainerStateStatusEnum::EXITED)
+			| Some(ContainerStateStatusEnum::DEAD) => {
+				return Err(format!(
+					"service {service} exited with code {}",
+					state.exit_code.unwrap_or_default()
+				));
+			}
+			Some(ContainerStateStatusEnum::RESTARTING) => {
+				return Err(format!("service {service} is restarting"));
+			}
+			_ => {}
+		}
+
+		if state
+			.health
+			.and_then(|h| h.status)
+			.is_some_and(|s| s == HealthStatusEnum::UNHEALTHY)
+		{
+			return Err(format!("service {service} is unhealthy"));
+		}
+	}
+
+	Ok(())
+}
+
+/// Checks the services until the grace period is over
+///
+/// A service which is still starting after the grace period counts as
+/// running.
+async fn watch(
+	docker: &Docker,
+	id: &AppId,
+	services: &[&str],
+	restarts_before: &HashMap<String, i64>,
+	grace_period: Duration,
+) -> Result<(), String> {
+	let deadline = Instant::now() + grace_period;
+
+	loop {
+		for service in services {
+			check_service(docker, id, service, restarts_before).await?;
+		}
+
+		if Instant::now() >= deadline {
+			return Ok(());
+		}
+
+		sleep(POLL_INTERVAL).await;
+	}
+}
+
+/// Pulls the new images and recreates or replaces the services depending on
+/// their deploy strategy
+async fn update_services(
+	docker: &Docker,
+	traefik: &Traefik,
+	id: &AppId,
+	compose_file: &Path,
+	compose: &Compose,
+	services: &[&str],
+	env: &[(String, String)],
+) -> Result<(), Error> {
+	let (start_first, stop_first): (Vec<_>, Vec<_>) =
+		services.iter().partition(|name| {
+			compose.services.get(**name).is_some_and(|s| {
+				s.hostdinghy.deploy == DeployStrategy::StartFirst
+			})
+		});
+
+	if !stop_first.is_empty() {
+		compose::up_services_with_env(compose_file, &stop_first, true, env)
+			.await?;
+	}
+
+	for name in start_first {
+		start_first::deploy_service(
+			docker,
+			traefik,
+			id,
+			compose_file,
+			name,
+			&compose.services[name],
+			env,
+		)
+		.await?;
+	}
+
+	Ok(())
+}
+
+/// Tags the previous images again and recreates the services
+///
+/// An image pinned by a digest cannot be tagged, but it also cannot have
+/// changed, so the service only gets recreated.
+async fn rollback(
+	compose_file: &Path,
+	compose: &Compose,
+	services: &[DeployedService],
+	env: &[(String, String)],
+) -> Result<(), Error> {
+	let mut names = vec![];
+	for service in services {
+		let (Some(previous), Some(compose_service)) = (
+			&service.previous_image_id,
+			compose.services.get(&service.name),
+		) else {
+			continue;
+		};
+
+		if !compose_service.image.contains('@') {
+			cmd(&["docker", "tag", previous, &compose_service.image])
+				.run()
+				.await?;
+		}
+		names.push(service.name.as_str());
+	}
+
+	// without any service docker compose would recreate all of them
+	if names.is_empty() {
+		return Ok(());
+	}
+
+	compose::up_services_with_env(compose_file, &names, false, env)
+		.await
+		.map_err(Into::into)
+}
+
+/// Pulls and recreates the services and watches them for the grace period
+///
+/// If a service fails the previous images are restored. The outcome is
+/// recorded and returned.
+#[allow(clippy::too_many_arguments)]
+pub async fn deploy(
+	docker: &Docker,
+	traefik: &Traefik,
+	id: &AppId,
+	app_dir: &Path,
+	compose: &Compose,
+	services: &[String],
+	env: &[(String, String)],
+	grace_period: Duration,
+) -> AppDeployment {
+	let created_on = DateTime::now();
+	let compose_file = app_dir.join("compose.yml");
+	let names = services.iter().map(String::as_str).collect::<Vec<_>>();
+
+	let previous = image_ids(docker, id).await.unwrap_or_else(|e| {
+		warn!("failed to get the current images of app {id} {e}");
+		HashMap::new()
+	});
+	let restarts_before =
+		restart_counts(docker, id).await.unwrap_or_else(|e| {
+			warn!("failed to get the restart counts of app {id} {e}");
+			HashMap::new()
+		});
+
+	let res = match update_services(
+		docker,
+		traefik,
+		id,
+		&compose_file,
+		compose,
+		&names,
+		env,
+	)
+	.await
+	{
+		Ok(()) => {
+			watch(docker, id, &names, &restarts_before, grace_period).await
+		}
+		Err(e) => Err(e.to_string()),
+	};
+
+	let current = image_ids(docker, id).await.unwrap_or_default();
+	let deployed = services
+		.iter()
+		.map(|name| DeployedService {
+			name: name.clone(),
+			previous_image_id: previous.get(name).cloned(),
+			image_id: current.get(name).cloned(),
+		})
+		.collect::<Vec<_>>();
+
+	let (status, message) = match res {
+		Ok(()) => (DeploymentStatus::Succeeded, None),
+		Err(msg) if deployed.iter().all(|s| s.previous_image_id.is_none()) => {
+			(DeploymentStatus::Failed, Some(msg))
+		}
+		Err(msg) => {
+			warn!("deployment of app {id} failed, rolling back: {msg}");
+
+			match rollback(&compose_file, compose, &deployed, env).await {
+				Ok(()) => (DeploymentStatus::RolledBack, Some(msg)),
+				Err(e) => (
+					DeploymentStatus::RollbackFailed,
+					Some(format!("{msg}, rollback failed: {e}")),
+				),
+			}
+		}
+	};
+
+	let deployment = AppDeployment {
+		created_on,
+		services: deployed,
+		status,
+		message,
+	};
+
+	if let Err(e) = record(app_dir, &deployment).await {
+		error!("failed to record deployment of app {id} {e}");
+	}
+
+	deployment
+}
@@ -0,0 +1,126 @@
+/*!
+Collects the hosts which are already claimed by the other apps, either in
+their compose file or by a router traefik currently knows.
+*/
+
+use std::path::Path;
+
+use api::{
+	apps::AppId,
+	error::{Error, WithMessage as _},
+};
+use compose_yml::{Compose, TraefikRule, domains::ClaimedHost};
+use tokio::fs;
+use tracing::warn;
+
+use crate::{
+	traefik::client::Traefik,
+	utils::{hostdinghy_dir, is_file},
+};
+
+/// Returns the compose file of every app, files which cannot be read are
+/// skipped
+async fn app_composes(
+	hostdinghy_dir: &Path,
+) -> Result<Vec<(AppId, Compose)>, Error> {
+	let mut entries = fs::read_dir(hostdinghy_dir)
+		.await
+		.with_message("Failed to read $HOSTDINGHY_DIR")?;
+
+	let mut apps = vec![];
+	while let Some(entry) = entries
+		.next_entry()
+		.await
+		.with_message("Failed to read $HOSTDINGHY_DIR")?
+	{
+		let Some(app_id) = entry
+			.file_name()
+			.to_str()
+			.and_then(|n| n.parse::<AppId>().ok())
+		else {
+			continue;
+		};
+
+		let compose_file = entry.path().join("compose.yml");
+		if !is_file(&compose_file).await {
+			continue;
+		}
+
+		let compose = match fs::read_to_string(&compose_file).await {
+			Ok(s) => s.parse::<Compose>(),
+			Err(e) => {
+				warn!("failed to read compose file of {app_id} {e}");
+				continue;
+			}
+		};
+
+		match compose {
+			Ok(c) => apps.push((app_id, c)),
+			Err(e) => warn!("failed to parse compose file of {app_id} {e}"),
+		}
+	}
+
+	Ok(apps)
+}
+
+/// Returns the app a router belongs to
+///
+/// Routers are named `{app_id}-{service}`, since an app id can contain a
+/// minus the longest matching app id wins.
+fn router_owner<'a>(
+	router: &str,
+	apps: impl Iterator<Item = &'a AppId>,
+) -> Option<&'a AppId> {
+	apps.filter(|id| {
+		router
+			.strip_prefix(id.as_ref())
+			.is_some_and(|r| r.starts_with('-'))
+	})
+	.max_by_key(|id| id.as_ref().len())
+}
+
+/// Returns the hosts claimed by everyone except the app
+///
+/// If traefik cannot be reached only the compose files are considered.
+pub async fn claimed_hosts(
+	traefik: &Traefik,
+	id: &AppId,
+) -> Result<Vec<ClaimedHost>, Error> {
+	let apps = app_composes(&hostdinghy_dir()?).await?;
+
+	let mut claimed = apps
+		.iter()
+		.filter(|(app_id, _)| app_id != id)
+		.flat_map(|(app_id, c)| c.claimed_hosts(Some(app_id.as_ref())))
+		.collect::<Vec<_>>();
+
+	let routers = traefik.routers().await.unwrap_or_else(|e| {
+		warn!("failed to get the routers from traefik {e}");
+		vec![]
+	});
+
+	for router in routers {
+		let name = router
+			.name
+			.split_once('@')
+			.map_or(router.name.as_str(), |(n, _)| n);
+
+		let app_ids = apps.iter().map(|(app_id, _)| app_id);
+		let owner = router_owner(name, app_ids.chain([id]));
+		if owner == Some(id) {
+			continue;
+		}
+
+		let Ok(rule) = router.rule.parse::<TraefikRule>() else {
+			continue;
+		};
+
+		claimed.extend(rule.hosts().into_iter().map(|host| ClaimedHost {
+			host: host.to_ascii_lowercase(),
+			router: name.to_string(),
+			app_id: owner.map(ToString::to_string),
+		}));
+	}
+
+	Ok(claimed)
+}
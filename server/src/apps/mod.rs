@@ -1,2 +1,9 @@
+pub mod deployments;
+mod domains;
+mod revisions;
 pub mod routes;
+pub mod secrets;
+mod shell;
+mod start_first;
 mod utils;
+mod volumes;
@@ -0,0 +1,201 @@
+/*!
+Every time a compose file gets saved a revision is stored in
+`$HOSTDINGHY_DIR/<id>/revisions/<revision>.toml`.
+
+The revision id is the time of the save in milliseconds since the unix epoch,
+bumped by one if another revision was saved in the same millisecond. Only the
+newest [`KEEP_REVISIONS`] revisions are kept.
+*/
+
+use std::{
+	io::ErrorKind,
+	path::{Path, PathBuf},
+};
+
+use api::{
+	apps::ComposeRevision,
+	error::{Error, WithMessage as _},
+};
+use chuchi_postgres::time::DateTime;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::utils::{is_file, read_toml, write_toml};
+
+/// How many revisions are kept per app, older ones get deleted on save
+pub const KEEP_REVISIONS: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RevisionFile {
+	created_on: DateTime,
+	author: Option<String>,
+	compose: String,
+}
+
+impl RevisionFile {
+	fn revision(&self, id: u64) -> ComposeRevision {
+		ComposeRevision {
+			id,
+			author: self.author.clone(),
+			created_on: self.created_on,
+		}
+	}
+}
+
+fn revision_path(app_dir: impl AsRef<Path>, id: u64) -> PathBuf {
+	app_dir
+		.as_ref()
+		.join("revisions")
+		.join(format!("{id}.toml"))
+}
+
+pub async fn save_revision(
+	app_dir: impl AsRef<Path>,
+	compose: &str,
+	author: Option<String>,
+) -> Result<ComposeRevision, Error> {
+	let revisions_dir = app_dir.as_ref().join("revisions");
+	match fs::create_dir(&revisions_dir).await {
+		Ok(()) => {}
+		Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+		Err(e) => {
+			return Err(Error::any("Failed to create revisions directory", e));
+		}
+	}
+
+	let created_on = DateTime::now();
+	let mut id = created_on.inner().timestamp_millis() as u64;
+	// another revision was saved in the same millisecond
+	while is_file(revision_path(&app_dir, id)).await {
+		id += 1;
+	}
+
+	let file = RevisionFile {
+		created_on,
+		author,
+		compose: compose.to_string(),
+	};
+	write_toml(&file, revision_path(&app_dir, id)).await?;
+
+	prune_revisions(&app_dir, KEEP_REVISIONS).await?;
+
+	Ok(file.revision(id))
+}
+
+/// Deletes every revision which is not one of the `keep_last` newest
+async fn prune_revisions(
+	app_dir: impl AsRef<Path>,
+	keep_last: usize,
+) -> Result<(), Error> {
+	let mut ids = revision_ids(&app_dir).await?;
+	ids.sort_unstable_by_key(|id| std::cmp::Reverse(*id));
+
+	for id in ids.into_iter().skip(keep_last) {
+		fs::remove_file(revision_path(&app_dir, id))
+			.await
+			.with_message("Failed to delete old revision")?;
+	}
+
+	Ok(())
+}
+
+async fn revision_ids(app_dir: impl AsRef<Path>) -> Result<Vec<u64>, Error> {
+	let revisions_dir = app_dir.as_ref().join("revisions");
+	let mut entries = match fs::read_dir(&revisions_dir).await {
+		Ok(e) => e,
+		// no revision was stored yet
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+		Err(e) => {
+			return Err(Error::any("Failed to read revisions directory", e));
+		}
+	};
+
+	let mut ids = vec![];
+	while let Some(entry) = entries
+		.next_entry()
+		.await
+		.with_message("Failed to read revisions directory")?
+	{
+		let id = entry
+			.file_name()
+			.to_str()
+			.and_then(|n| n.strip_suffix(".toml"))
+			.and_then(|n| n.parse::<u64>().ok());
+		ids.extend(id);
+	}
+
+	Ok(ids)
+}
+
+/// Returns the revisions sorted by the newest first
+pub async fn list_revisions(
+	app_dir: impl AsRef<Path>,
+) -> Result<Vec<ComposeRevision>, Error> {
+	let mut revisions = vec![];
+	for id in revision_ids(&app_dir).await? {
+		let file: RevisionFile = read_toml(revision_path(&app_dir, id)).await?;
+		revisions.push(file.revision(id));
+	}
+
+	revisions.sort_by_key(|r| std::cmp::Reverse(r.id));
+
+	Ok(revisions)
+}
+
+pub async fn read_revision(
+	app_dir: impl AsRef<Path>,
+	id: u64,
+) -> Result<(ComposeRevision, String), Error> {
+	let path = revision_path(app_dir, id);
+	if !is_file(&path).await {
+		return Err(Error::RevisionNotFound);
+	}
+
+	let file: RevisionFile = read_toml(&path).await?;
+
+	Ok((file.revision(id), file.compose))
+}
+
+/// Creates a unified diff between the two compose files
+pub fn diff(original: &str, modified: &str) -> String {
+	similar::TextDiff::from_lines(original, modified)
+		.unified_diff()
+		.header("original", "modified")
+		.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[tokio::test]
+	async fn same_millisecond_and_pruning() {
+		let dir = tempfile::tempdir().unwrap();
+
+		let mut ids = vec![];
+		for i in 0..KEEP_REVISIONS + 5 {
+			let rev = save_revision(dir.path(), &format!("# {i}"), None)
+				.await
+				.unwrap();
+			ids.push(rev.id);
+		}
+
+		// every save got its own id even if they happened in the same ms
+		let mut unique = ids.clone();
+		unique.dedup();
+		assert_eq!(unique.len(), ids.len());
+
+		let revisions = list_revisions(dir.path()).await.unwrap();
+		assert_eq!(revisions.len(), KEEP_REVISIONS);
+		assert_eq!(revisions[0].id, *ids.last().unwrap());
+
+		let (_, compose) =
+			read_revision(dir.path(), revisions[0].id).await.unwrap();
+		assert_eq!(compose, format!("# {}", KEEP_REVISIONS + 4));
+		assert!(matches!(
+			read_revision(dir.path(), ids[0]).await,
+			Err(Error::RevisionNotFound)
+		));
+	}
+}
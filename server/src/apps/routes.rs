@@ -1,35 +1,68 @@
-use std::{io::ErrorKind, sync::Arc};
+use std::{
+	convert::Infallible,
+	io::ErrorKind,
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
 use api::{
 	apps::{
-		AppId, AppInfoRes, AppService, ComposeCommand, GetComposeRes,
-		SaveComposeReq, ServiceState,
+		AppDeploymentsRes, AppId, AppInfoRes, AppLogsStreamReq, AppSecretsRes,
+		AppService, AppStatsRes, AppVolumesRes, ComposeCommand,
+		ComposeRevisionDiffReq, ComposeRevisionDiffRes, ComposeRevisionRes,
+		ComposeRevisionsRes, DeleteAppReq, Diagnostic, DiagnosticSeverity,
+		GetComposeRes, RollbackComposeReq, SaveComposeReq, SaveComposeRes,
+		ServiceState, SetSecretReq, ShellReq, ValidateComposeReq,
+		ValidateComposeRes, VolumesRestoreReq, VolumesSnapshotReq,
 	},
 	error::{Error, WithMessage},
+	requests::ApiTokenScope,
 };
 use axum::{
 	Json, Router,
-	extract::{Path, Query, State},
-	routing::{get, post},
+	body::Body,
+	extract::{Path, Query, State, WebSocketUpgrade},
+	response::{
+		Response,
+		sse::{Event, KeepAlive, Sse},
+	},
+	routing::{get, post, put},
+};
+use bollard::{
+	query_parameters::LogsOptionsBuilder, secret::ContainerSummaryStateEnum,
 };
-use compose_yml::Compose;
+use compose_yml::{Compose, ComposeError, diagnostics::diagnose};
+use futures::{Stream, StreamExt as _, TryStreamExt as _, future, stream};
 use serde::{Deserialize, Serialize};
-use tokio::fs;
+use tokio::{fs, io, time::sleep};
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::{info, warn};
 
 use crate::{
-	apps::utils::{
-		cont_sum_state_enum_to_service_state, container_names_to_service_name,
-		traefik_route_to_service_route,
+	apps::{
+		deployments::{self, DeployLocks},
+		domains, revisions, secrets, shell,
+		utils::{
+			app_ids, apply_container_inspect,
+			cont_sum_state_enum_to_service_state,
+			container_names_to_service_name, container_stats_to_service_stats,
+			log_output_to_lines, owns_resource, traefik_route_to_service_route,
+			validate_compose_with_docker,
+		},
+		volumes,
 	},
 	config::Config,
 	docker::Docker,
-	server::{Authenticated, router::AppState},
+	metrics::Metrics,
+	postgres::Client,
+	registry::{RemoveUser, remove_user},
+	server::{Authenticated, ScopeApps, ScopeRead, router::AppState},
 	traefik::client::Traefik,
-	utils::{compose, hostdinghy_dir, is_dir, is_file},
+	utils::{compose, hostdinghy_dir, is_dir, is_file, write_atomic},
 };
 
 async fn app_info(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRead>,
 	State(docker): State<Docker>,
 	State(traefik): State<Traefik>,
 	Path(id): Path<AppId>,
@@ -73,10 +106,27 @@ async fn app_info(
 					.unwrap_or(ServiceState::Unknown),
 				state_hr: s.status.unwrap_or_default(),
 				routes: vec![],
+				health: None,
+				restart_count: 0,
 			}
 		})
 		.collect::<Vec<_>>();
 
+	// the summary does not contain the health or the restart count
+	let inspects = future::join_all(
+		services
+			.iter()
+			.map(|s| docker.inspect_container(&s.container_name)),
+	)
+	.await;
+
+	for (inspect, service) in inspects.into_iter().zip(services.iter_mut()) {
+		// the container might have been removed in the meantime
+		if let Ok(inspect) = inspect {
+			apply_container_inspect(service, inspect);
+		}
+	}
+
 	// add any missing service
 	for (name, _) in compose.services {
 		if services.iter().any(|s| s.name == name) {
@@ -89,6 +139,8 @@ async fn app_info(
 			state: ServiceState::Unknown,
 			state_hr: "Service does not exist".to_string(),
 			routes: vec![],
+			health: None,
+			restart_count: 0,
 		});
 	}
 
@@ -123,8 +175,78 @@ async fn app_info(
 	Ok(Json(AppInfoRes { services }))
 }
 
+/// If the app folder does not exist only the database and the registry users
+/// get removed
+///
+/// Dropping the database or removing registry users requires the respective
+/// scope and only works for the ones belonging to the app.
+async fn delete_app(
+	auth: Authenticated<ScopeApps>,
+	State(config): State<Arc<Config>>,
+	State(locks): State<DeployLocks>,
+	Path(id): Path<AppId>,
+	Json(req): Json<DeleteAppReq>,
+) -> Result<(), Error> {
+	if req.database.is_some() {
+		auth.require(ApiTokenScope::Postgres)?;
+	}
+	if !req.registry_users.is_empty() {
+		auth.require(ApiTokenScope::Registry)?;
+	}
+
+	let hostdinghy_dir = hostdinghy_dir()?;
+	let apps = app_ids(&hostdinghy_dir).await?;
+
+	if let Some(database) = &req.database
+		&& !owns_resource(&id, &apps, database.as_ref())
+	{
+		return Err(Error::NotOwnedByApp {
+			name: database.to_string(),
+		});
+	}
+	for username in &req.registry_users {
+		if !owns_resource(&id, &apps, username.as_ref()) {
+			return Err(Error::NotOwnedByApp {
+				name: username.to_string(),
+			});
+		}
+	}
+
+	let _lock = locks.lock(&id).await;
+	let app_dir = hostdinghy_dir.join(id.as_ref());
+	if is_dir(&app_dir).await {
+		// the containers need to be removed while the compose file still exists
+		let compose_path = app_dir.join("compose.yml");
+		if is_file(&compose_path).await {
+			let env = secrets::compose_env(&app_dir, &config.secret).await?;
+			compose::down(&compose_path, req.volumes, &env).await?;
+		}
+
+		fs::remove_dir_all(&app_dir).await.with_message(format!(
+			"Failed to remove app directory {}",
+			app_dir.display()
+		))?;
+	}
+
+	if let Some(database) = &req.database {
+		let client = Client::new().await?;
+
+		client.drop_database(database.as_ref()).await?;
+		client.drop_user(database.as_ref()).await?;
+	}
+
+	for username in req.registry_users {
+		remove_user(RemoveUser {
+			username: username.into(),
+		})
+		.await?;
+	}
+
+	Ok(())
+}
+
 async fn get_compose(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRead>,
 	Path(id): Path<AppId>,
 ) -> Result<Json<GetComposeRes>, Error> {
 	// let's first check if the folder exists
@@ -149,17 +271,53 @@ async fn get_compose(
 
 	Ok(Json(GetComposeRes { compose }))
 }
+/// Checks everything a compose file needs to pass before it gets written
+///
+/// The policy or the domains of other apps might have changed since an old
+/// revision was saved, so a rollback needs to pass this as well.
+/// Returns the warnings of the policy.
+async fn check_compose(
+	config: &Config,
+	traefik: &Traefik,
+	id: &AppId,
+	app_dir: &std::path::Path,
+	compose: &str,
+) -> Result<Vec<ComposeError>, Error> {
+	// before doing anything let's validate a small part of the compose file
+	let parsed = compose.parse::<Compose>()?;
+	parsed.validate_for(&config.registry.domain, id.as_ref())?;
+	let warnings = parsed.check_policy(&config.compose_policy, app_dir)?;
+	for warning in &warnings {
+		warn!("compose file of {id}: {warning}");
+	}
+
+	// traefik would only use one of the routers if a host is used twice
+	let claimed = domains::claimed_hosts(traefik, id).await?;
+	parsed.check_domain_conflicts(&claimed)?;
+
+	// and let docker compose check the rest before we replace the file
+	let env = secrets::compose_env(app_dir, &config.secret).await?;
+	validate_compose_with_docker(app_dir, compose, &env).await?;
+
+	Ok(warnings)
+}
+
 async fn save_compose(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeApps>,
 	State(config): State<Arc<Config>>,
+	State(traefik): State<Traefik>,
+	State(locks): State<DeployLocks>,
 	Path(id): Path<AppId>,
 	Json(req): Json<SaveComposeReq>,
-) -> Result<(), Error> {
-	// before doing anything let's validate a small part of the compose file
-	let parsed = req.compose.parse::<Compose>()?;
-	parsed.validate_for(&config.registry.domain, id.as_ref())?;
-
+) -> Result<Json<SaveComposeRes>, Error> {
 	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+
+	let warnings =
+		check_compose(&config, &traefik, &id, &app_dir, &req.compose).await?;
+
+	// a running deployment should not see the file change underneath it
+	let _lock = locks.lock(&id).await;
+
 	match fs::create_dir(&app_dir).await {
 		Ok(()) => {}
 		Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
@@ -170,18 +328,178 @@ async fn save_compose(
 
 	// let's write the file
 	let compose_path = app_dir.join("compose.yml");
-	fs::write(&compose_path, req.compose)
+	write_atomic(&compose_path, &req.compose)
 		.await
 		.with_message(format!(
 			"Failed to write compose file to {}",
 			compose_path.display()
 		))?;
 
+	revisions::save_revision(&app_dir, &req.compose, req.author).await?;
+
+	Ok(Json(SaveComposeRes { warnings }))
+}
+
+async fn validate_compose(
+	_auth: Authenticated<ScopeRead>,
+	State(config): State<Arc<Config>>,
+	State(traefik): State<Traefik>,
+	Path(id): Path<AppId>,
+	Json(req): Json<ValidateComposeReq>,
+) -> Result<Json<ValidateComposeRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	let claimed = domains::claimed_hosts(&traefik, &id).await?;
+
+	let mut diagnostics = diagnose(
+		&req.compose,
+		&config.registry.domain,
+		id.as_ref(),
+		&config.compose_policy,
+		&app_dir,
+		&claimed,
+	);
+
+	// docker compose only gets asked if we could not find an error
+	if !diagnostics.iter().any(|d| d.is_error()) {
+		let env = secrets::compose_env(&app_dir, &config.secret).await?;
+		match validate_compose_with_docker(&app_dir, &req.compose, &env).await {
+			Ok(()) => {}
+			Err(Error::Compose(ComposeError::Config { errors })) => {
+				let errors = errors.into_iter().map(|message| Diagnostic {
+					severity: DiagnosticSeverity::Error,
+					message,
+					line: None,
+					column: None,
+					path: vec![],
+				});
+				diagnostics.splice(0..0, errors);
+			}
+			Err(e) => return Err(e),
+		}
+	}
+
+	Ok(Json(ValidateComposeRes { diagnostics }))
+}
+
+async fn compose_revisions(
+	_auth: Authenticated<ScopeRead>,
+	Path(id): Path<AppId>,
+) -> Result<Json<ComposeRevisionsRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	revisions::list_revisions(&app_dir)
+		.await
+		.map(|r| Json(ComposeRevisionsRes(r)))
+}
+
+async fn compose_revision(
+	_auth: Authenticated<ScopeRead>,
+	Path((id, revision)): Path<(AppId, u64)>,
+) -> Result<Json<ComposeRevisionRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let (revision, compose) =
+		revisions::read_revision(&app_dir, revision).await?;
+
+	Ok(Json(ComposeRevisionRes { revision, compose }))
+}
+
+async fn compose_revision_diff(
+	_auth: Authenticated<ScopeRead>,
+	Path((id, revision)): Path<(AppId, u64)>,
+	Query(req): Query<ComposeRevisionDiffReq>,
+) -> Result<Json<ComposeRevisionDiffRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let (_, original) = revisions::read_revision(&app_dir, revision).await?;
+
+	let modified = match req.against {
+		Some(against) => revisions::read_revision(&app_dir, against).await?.1,
+		None => {
+			let compose_path = app_dir.join("compose.yml");
+			if is_file(&compose_path).await {
+				fs::read_to_string(&compose_path).await.with_message(
+					format!(
+						"Failed to read compose {} file",
+						compose_path.display()
+					),
+				)?
+			} else {
+				String::new()
+			}
+		}
+	};
+
+	Ok(Json(ComposeRevisionDiffRes {
+		diff: revisions::diff(&original, &modified),
+		original,
+		modified,
+	}))
+}
+
+async fn rollback_compose(
+	_auth: Authenticated<ScopeApps>,
+	State(config): State<Arc<Config>>,
+	State(traefik): State<Traefik>,
+	State(locks): State<DeployLocks>,
+	Path((id, revision)): Path<(AppId, u64)>,
+	Json(req): Json<RollbackComposeReq>,
+) -> Result<(), Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let (_, compose) = revisions::read_revision(&app_dir, revision).await?;
+	check_compose(&config, &traefik, &id, &app_dir, &compose).await?;
+
+	let _lock = locks.lock(&id).await;
+
+	let compose_path = app_dir.join("compose.yml");
+	write_atomic(&compose_path, &compose)
+		.await
+		.with_message(format!(
+			"Failed to write compose file to {}",
+			compose_path.display()
+		))?;
+
+	// the rollback itself is a new revision
+	revisions::save_revision(&app_dir, &compose, req.author).await?;
+
+	let env = secrets::compose_env(&app_dir, &config.secret).await?;
+	deployments::up(&app_dir, None, &env).await?;
+
 	Ok(())
 }
 
+async fn list_deployments(
+	_auth: Authenticated<ScopeRead>,
+	Path(id): Path<AppId>,
+) -> Result<Json<AppDeploymentsRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	deployments::list(&app_dir)
+		.await
+		.map(|d| Json(AppDeploymentsRes(d)))
+}
+
 async fn compose_action(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeApps>,
+	State(config): State<Arc<Config>>,
+	State(metrics): State<Metrics>,
+	State(locks): State<DeployLocks>,
 	Path((id, command)): Path<(AppId, ComposeCommand)>,
 ) -> Result<(), Error> {
 	let app_dir = hostdinghy_dir()?.join(id.as_ref());
@@ -194,18 +512,39 @@ async fn compose_action(
 		return Err(Error::AppNotFound);
 	}
 
-	match command {
-		ComposeCommand::Start => compose::start(&compose_path, None).await,
-		ComposeCommand::Up => compose::up(&compose_path, None).await,
-		ComposeCommand::Restart => compose::restart(&compose_path, None).await,
-		ComposeCommand::Stop => compose::stop(&compose_path, None).await,
-	}?;
+	let env = secrets::compose_env(&app_dir, &config.secret).await?;
+	let _lock = locks.lock(&id).await;
+
+	let start = Instant::now();
+	let res = match &command {
+		ComposeCommand::Start => compose::start(&compose_path, None, &env)
+			.await
+			.map_err(Into::into),
+		ComposeCommand::Up => deployments::up(&app_dir, None, &env).await,
+		ComposeCommand::Restart => compose::restart(&compose_path, None, &env)
+			.await
+			.map_err(Into::into),
+		ComposeCommand::Stop => compose::stop(&compose_path, None, &env)
+			.await
+			.map_err(Into::into),
+	};
+
+	metrics.observe_compose_action(
+		id.as_ref(),
+		&command.to_string(),
+		start.elapsed(),
+		res.is_ok(),
+	);
+	res?;
 
 	Ok(())
 }
 
 async fn compose_service_action(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeApps>,
+	State(config): State<Arc<Config>>,
+	State(metrics): State<Metrics>,
+	State(locks): State<DeployLocks>,
 	Path((id, service, command)): Path<(AppId, String, ComposeCommand)>,
 ) -> Result<(), Error> {
 	let app_dir = hostdinghy_dir()?.join(id.as_ref());
@@ -218,18 +557,38 @@ async fn compose_service_action(
 		return Err(Error::AppNotFound);
 	}
 
-	match command {
+	let env = secrets::compose_env(&app_dir, &config.secret).await?;
+	let _lock = locks.lock(&id).await;
+
+	let start = Instant::now();
+	let res = match &command {
 		ComposeCommand::Start => {
-			compose::start(&compose_path, Some(&service)).await
+			compose::start(&compose_path, Some(&service), &env)
+				.await
+				.map_err(Into::into)
+		}
+		ComposeCommand::Up => {
+			deployments::up(&app_dir, Some(&service), &env).await
 		}
-		ComposeCommand::Up => compose::up(&compose_path, Some(&service)).await,
 		ComposeCommand::Restart => {
-			compose::restart(&compose_path, Some(&service)).await
+			compose::restart(&compose_path, Some(&service), &env)
+				.await
+				.map_err(Into::into)
 		}
 		ComposeCommand::Stop => {
-			compose::stop(&compose_path, Some(&service)).await
+			compose::stop(&compose_path, Some(&service), &env)
+				.await
+				.map_err(Into::into)
 		}
-	}?;
+	};
+
+	metrics.observe_compose_action(
+		id.as_ref(),
+		&command.to_string(),
+		start.elapsed(),
+		res.is_ok(),
+	);
+	res?;
 
 	Ok(())
 }
@@ -241,7 +600,8 @@ pub struct LogsQueryReq {
 }
 
 async fn logs(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRead>,
+	State(config): State<Arc<Config>>,
 	Path(id): Path<AppId>,
 	Query(req): Query<LogsQueryReq>,
 ) -> Result<String, Error> {
@@ -255,19 +615,398 @@ async fn logs(
 		return Err(Error::AppNotFound);
 	}
 
-	compose::logs(&compose_path, req.lines)
+	let env = secrets::compose_env(&app_dir, &config.secret).await?;
+	compose::logs(&compose_path, req.lines, &env)
 		.await
 		.map_err(Into::into)
 }
 
+async fn logs_stream(
+	_auth: Authenticated<ScopeRead>,
+	State(docker): State<Docker>,
+	Path(id): Path<AppId>,
+	Query(req): Query<AppLogsStreamReq>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let containers = docker
+		.containers_by_composer_project(id.as_ref())
+		.await
+		.with_message("Failed to list Docker services")?;
+
+	let mut options = LogsOptionsBuilder::new()
+		.follow(req.follow)
+		.stdout(req.stdout)
+		.stderr(req.stderr)
+		// the timestamps get parsed by log_output_to_lines
+		.timestamps(true)
+		.tail(&req.lines.map_or("all".into(), |l| l.to_string()));
+	// docker only accepts 32 bit timestamps
+	if let Some(since) = &req.since {
+		let since = i32::try_from(since.inner().timestamp())
+			.with_message("since is out of range")?;
+		options = options.since(since);
+	}
+	if let Some(until) = &req.until {
+		let until = i32::try_from(until.inner().timestamp())
+			.with_message("until is out of range")?;
+		options = options.until(until);
+	}
+	let options = options.build();
+
+	let streams = containers
+		.into_iter()
+		.filter_map(|c| {
+			let service = c
+				.labels
+				.as_ref()?
+				.get("com.docker.compose.service")?
+				.clone();
+			if req.service.as_ref().is_some_and(|s| s != &service) {
+				return None;
+			}
+
+			let container_name = container_names_to_service_name(&c.names)?;
+
+			let stream = docker
+				.logs(&container_name, options.clone())
+				.flat_map(move |r| {
+					stream::iter(match r {
+						Ok(output) => log_output_to_lines(&service, output)
+							.into_iter()
+							.map(Ok)
+							.collect(),
+						Err(e) => vec![Err(Error::from(e))],
+					})
+				});
+
+			Some(stream)
+		})
+		.collect::<Vec<_>>();
+
+	if req.service.is_some() && streams.is_empty() {
+		return Err(Error::ServiceNotFound);
+	}
+
+	let stream = stream::select_all(streams).map(|r| {
+		let event = match r {
+			Ok(line) => Event::default().json_data(line),
+			Err(e) => Event::default().event("error").json_data(e),
+		};
+
+		// serializing to json should never fail
+		Ok(event.unwrap())
+	});
+
+	Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn list_secrets(
+	_auth: Authenticated<ScopeApps>,
+	Path(id): Path<AppId>,
+) -> Result<Json<AppSecretsRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let names = secrets::list(&app_dir).await?;
+
+	Ok(Json(AppSecretsRes { names }))
+}
+
+async fn set_secret(
+	_auth: Authenticated<ScopeApps>,
+	State(config): State<Arc<Config>>,
+	State(locks): State<DeployLocks>,
+	Path((id, name)): Path<(AppId, String)>,
+	Json(req): Json<SetSecretReq>,
+) -> Result<(), Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let _lock = locks.lock(&id).await;
+	secrets::set(&app_dir, &config.secret, &name, &req.value).await
+}
+
+async fn unset_secret(
+	_auth: Authenticated<ScopeApps>,
+	State(locks): State<DeployLocks>,
+	Path((id, name)): Path<(AppId, String)>,
+) -> Result<(), Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let _lock = locks.lock(&id).await;
+	secrets::unset(&app_dir, &name).await
+}
+
+async fn list_volumes(
+	_auth: Authenticated<ScopeRead>,
+	State(docker): State<Docker>,
+	Path(id): Path<AppId>,
+) -> Result<Json<AppVolumesRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let volumes = volumes::app_volumes(&docker, id.as_ref()).await?;
+
+	Ok(Json(AppVolumesRes {
+		volumes: volumes.iter().map(|v| v.to_api()).collect(),
+	}))
+}
+
+async fn volumes_snapshot(
+	_auth: Authenticated<ScopeApps>,
+	State(docker): State<Docker>,
+	Path(id): Path<AppId>,
+	Query(req): Query<VolumesSnapshotReq>,
+) -> Result<Body, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let volumes = volumes::app_volumes(&docker, id.as_ref()).await?;
+
+	let mut child = volumes::snapshot(&volumes, req.compression).await?;
+
+	// lets wait until the process hopefully has decided
+	// if the operation will work or not
+	sleep(Duration::from_millis(10)).await;
+
+	if child.exited_with_error() {
+		match child.wait().await {
+			Ok(()) => unreachable!("child exit status changed"),
+			Err(e) => return Err(e.into()),
+		};
+	}
+
+	// make sure to wait until the child has exited in the stream
+	child.wait_for_child_exit(true);
+
+	Ok(Body::from_stream(ReaderStream::new(child)))
+}
+
+async fn volumes_restore(
+	_auth: Authenticated<ScopeApps>,
+	State(config): State<Arc<Config>>,
+	State(locks): State<DeployLocks>,
+	State(docker): State<Docker>,
+	Path(id): Path<AppId>,
+	Query(req): Query<VolumesRestoreReq>,
+	body: Body,
+) -> Result<(), Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	let compose_path = app_dir.join("compose.yml");
+	if !is_file(&compose_path).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let volumes = volumes::app_volumes(&docker, id.as_ref()).await?;
+
+	// first extract everything, this way a broken archive
+	// does not leave the volumes half restored
+	let tmp_dir = tempfile::Builder::new()
+		.prefix(".restore-")
+		.tempdir_in(&app_dir)
+		.with_message("Failed to create temporary directory")?;
+
+	let mut body =
+		StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+
+	let mut child = volumes::extract(tmp_dir.path(), req.compression).await?;
+
+	io::copy(&mut body, &mut child)
+		.await
+		.with_message("Failed to extract volume snapshot")?;
+
+	child
+		.wait()
+		.await
+		.with_message("Volume snapshot extraction failed")?;
+
+	let env = secrets::compose_env(&app_dir, &config.secret).await?;
+	let _lock = locks.lock(&id).await;
+	compose::stop(&compose_path, None, &env).await?;
+
+	let restored = volumes::replace_from(tmp_dir.path(), &volumes).await;
+
+	// start the app again even if the restore failed
+	compose::start(&compose_path, None, &env).await?;
+
+	info!("restored volumes {:?} of app {id}", restored?);
+
+	Ok(())
+}
+
+/// Returns the service and container name of all running containers
+async fn running_containers(
+	docker: &Docker,
+	id: &AppId,
+) -> Result<Vec<(String, String)>, Error> {
+	let containers = docker
+		.containers_by_composer_project(id.as_ref())
+		.await
+		.with_message("Failed to list Docker services")?;
+
+	Ok(containers
+		.into_iter()
+		.filter(|c| c.state == Some(ContainerSummaryStateEnum::RUNNING))
+		.filter_map(|c| {
+			let service = c
+				.labels
+				.as_ref()?
+				.get("com.docker.compose.service")?
+				.clone();
+			let container_name = container_names_to_service_name(&c.names)?;
+
+			Some((service, container_name))
+		})
+		.collect())
+}
+
+async fn stats(
+	_auth: Authenticated<ScopeRead>,
+	State(docker): State<Docker>,
+	Path(id): Path<AppId>,
+) -> Result<Json<AppStatsRes>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let containers = running_containers(&docker, &id).await?;
+
+	let services =
+		future::try_join_all(containers.iter().map(|(service, container)| {
+			let mut stream = docker.stats(container, false);
+
+			async move {
+				let stats = stream.try_next().await?;
+
+				Ok::<_, Error>(stats.map(|s| {
+					container_stats_to_service_stats(service, container, s)
+				}))
+			}
+		}))
+		.await?;
+
+	Ok(Json(AppStatsRes {
+		services: services.into_iter().flatten().collect(),
+	}))
+}
+
+async fn stats_stream(
+	_auth: Authenticated<ScopeRead>,
+	State(docker): State<Docker>,
+	Path(id): Path<AppId>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let containers = running_containers(&docker, &id).await?;
+
+	let streams = containers
+		.into_iter()
+		.map(|(service, container)| {
+			docker.stats(&container, true).map(move |r| {
+				r.map(|s| {
+					container_stats_to_service_stats(&service, &container, s)
+				})
+				.map_err(Error::from)
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let stream = stream::select_all(streams).map(|r| {
+		let event = match r {
+			Ok(stats) => Event::default().json_data(stats),
+			Err(e) => Event::default().event("error").json_data(e),
+		};
+
+		// serializing to json should never fail
+		Ok(event.unwrap())
+	});
+
+	Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+async fn service_shell(
+	_auth: Authenticated<ScopeApps>,
+	State(docker): State<Docker>,
+	Path((id, service)): Path<(AppId, String)>,
+	Query(req): Query<ShellReq>,
+	ws: WebSocketUpgrade,
+) -> Result<Response, Error> {
+	let app_dir = hostdinghy_dir()?.join(id.as_ref());
+	if !is_dir(&app_dir).await {
+		return Err(Error::AppNotFound);
+	}
+
+	let container = running_containers(&docker, &id)
+		.await?
+		.into_iter()
+		.find(|(s, _)| s == &service)
+		.map(|(_, c)| c)
+		.ok_or(Error::ServiceNotFound)?;
+
+	let cmd = match &req.shell {
+		Some(shell) => vec![shell.clone()],
+		None => vec![
+			"sh".into(),
+			"-c".into(),
+			"command -v bash >/dev/null && exec bash || exec sh".into(),
+		],
+	};
+
+	// the exec is only created once the client is connected, otherwise it
+	// would keep running in the container if the upgrade fails
+	Ok(ws.on_upgrade(move |socket| {
+		shell::run_session(docker, container, cmd, req, socket)
+	}))
+}
+
 pub fn routes() -> Router<AppState> {
 	Router::new()
-		.route("/{id}", get(app_info))
+		.route("/{id}", get(app_info).delete(delete_app))
 		.route("/{id}/compose", get(get_compose).post(save_compose))
+		.route("/{id}/compose/validate", post(validate_compose))
+		.route("/{id}/compose/revisions", get(compose_revisions))
+		.route("/{id}/compose/revisions/{revision}", get(compose_revision))
+		.route(
+			"/{id}/compose/revisions/{revision}/diff",
+			get(compose_revision_diff),
+		)
+		.route(
+			"/{id}/compose/revisions/{revision}/rollback",
+			post(rollback_compose),
+		)
+		.route("/{id}/deployments", get(list_deployments))
 		.route("/{id}/action/{cmd}", post(compose_action))
 		.route(
 			"/{id}/service/{service}/action/{cmd}",
 			post(compose_service_action),
 		)
+		.route("/{id}/service/{service}/shell", get(service_shell))
 		.route("/{id}/logs", get(logs))
+		.route("/{id}/logs/stream", get(logs_stream))
+		.route("/{id}/secrets", get(list_secrets))
+		.route("/{id}/secrets/{name}", put(set_secret).delete(unset_secret))
+		.route("/{id}/volumes", get(list_volumes))
+		.route("/{id}/volumes/snapshot", get(volumes_snapshot))
+		.route("/{id}/volumes/restore", put(volumes_restore))
+		.route("/{id}/stats", get(stats))
+		.route("/{id}/stats/stream", get(stats_stream))
 }
@@ -0,0 +1,223 @@
+/*!
+The secrets of an app are stored in `$HOSTDINGHY_DIR/<id>/secrets.toml`, every
+value is encrypted with a key derived from `Config::secret`.
+
+They get passed as environment variables to every `docker compose` command of
+the app so they can be used with interpolation, for example `DB_PASSWORD: ${DB_PASSWORD}`.
+*/
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use api::error::Error;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chuchi_crypto::hash::Hasher;
+use ring::{
+	aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+	rand::{SecureRandom as _, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+	config::SecretToken,
+	utils::{is_file, read_toml, write_toml},
+};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SecretsFile {
+	/// base64 encoded nonce followed by the encrypted value
+	#[serde(default)]
+	secrets: BTreeMap<String, String>,
+}
+
+fn secrets_path(app_dir: impl AsRef<Path>) -> PathBuf {
+	app_dir.as_ref().join("secrets.toml")
+}
+
+async fn read_file(app_dir: impl AsRef<Path>) -> Result<SecretsFile, Error> {
+	let path = secrets_path(app_dir);
+	if !is_file(&path).await {
+		return Ok(SecretsFile::default());
+	}
+
+	read_toml(&path).await.map_err(Into::into)
+}
+
+/// Variables which change how docker compose or the processes it spawns
+/// behave, a secret should never be able to override them
+const RESERVED_NAMES: &[&str] = &[
+	"PATH",
+	"HOME",
+	"LD_PRELOAD",
+	"LD_LIBRARY_PATH",
+	"DOCKER_HOST",
+	"DOCKER_CONFIG",
+	"COMPOSE_FILE",
+	"COMPOSE_PROJECT_NAME",
+];
+
+const RESERVED_PREFIXES: &[&str] = &["DOCKER_", "COMPOSE_"];
+
+fn is_reserved(name: &str) -> bool {
+	RESERVED_NAMES.contains(&name)
+		|| RESERVED_PREFIXES.iter().any(|p| name.starts_with(p))
+}
+
+/// Only letters, digits and underscores are allowed and the name cannot
+/// start with a digit, the same as for environment variables
+///
+/// Names used by docker compose itself are rejected.
+pub fn validate_name(name: &str) -> Result<(), Error> {
+	let valid = name
+		.chars()
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+		&& !is_reserved(name);
+
+	if valid {
+		Ok(())
+	} else {
+		Err(Error::InvalidSecretName)
+	}
+}
+
+fn key(secret: &SecretToken) -> LessSafeKey {
+	let mut hasher = Hasher::new();
+	hasher.update(b"hostdinghy app secrets");
+	hasher.update(secret.as_ref());
+	let hash = hasher.finalize().to_bytes();
+
+	// the hash is 64 bytes long so this can never fail
+	LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &hash[..32]).unwrap())
+}
+
+fn encrypt(
+	secret: &SecretToken,
+	name: &str,
+	value: &str,
+) -> Result<String, Error> {
+	let mut nonce = [0u8; NONCE_LEN];
+	SystemRandom::new()
+		.fill(&mut nonce)
+		.map_err(|e| Error::any("Failed to generate nonce", e))?;
+
+	let mut data = value.as_bytes().to_vec();
+	// the name is used as additional data so a value cannot be moved
+	// to another name
+	key(secret)
+		.seal_in_place_append_tag(
+			Nonce::assume_unique_for_key(nonce),
+			Aad::from(name),
+			&mut data,
+		)
+		.map_err(|e| Error::any("Failed to encrypt secret", e))?;
+
+	let mut out = nonce.to_vec();
+	out.extend(data);
+
+	Ok(STANDARD.encode(out))
+}
+
+fn decrypt(
+	secret: &SecretToken,
+	name: &str,
+	value: &str,
+) -> Result<String, Error> {
+	let data = STANDARD.decode(value).map_err(|e| {
+		Error::any(format!("Failed to decode secret {name}"), e)
+	})?;
+	if data.len() < NONCE_LEN {
+		return Err(Error::any(
+			format!("Failed to decrypt secret {name}"),
+			"value too short",
+		));
+	}
+
+	let (nonce, data) = data.split_at(NONCE_LEN);
+	let mut data = data.to_vec();
+	let plain = key(secret)
+		.open_in_place(
+			// the length was checked above
+			Nonce::try_assume_unique_for_key(nonce).unwrap(),
+			Aad::from(name),
+			&mut data,
+		)
+		.map_err(|e| {
+			Error::any(format!("Failed to decrypt secret {name}"), e)
+		})?;
+
+	String::from_utf8(plain.to_vec())
+		.map_err(|e| Error::any(format!("Failed to decrypt secret {name}"), e))
+}
+
+/// Returns the names of all secrets
+pub async fn list(app_dir: impl AsRef<Path>) -> Result<Vec<String>, Error> {
+	read_file(app_dir)
+		.await
+		.map(|f| f.secrets.into_keys().collect())
+}
+
+/// Stores the encrypted secret
+///
+/// This reads and rewrites the whole file, hold the app's deploy lock.
+pub async fn set(
+	app_dir: impl AsRef<Path>,
+	secret: &SecretToken,
+	name: &str,
+	value: &str,
+) -> Result<(), Error> {
+	validate_name(name)?;
+
+	let mut file = read_file(&app_dir).await?;
+	file.secrets
+		.insert(name.to_string(), encrypt(secret, name, value)?);
+
+	write_toml(&file, secrets_path(&app_dir))
+		.await
+		.map_err(Into::into)
+}
+
+/// Removes the secret
+///
+/// This reads and rewrites the whole file, hold the app's deploy lock.
+pub async fn unset(app_dir: impl AsRef<Path>, name: &str) -> Result<(), Error> {
+	let mut file = read_file(&app_dir).await?;
+	if file.secrets.remove(name).is_none() {
+		return Err(Error::SecretNotFound);
+	}
+
+	write_toml(&file, secrets_path(&app_dir))
+		.await
+		.map_err(Into::into)
+}
+
+/// Returns all decrypted secrets, ready to be passed to `docker compose`
+///
+/// Secrets with a reserved name are skipped, they could have been stored
+/// before the name was reserved.
+pub async fn compose_env(
+	app_dir: impl AsRef<Path>,
+	secret: &SecretToken,
+) -> Result<Vec<(String, String)>, Error> {
+	read_file(app_dir)
+		.await?
+		.secrets
+		.into_iter()
+		.filter(|(name, _)| {
+			let reserved = is_reserved(name);
+			if reserved {
+				warn!("skipping secret {name}, the name is reserved");
+			}
+			!reserved
+		})
+		.map(|(name, value)| {
+			let value = decrypt(secret, &name, &value)?;
+			Ok((name, value))
+		})
+		.collect()
+}
@@ -0,0 +1,133 @@
+/*!
+Bridges a WebSocket with a tty exec session in a container.
+
+Binary messages are the raw terminal input and output, text messages contain
+a json `ShellControl`.
+*/
+
+use api::{
+	apps::{ShellControl, ShellReq},
+	error::{Error, WithMessage as _},
+};
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use bollard::container::LogOutput;
+use futures::StreamExt as _;
+use tokio::io::AsyncWriteExt as _;
+use tracing::warn;
+
+use crate::docker::{Docker, ExecSession};
+
+/// Starts the shell in the container and bridges it with the socket
+pub async fn run_session(
+	docker: Docker,
+	container: String,
+	cmd: Vec<String>,
+	req: ShellReq,
+	mut socket: WebSocket,
+) {
+	let session = match start(&docker, &container, cmd, &req).await {
+		Ok(s) => s,
+		Err(e) => {
+			warn!("failed to start shell: {e}");
+			let frame = CloseFrame {
+				code: close_code::ERROR,
+				reason: e.to_string().into(),
+			};
+			let _ = socket.send(Message::Close(Some(frame))).await;
+			return;
+		}
+	};
+
+	if let Err(e) = bridge(&docker, session, socket).await {
+		warn!("shell session failed: {e}");
+	}
+}
+
+async fn start(
+	docker: &Docker,
+	container: &str,
+	cmd: Vec<String>,
+	req: &ShellReq,
+) -> Result<ExecSession, Error> {
+	let session = docker
+		.exec_tty(container, cmd, vec!["TERM=xterm-256color".into()])
+		.await?;
+
+	if let (Some(cols), Some(rows)) = (req.cols, req.rows) {
+		docker.resize_exec(&session.id, cols, rows).await?;
+	}
+
+	Ok(session)
+}
+
+async fn bridge(
+	docker: &Docker,
+	session: ExecSession,
+	mut socket: WebSocket,
+) -> Result<(), Error> {
+	let ExecSession {
+		id,
+		mut input,
+		mut output,
+	} = session;
+
+	loop {
+		tokio::select! {
+			msg = socket.recv() => {
+				// the client went away
+				let Some(msg) = msg else {
+					return Ok(());
+				};
+
+				match msg.with_message("Failed to read websocket message")? {
+					Message::Binary(b) => {
+						input
+							.write_all(&b)
+							.await
+							.with_message("Failed to write to shell")?;
+					}
+					Message::Text(t) => {
+						// the client is only allowed to resize
+						if let Ok(ShellControl::Resize { cols, rows }) =
+							serde_json::from_str(t.as_str())
+						{
+							docker.resize_exec(&id, cols, rows).await?;
+						}
+					}
+					Message::Close(_) => return Ok(()),
+					_ => {}
+				}
+			}
+			out = output.next() => {
+				let Some(out) = out else {
+					break;
+				};
+
+				let bytes = match out? {
+					LogOutput::StdOut { message }
+					| LogOutput::StdErr { message }
+					| LogOutput::Console { message } => message,
+					LogOutput::StdIn { .. } => continue,
+				};
+
+				socket
+					.send(Message::Binary(bytes))
+					.await
+					.with_message("Failed to send shell output")?;
+			}
+		}
+	}
+
+	// the shell exited
+	let code = docker.exec_exit_code(&id).await?;
+	let exit = serde_json::to_string(&ShellControl::Exit { code })
+		.with_message("Failed to serialize exit message")?;
+
+	socket
+		.send(Message::Text(exit.into()))
+		.await
+		.with_message("Failed to send exit message")?;
+	let _ = socket.send(Message::Close(None)).await;
+
+	Ok(())
+}
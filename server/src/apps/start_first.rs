@@ -0,0 +1,198 @@
+/*!
+Zero-downtime deployment of a single service.
+
+The service gets scaled to twice its containers without recreating the
+existing ones, so the new containers run next to the old ones. Once all new
+containers are healthy and, if the service is routed by traefik, registered in
+its load balancer, the old containers get removed. If the new containers don't
+become ready they are removed and the old ones keep running.
+
+This is selected with `x-hostdinghy: { deploy: start-first }` on the service.
+*/
+
+use std::{path::Path, time::Duration};
+
+use api::{
+	apps::AppId,
+	error::{Error, WithMessage as _},
+};
+use bollard::secret::{ContainerStateStatusEnum, HealthStatusEnum};
+use compose_yml::ComposeService;
+use tokio::time::{Instant, sleep};
+use tracing::{info, warn};
+
+use crate::{docker::Docker, traefik::client::Traefik, utils::compose};
+
+const READY_TIMEOUT: Duration = Duration::from_secs(120);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns the ids of all containers of the service
+async fn service_containers(
+	docker: &Docker,
+	id: &AppId,
+	service: &str,
+) -> Result<Vec<String>, Error> {
+	let containers = docker.containers_by_composer_project(id.as_ref()).await?;
+
+	Ok(containers
+		.into_iter()
+		.filter(|c| {
+			c.labels.as_ref().is_some_and(|l| {
+				l.get("com.docker.compose.service")
+					.is_some_and(|s| s == service)
+			})
+		})
+		.filter_map(|c| c.id)
+		.collect())
+}
+
+/// Returns the ips of the container if it is ready
+///
+/// A container is ready if it is running and healthy, if it has no
+/// healthcheck running is enough.
+async fn ready_container(
+	docker: &Docker,
+	container: &str,
+) -> Result<Option<Vec<String>>, Error> {
+	let inspect = docker.inspect_container(container).await?;
+	let state = inspect.state.unwrap_or_default();
+
+	match state.status {
+		Some(ContainerStateStatusEnum::RUNNING) => {}
+		Some(ContainerStateStatusEnum::EXITED)
+		| Some(ContainerStateStatusEnum::DEAD) => {
+			return Err(Error::any(
+				"New container exited",
+				format!("exit code {}", state.exit_code.unwrap_or_default()),
+			));
+		}
+		_ => return Ok(None),
+	}
+
+	match state.health.and_then(|h| h.status) {
+		Some(HealthStatusEnum::UNHEALTHY) => {
+			return Err(Error::any("New container is unhealthy", container));
+		}
+		Some(HealthStatusEnum::HEALTHY)
+		| Some(HealthStatusEnum::NONE)
+		| Some(HealthStatusEnum::EMPTY)
+		| None => {}
+		Some(HealthStatusEnum::STARTING) => return Ok(None),
+	}
+
+	let ips = inspect
+		.network_settings
+		.and_then(|n| n.networks)
+		.into_iter()
+		.flat_map(|n| n.into_values())
+		.filter_map(|n| n.ip_address)
+		.filter(|ip| !ip.is_empty())
+		.collect();
+
+	Ok(Some(ips))
+}
+
+/// Waits until all containers are ready and routed by traefik
+async fn wait_ready(
+	docker: &Docker,
+	traefik: Option<(&Traefik, String)>,
+	containers: &[String],
+) -> Result<(), Error> {
+	let deadline = Instant::now() + READY_TIMEOUT;
+
+	loop {
+		let mut all_ready = true;
+
+		for container in containers {
+			let Some(ips) = ready_container(docker, container).await? else {
+				all_ready = false;
+				break;
+			};
+
+			if let Some((traefik, name)) = &traefik {
+				// the service does not exist until the first container is
+				// registered
+				let routed = traefik
+					.service_by_name(name)
+					.await
+					.is_ok_and(|s| ips.iter().any(|ip| s.has_server_up(ip)));
+				if !routed {
+					all_ready = false;
+					break;
+				}
+			}
+		}
+
+		if all_ready {
+			return Ok(());
+		}
+
+		if Instant::now() >= deadline {
+			return Err(Error::any(
+				"New containers did not become ready",
+				format!("waited {}s", READY_TIMEOUT.as_secs()),
+			));
+		}
+
+		sleep(POLL_INTERVAL).await;
+	}
+}
+
+async fn remove_containers(
+	docker: &Docker,
+	containers: &[String],
+) -> Result<(), Error> {
+	for container in containers {
+		docker.stop_and_remove_container(container).await?;
+	}
+
+	Ok(())
+}
+
+/// Pulls the image and replaces the containers of the service without
+/// downtime
+pub async fn deploy_service(
+	docker: &Docker,
+	traefik: &Traefik,
+	id: &AppId,
+	compose_file: &Path,
+	name: &str,
+	service: &ComposeService,
+	env: &[(String, String)],
+) -> Result<(), Error> {
+	let old = service_containers(docker, id, name).await?;
+
+	// nothing is running which could be kept up
+	if old.is_empty() {
+		return compose::up_services_with_env(compose_file, &[name], true, env)
+			.await
+			.map_err(Into::into);
+	}
+
+	compose::scale_service_with_env(compose_file, name, old.len() * 2, env)
+		.await?;
+
+	let new = service_containers(docker, id, name)
+		.await?
+		.into_iter()
+		.filter(|c| !old.contains(c))
+		.collect::<Vec<_>>();
+
+	// validating the compose file makes sure the traefik service has this name
+	let routed = service.traefik_enabled();
+	let traefik = routed.then(|| (traefik, format!("{id}-{name}@docker")));
+
+	if let Err(e) = wait_ready(docker, traefik, &new).await {
+		warn!(
+			"start-first deployment of {id}-{name} failed, keeping the old containers"
+		);
+		remove_containers(docker, &new)
+			.await
+			.with_message("Failed to remove the new containers")?;
+
+		return Err(e);
+	}
+
+	info!("new containers of {id}-{name} are ready, removing the old ones");
+	remove_containers(docker, &old).await
+}
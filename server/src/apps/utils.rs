@@ -1,10 +1,106 @@
+use std::path::Path;
+
 use api::{
-	apps::{ServiceRoute, ServiceState},
-	error::Error,
+	apps::{
+		AppId, AppService, HealthStatus, LogLine, LogStream, ServiceHealth,
+		ServiceRoute, ServiceState, ServiceStats,
+	},
+	error::{ComposeError, Error, WithMessage as _},
+};
+use bollard::{
+	container::LogOutput,
+	secret::{
+		ContainerCpuStats, ContainerInspectResponse, ContainerNetworkStats,
+		ContainerStatsResponse, ContainerSummaryStateEnum, HealthStatusEnum,
+	},
+};
+use chuchi_postgres::time::DateTime;
+use tokio::fs;
+
+use crate::{
+	traefik::api::TraefikRoute,
+	utils::{cmd::CmdError, compose, is_dir},
 };
-use bollard::secret::ContainerSummaryStateEnum;
 
-use crate::traefik::{api::TraefikRoute, utils::parse_rule_to_domains};
+/// Returns the id of every app folder in `$HOSTDINGHY_DIR`
+pub async fn app_ids(hostdinghy_dir: &Path) -> Result<Vec<AppId>, Error> {
+	let mut entries = fs::read_dir(hostdinghy_dir)
+		.await
+		.with_message("Failed to read $HOSTDINGHY_DIR")?;
+
+	let mut ids = vec![];
+	while let Some(entry) = entries
+		.next_entry()
+		.await
+		.with_message("Failed to read $HOSTDINGHY_DIR")?
+	{
+		let id = entry.file_name().to_str().and_then(|n| n.parse().ok());
+		if let Some(id) = id
+			&& is_dir(entry.path()).await
+		{
+			ids.push(id);
+		}
+	}
+
+	Ok(ids)
+}
+
+/// Returns true if a registry user or a database belongs to the app
+///
+/// They are named like the app or prefixed with the app id followed by a
+/// minus or an underscore, databases use underscores instead of minuses.
+/// Since an app id can be the prefix of another app id the longest matching
+/// id of `apps` wins.
+pub fn owns_resource(id: &AppId, apps: &[AppId], name: &str) -> bool {
+	let normalize = |s: &str| s.replace('-', "_");
+	let name = normalize(name);
+
+	let owner = apps
+		.iter()
+		.chain([id])
+		.filter(|app| {
+			let prefix = normalize(app.as_ref());
+			name.strip_prefix(&prefix)
+				.is_some_and(|r| r.is_empty() || r.starts_with('_'))
+		})
+		.max_by_key(|app| app.as_ref().len());
+
+	owner == Some(id)
+}
+
+/// Lets docker compose validate the file in a temporary directory so a
+/// broken file never replaces the working one.
+pub async fn validate_compose_with_docker(
+	app_dir: impl AsRef<Path>,
+	compose: &str,
+	env: &[(String, String)],
+) -> Result<(), Error> {
+	let tmp_dir = tempfile::tempdir()
+		.map_err(|e| Error::any("Failed to create temporary directory", e))?;
+	let tmp_path = tmp_dir.path().join("compose.yml");
+	fs::write(&tmp_path, compose)
+		.await
+		.with_message("Failed to write temporary compose file")?;
+
+	// relative paths like env files should resolve to the app directory
+	let app_dir = app_dir.as_ref();
+	let project_dir = is_dir(app_dir).await.then_some(app_dir);
+
+	match compose::config(&tmp_path, project_dir, env).await {
+		Ok(()) => Ok(()),
+		Err(CmdError::Command { message, .. }) => {
+			let tmp_path = tmp_path.to_string_lossy();
+			let errors = message
+				.lines()
+				.map(str::trim)
+				.filter(|l| !l.is_empty())
+				.map(|l| l.replace(&*tmp_path, "compose.yml"))
+				.collect();
+
+			Err(ComposeError::Config { errors }.into())
+		}
+	}
+}
 
 pub fn cont_sum_state_enum_to_service_state(
 	en: ContainerSummaryStateEnum,
@@ -24,6 +120,51 @@ pub fn cont_sum_state_enum_to_service_state(
 	}
 }
 
+/// Adds the health and the restart count to the service
+///
+/// A running service with a failing healthcheck is marked as unhealthy.
+pub fn apply_container_inspect(
+	service: &mut AppService,
+	inspect: ContainerInspectResponse,
+) {
+	service.restart_count = inspect.restart_count.unwrap_or(0).max(0) as u64;
+
+	let Some(health) = inspect.state.and_then(|s| s.health) else {
+		return;
+	};
+
+	let status = match health.status {
+		Some(HealthStatusEnum::STARTING) => HealthStatus::Starting,
+		Some(HealthStatusEnum::HEALTHY) => HealthStatus::Healthy,
+		Some(HealthStatusEnum::UNHEALTHY) => HealthStatus::Unhealthy,
+		// no healthcheck is configured
+		_ => return,
+	};
+
+	if status == HealthStatus::Unhealthy
+		&& matches!(service.state, ServiceState::Running)
+	{
+		service.state = ServiceState::Unhealthy;
+	}
+
+	// docker keeps the last few probes, the newest one is last
+	let last = health.log.and_then(|l| l.into_iter().last());
+
+	service.health = Some(ServiceHealth {
+		status,
+		failing_streak: health.failing_streak.unwrap_or(0).max(0) as u64,
+		last_output: last
+			.as_ref()
+			.and_then(|l| l.output.as_ref())
+			.map(|o| o.trim().to_string()),
+		last_exit_code: last.as_ref().and_then(|l| l.exit_code),
+		last_probe_on: last
+			.as_ref()
+			.and_then(|l| l.end.as_ref().or(l.start.as_ref()))
+			.and_then(|d| DateTime::parse_from_iso8601(d).ok()),
+	});
+}
+
 pub fn container_names_to_service_name(
 	names: &Option<Vec<String>>,
 ) -> Option<String> {
@@ -39,8 +180,186 @@ pub fn container_names_to_service_name(
 pub fn traefik_route_to_service_route(
 	route: TraefikRoute,
 ) -> Result<ServiceRoute, Error> {
-	Ok(ServiceRoute {
-		domains: parse_rule_to_domains(&route.rule),
-		rule: route.rule,
-	})
+	Ok(ServiceRoute::from_rule(route.rule))
+}
+
+/// Splits the output into lines, expects the logs to be requested
+/// with timestamps
+pub fn log_output_to_lines(service: &str, output: LogOutput) -> Vec<LogLine> {
+	let (stream, message) = match output {
+		LogOutput::StdErr { message } => (LogStream::Stderr, message),
+		LogOutput::StdOut { message } | LogOutput::Console { message } => {
+			(LogStream::Stdout, message)
+		}
+		LogOutput::StdIn { .. } => return vec![],
+	};
+
+	String::from_utf8_lossy(&message)
+		.lines()
+		.map(|line| {
+			// docker prefixes each line with an RFC3339 timestamp
+			let (timestamp, message) = line
+				.split_once(' ')
+				.and_then(|(ts, msg)| {
+					DateTime::parse_from_iso8601(ts).ok().map(|ts| (ts, msg))
+				})
+				.map_or((None, line), |(ts, msg)| (Some(ts), msg));
+
+			LogLine {
+				service: service.to_string(),
+				stream,
+				timestamp,
+				message: message.to_string(),
+			}
+		})
+		.collect()
+}
+
+/// Calculates the values the same way `docker stats` does
+pub fn container_stats_to_service_stats(
+	name: &str,
+	container_name: &str,
+	stats: ContainerStatsResponse,
+) -> ServiceStats {
+	let cpu = stats.cpu_stats.unwrap_or_default();
+	let precpu = stats.precpu_stats.unwrap_or_default();
+
+	let total_usage = |c: &ContainerCpuStats| {
+		c.cpu_usage
+			.as_ref()
+			.and_then(|u| u.total_usage)
+			.unwrap_or(0)
+	};
+	let cpu_delta = total_usage(&cpu).saturating_sub(total_usage(&precpu));
+	let system_delta = cpu
+		.system_cpu_usage
+		.unwrap_or(0)
+		.saturating_sub(precpu.system_cpu_usage.unwrap_or(0));
+	let online_cpus = cpu.online_cpus.map(|c| c as u64).unwrap_or_else(|| {
+		cpu.cpu_usage
+			.as_ref()
+			.and_then(|u| u.percpu_usage.as_ref())
+			.map_or(1, |p| p.len() as u64)
+	});
+
+	let cpu_percent = if system_delta > 0 && cpu_delta > 0 {
+		cpu_delta as f64 / system_delta as f64 * online_cpus as f64 * 100.0
+	} else {
+		0.0
+	};
+
+	let memory = stats.memory_stats.unwrap_or_default();
+	// the page cache is counted as usage but can be reclaimed any time
+	let cache = memory
+		.stats
+		.as_ref()
+		.and_then(|s| {
+			// cgroup v1 or cgroup v2
+			s.get("total_inactive_file")
+				.or_else(|| s.get("inactive_file"))
+		})
+		.copied()
+		.unwrap_or(0);
+	let memory_usage = memory.usage.unwrap_or(0).saturating_sub(cache);
+
+	let mut block_read = 0;
+	let mut block_write = 0;
+	let entries = stats
+		.blkio_stats
+		.and_then(|b| b.io_service_bytes_recursive)
+		.unwrap_or_default();
+	for entry in entries {
+		let value = entry.value.unwrap_or(0);
+		match entry.op.as_deref().map(str::to_ascii_lowercase).as_deref() {
+			Some("read") => block_read += value,
+			Some("write") => block_write += value,
+			_ => {}
+		}
+	}
+
+	// docker reports the traffic per interface
+	let networks = stats.networks.as_ref();
+	let network_total = |bytes: fn(&ContainerNetworkStats) -> Option<u64>| {
+		networks.map(|n| n.values().filter_map(bytes).sum())
+	};
+
+	ServiceStats {
+		name: name.to_string(),
+		container_name: container_name.to_string(),
+		cpu_percent,
+		memory_usage,
+		memory_limit: memory.limit.unwrap_or(0),
+		network_rx: network_total(|n| n.rx_bytes),
+		network_tx: network_total(|n| n.tx_bytes),
+		block_read,
+		block_write,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Shortened response of `GET /containers/{id}/stats?stream=false` of a
+	/// container attached to two networks
+	const STATS: &str = r#"{
+		"read": "2025-08-01T10:00:01.000000000Z",
+		"preread": "2025-08-01T10:00:00.000000000Z",
+		"cpu_stats": {
+			"cpu_usage": { "total_usage": 2000000 },
+			"system_cpu_usage": 20000000,
+			"online_cpus": 2
+		},
+		"precpu_stats": {
+			"cpu_usage": { "total_usage": 1000000 },
+			"system_cpu_usage": 10000000,
+			"online_cpus": 2
+		},
+		"memory_stats": {
+			"usage": 104857600,
+			"limit": 1073741824,
+			"stats": { "inactive_file": 4857600 }
+		},
+		"blkio_stats": {
+			"io_service_bytes_recursive": [
+				{ "major": 8, "minor": 0, "op": "read", "value": 4096 },
+				{ "major": 8, "minor": 0, "op": "write", "value": 8192 }
+			]
+		},
+		"networks": {
+			"eth0": {
+				"rx_bytes": 1000, "rx_packets": 10, "rx_errors": 0,
+				"rx_dropped": 0, "tx_bytes": 300, "tx_packets": 3,
+				"tx_errors": 0, "tx_dropped": 0
+			},
+			"eth1": {
+				"rx_bytes": 500, "rx_packets": 5, "rx_errors": 0,
+				"rx_dropped": 0, "tx_bytes": 200, "tx_packets": 2,
+				"tx_errors": 0, "tx_dropped": 0
+			}
+		}
+	}"#;
+
+	#[test]
+	fn stats_sum_interfaces() {
+		let stats = serde_json::from_str(STATS).unwrap();
+		let stats = container_stats_to_service_stats("web", "app-web-1", stats);
+
+		assert_eq!(stats.network_rx, Some(1500));
+		assert_eq!(stats.network_tx, Some(500));
+		assert_eq!(stats.memory_usage, 100_000_000);
+		assert_eq!(stats.memory_limit, 1_073_741_824);
+		assert_eq!(stats.block_read, 4096);
+		assert_eq!(stats.block_write, 8192);
+		assert!((stats.cpu_percent - 20.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn stats_without_network() {
+		let stats = serde_json::from_str(r#"{ "read": null }"#).unwrap();
+		let stats = container_stats_to_service_stats("web", "app-web-1", stats);
+
+		assert_eq!(stats.network_rx, None);
+		assert_eq!(stats.network_tx, None);
+	}
 }
@@ -0,0 +1,196 @@
+/*!
+Snapshots of the named volumes of an app.
+
+A snapshot is a tar archive (optionally zstd compressed) containing a folder
+for every volume, named like the volume in the compose file. Only volumes
+using the `local` driver are supported since the data is read directly from
+the mountpoint.
+*/
+
+use std::path::{Path, PathBuf};
+
+use api::{
+	apps::{AppVolume, SnapshotCompression},
+	error::{Error, WithMessage as _},
+};
+use bollard::secret::Volume;
+
+use crate::{
+	docker::Docker,
+	utils::{
+		cmd::{ChildReadableStdout, ChildWritableStdin, cmd},
+		is_dir,
+	},
+};
+
+#[derive(Debug, Clone)]
+pub struct LocalVolume {
+	/// The name of the volume in the compose file
+	pub name: String,
+	pub volume_name: String,
+	pub mountpoint: PathBuf,
+}
+
+impl LocalVolume {
+	fn from_volume(v: Volume) -> Option<Self> {
+		if v.driver != "local" || v.mountpoint.is_empty() {
+			return None;
+		}
+
+		Some(Self {
+			name: v
+				.labels
+				.get("com.docker.compose.volume")
+				.cloned()
+				.unwrap_or_else(|| v.name.clone()),
+			volume_name: v.name,
+			mountpoint: v.mountpoint.into(),
+		})
+	}
+
+	pub fn to_api(&self) -> AppVolume {
+		AppVolume {
+			name: self.name.clone(),
+			volume_name: self.volume_name.clone(),
+		}
+	}
+}
+
+pub async fn app_volumes(
+	docker: &Docker,
+	id: &str,
+) -> Result<Vec<LocalVolume>, Error> {
+	let mut volumes = docker
+		.volumes_by_composer_project(id)
+		.await?
+		.into_iter()
+		.filter_map(LocalVolume::from_volume)
+		.collect::<Vec<_>>();
+	volumes.sort_by(|a, b| a.name.cmp(&b.name));
+
+	Ok(volumes)
+}
+
+fn compression_arg(compression: SnapshotCompression) -> Option<&'static str> {
+	match compression {
+		SnapshotCompression::None => None,
+		SnapshotCompression::Zstd => Some("--zstd"),
+	}
+}
+
+/// Escapes a string to be used in a basic regular expression
+fn escape_regex(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	for c in s.chars() {
+		if matches!(c, '.' | '[' | ']' | '*' | '^' | '$' | '\\' | ',') {
+			out.push('\\');
+		}
+		out.push(c);
+	}
+
+	out
+}
+
+pub async fn snapshot(
+	volumes: &[LocalVolume],
+	compression: SnapshotCompression,
+) -> Result<ChildReadableStdout, Error> {
+	// tar refuses to create an empty archive without any file list
+	let mut args = vec![
+		"tar".to_string(),
+		"-c".into(),
+		"--files-from".into(),
+		"/dev/null".into(),
+	];
+
+	for volume in volumes {
+		// the mountpoint looks like /var/lib/docker/volumes/<name>/_data
+		// the folder in the archive gets renamed to the compose name
+		let root = volume
+			.mountpoint
+			.parent()
+			.and_then(Path::parent)
+			.ok_or_else(|| {
+				Error::any(
+					"Invalid volume mountpoint",
+					volume.mountpoint.display().to_string(),
+				)
+			})?;
+		let member = volume
+			.mountpoint
+			.strip_prefix(root)
+			.unwrap()
+			.to_string_lossy()
+			.into_owned();
+
+		args.push(format!(
+			"--transform=s,^{},{},",
+			escape_regex(&member),
+			volume.name
+		));
+		args.push("-C".into());
+		args.push(root.to_string_lossy().into_owned());
+		args.push(member);
+	}
+
+	let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+	cmd(&args)
+		.arg_opt(compression_arg(compression))
+		.as_root()
+		.spawn_readable_stdout()
+		.await
+		.map_err(Into::into)
+}
+
+/// Extracts an archive into the given folder
+pub async fn extract(
+	dir: impl AsRef<Path>,
+	compression: SnapshotCompression,
+) -> Result<ChildWritableStdin, Error> {
+	cmd(&["tar", "-x", "-C", &dir.as_ref().to_string_lossy()])
+		.arg_opt(compression_arg(compression))
+		.as_root()
+		.spawn_writable_stdin()
+		.await
+		.map_err(Into::into)
+}
+
+/// Replaces the content of every volume which has a folder in `dir`
+///
+/// Returns the names of the restored volumes
+pub async fn replace_from(
+	dir: impl AsRef<Path>,
+	volumes: &[LocalVolume],
+) -> Result<Vec<String>, Error> {
+	let mut restored = vec![];
+
+	for volume in volumes {
+		let src = dir.as_ref().join(&volume.name);
+		if !is_dir(&src).await {
+			continue;
+		}
+
+		let mountpoint = volume.mountpoint.to_string_lossy();
+		cmd(&["find", &mountpoint, "-mindepth", "1", "-delete"])
+			.as_root()
+			.run()
+			.await
+			.with_message(format!("Failed to clear volume {}", volume.name))?;
+
+		cmd(&[
+			"cp",
+			"-a",
+			&format!("{}/.", src.to_string_lossy()),
+			&mountpoint,
+		])
+		.as_root()
+		.run()
+		.await
+		.with_message(format!("Failed to restore volume {}", volume.name))?;
+
+		restored.push(volume.name.clone());
+	}
+
+	Ok(restored)
+}
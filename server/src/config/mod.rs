@@ -2,11 +2,13 @@ use std::{io::ErrorKind, path::Path};
 
 use api::error::{Error, WithMessage as _};
 use chuchi_crypto::token::Token;
+use compose_yml::SecurityPolicy;
 use dialoguer::{Input, theme::ColorfulTheme};
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 
 use crate::{
+	metrics::MetricsConfig, postgres::backups::PostgresBackupsConfig,
 	registry::RegistryConfig, server::config::ServerConfig,
 	traefik::TraefikConfig,
 };
@@ -24,6 +26,15 @@ pub struct Config {
 	pub server: ServerConfig,
 	pub traefik: TraefikConfig,
 	pub registry: RegistryConfig,
+	/// If not set no backups get created automatically
+	#[serde(default)]
+	pub postgres_backups: Option<PostgresBackupsConfig>,
+	/// Which settings of a compose file are allowed
+	#[serde(default)]
+	pub compose_policy: SecurityPolicy,
+	/// If not set `/metrics` is disabled
+	#[serde(default)]
+	pub metrics: Option<MetricsConfig>,
 }
 
 /*
@@ -104,6 +115,9 @@ impl Config {
 			server: ServerConfig::new_from_user(),
 			traefik: TraefikConfig::new_from_user(),
 			registry: RegistryConfig::new_from_user(),
+			postgres_backups: None,
+			compose_policy: SecurityPolicy::default(),
+			metrics: None,
 		}
 	}
 }
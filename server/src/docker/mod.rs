@@ -1,10 +1,34 @@
+use std::pin::Pin;
+
 use bollard::{
-	query_parameters::ListContainersOptionsBuilder,
-	secret::{ContainerSummary, NetworkCreateRequest, NetworkCreateResponse},
+	container::LogOutput,
+	exec::{
+		CreateExecOptions, ResizeExecOptions, StartExecOptions,
+		StartExecResults,
+	},
+	query_parameters::{
+		InspectContainerOptions, ListContainersOptionsBuilder,
+		ListVolumesOptionsBuilder, LogsOptions, RemoveContainerOptions,
+		StatsOptionsBuilder, StopContainerOptions,
+	},
+	secret::{
+		ContainerInspectResponse, ContainerStatsResponse, ContainerSummary,
+		NetworkCreateRequest, NetworkCreateResponse, SystemInfo, SystemVersion,
+		Volume,
+	},
 };
+use futures::{StreamExt as _, stream::BoxStream};
+use tokio::io::AsyncWrite;
 
 use crate::utils::cli::{CliError, WithMessage as _};
 
+/// A command running in a container with an attached tty
+pub struct ExecSession {
+	pub id: String,
+	pub input: Pin<Box<dyn AsyncWrite + Send>>,
+	pub output: BoxStream<'static, Result<LogOutput, CliError>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Docker {
 	inner: bollard::Docker,
@@ -51,4 +75,210 @@ impl Docker {
 				"Failed to list Docker services for composer ID: {id}"
 			))
 	}
+
+	/// Returns the containers of every compose project
+	pub async fn compose_containers(
+		&self,
+	) -> Result<Vec<ContainerSummary>, CliError> {
+		self.inner
+			.list_containers(Some(
+				ListContainersOptionsBuilder::new()
+					.all(true)
+					.filters(
+						&[("label", vec!["com.docker.compose.project"])].into(),
+					)
+					.build(),
+			))
+			.await
+			.with_message("Failed to list Docker compose containers")
+	}
+
+	pub async fn volumes_by_composer_project(
+		&self,
+		id: &str,
+	) -> Result<Vec<Volume>, CliError> {
+		self.inner
+			.list_volumes(Some(
+				ListVolumesOptionsBuilder::new()
+					.filters(
+						&[(
+							"label",
+							vec![format!("com.docker.compose.project={id}")],
+						)]
+						.into(),
+					)
+					.build(),
+			))
+			.await
+			.map(|r| r.volumes.unwrap_or_default())
+			.with_message(format!(
+				"Failed to list Docker volumes for composer ID: {id}"
+			))
+	}
+
+	pub async fn inspect_container(
+		&self,
+		container_name: &str,
+	) -> Result<ContainerInspectResponse, CliError> {
+		self.inner
+			.inspect_container(container_name, None::<InspectContainerOptions>)
+			.await
+			.with_message(format!(
+				"Failed to inspect container {container_name}"
+			))
+	}
+
+	/// Stops the container gracefully and removes it
+	pub async fn stop_and_remove_container(
+		&self,
+		id: &str,
+	) -> Result<(), CliError> {
+		self.inner
+			.stop_container(id, None::<StopContainerOptions>)
+			.await
+			.with_message(format!("Failed to stop container {id}"))?;
+
+		self.inner
+			.remove_container(id, None::<RemoveContainerOptions>)
+			.await
+			.with_message(format!("Failed to remove container {id}"))
+	}
+
+	pub fn logs(
+		&self,
+		container_name: &str,
+		options: LogsOptions,
+	) -> BoxStream<'static, Result<LogOutput, CliError>> {
+		let container_name = container_name.to_string();
+
+		self.inner
+			.logs(&container_name, Some(options))
+			.map(move |r| {
+				r.with_message(format!(
+					"Failed to read logs of container {container_name}"
+				))
+			})
+			.boxed()
+	}
+
+	pub async fn exec_tty(
+		&self,
+		container_name: &str,
+		cmd: Vec<String>,
+		env: Vec<String>,
+	) -> Result<ExecSession, CliError> {
+		let exec = self
+			.inner
+			.create_exec(
+				container_name,
+				CreateExecOptions {
+					attach_stdin: Some(true),
+					attach_stdout: Some(true),
+					attach_stderr: Some(true),
+					tty: Some(true),
+					cmd: Some(cmd),
+					env: Some(env),
+					..Default::default()
+				},
+			)
+			.await
+			.with_message(format!(
+				"Failed to create exec in container {container_name}"
+			))?;
+
+		let res = self
+			.inner
+			.start_exec(
+				&exec.id,
+				Some(StartExecOptions {
+					detach: false,
+					tty: true,
+					output_capacity: None,
+				}),
+			)
+			.await
+			.with_message(format!(
+				"Failed to start exec in container {container_name}"
+			))?;
+
+		let StartExecResults::Attached { output, input } = res else {
+			return Err(CliError::any(
+				"Failed to attach to exec",
+				"exec was started detached",
+			));
+		};
+
+		Ok(ExecSession {
+			id: exec.id,
+			input,
+			output: output
+				.map(|r| r.with_message("Failed to read exec output"))
+				.boxed(),
+		})
+	}
+
+	pub async fn resize_exec(
+		&self,
+		id: &str,
+		cols: u16,
+		rows: u16,
+	) -> Result<(), CliError> {
+		self.inner
+			.resize_exec(
+				id,
+				ResizeExecOptions {
+					height: rows,
+					width: cols,
+				},
+			)
+			.await
+			.with_message("Failed to resize exec")
+	}
+
+	/// Returns None if the exec is still running
+	pub async fn exec_exit_code(
+		&self,
+		id: &str,
+	) -> Result<Option<i64>, CliError> {
+		self.inner
+			.inspect_exec(id)
+			.await
+			.map(|i| i.exit_code)
+			.with_message("Failed to inspect exec")
+	}
+
+	pub async fn version(&self) -> Result<SystemVersion, CliError> {
+		self.inner
+			.version()
+			.await
+			.with_message("Failed to get the Docker version")
+	}
+
+	pub async fn info(&self) -> Result<SystemInfo, CliError> {
+		self.inner
+			.info()
+			.await
+			.with_message("Failed to get the Docker info")
+	}
+
+	/// If stream is false only a single entry is returned
+	pub fn stats(
+		&self,
+		container_name: &str,
+		stream: bool,
+	) -> BoxStream<'static, Result<ContainerStatsResponse, CliError>> {
+		let container_name = container_name.to_string();
+
+		self.inner
+			.stats(
+				&container_name,
+				Some(StatsOptionsBuilder::new().stream(stream).build()),
+			)
+			.map(move |r| {
+				r.with_message(format!(
+					"Failed to read stats of container {container_name}"
+				))
+			})
+			.boxed()
+	}
 }
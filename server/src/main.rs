@@ -9,6 +9,7 @@ How to install this
 mod apps;
 mod config;
 mod docker;
+mod metrics;
 mod postgres;
 mod registry;
 #[cfg(debug_assertions)]
@@ -34,6 +35,7 @@ enum SubCommand {
 	Setup(setup::Setup),
 	Registry(registry::Registry),
 	Postgres(postgres::Postgres),
+	Server(server::Server),
 	Serve,
 	#[cfg(debug_assertions)]
 	Test(runtime_test::Test),
@@ -57,6 +59,9 @@ async fn main() {
 		SubCommand::Postgres(postgres) => {
 			postgres::postgres(postgres).await;
 		}
+		SubCommand::Server(s) => {
+			server::server(s).await;
+		}
 		SubCommand::Serve => {
 			server::serve().await;
 		}
@@ -0,0 +1,389 @@
+/*!
+Metrics in the prometheus text format.
+
+Events like requests or deployments are recorded while the server runs, the
+state of the containers, the databases and the registry is collected when
+`/metrics` gets scraped.
+*/
+
+pub mod routes;
+
+use std::{
+	collections::BTreeMap,
+	fmt::{self, Write as _},
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use axum::{
+	extract::{MatchedPath, Request, State},
+	middleware::Next,
+	response::Response,
+};
+use chuchi_crypto::token::Token;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+	config::Config,
+	utils::{
+		cli::{CliError, WithMessage as _},
+		hostdinghy_dir,
+	},
+};
+
+pub type MetricsToken = Token<32>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MetricsConfig {
+	/// Needs to be sent as bearer token to `/metrics`
+	pub token: MetricsToken,
+}
+
+const HTTP_BUCKETS: &[f64] = &[
+	0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+const COMPOSE_BUCKETS: &[f64] =
+	&[0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+#[derive(Debug, Clone)]
+struct Histogram {
+	bounds: &'static [f64],
+	/// The count per bound, not cumulative
+	buckets: Vec<u64>,
+	sum: f64,
+	count: u64,
+}
+
+impl Histogram {
+	fn new(bounds: &'static [f64]) -> Self {
+		Self {
+			bounds,
+			buckets: vec![0; bounds.len()],
+			sum: 0.0,
+			count: 0,
+		}
+	}
+
+	fn observe(&mut self, duration: Duration) {
+		let secs = duration.as_secs_f64();
+
+		if let Some(i) = self.bounds.iter().position(|b| secs <= *b) {
+			self.buckets[i] += 1;
+		}
+		self.sum += secs;
+		self.count += 1;
+	}
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+	/// (method, path, status)
+	http_requests: BTreeMap<(String, String, u16), Histogram>,
+	/// (app, action)
+	compose_actions: BTreeMap<(String, String), Histogram>,
+	/// (app, action)
+	compose_action_failures: BTreeMap<(String, String), u64>,
+	/// (app, status)
+	webhook_deployments: BTreeMap<(String, String), u64>,
+	/// app
+	webhook_deployment_durations: BTreeMap<String, Histogram>,
+	/// The size and when it was measured, measuring takes a while
+	registry_storage: Option<(u64, Instant)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+	inner: Arc<Mutex<Inner>>,
+}
+
+impl Metrics {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn observe_http_request(
+		&self,
+		method: &str,
+		path: &str,
+		status: u16,
+		duration: Duration,
+	) {
+		let mut inner = self.inner.lock().unwrap();
+		inner
+			.http_requests
+			.entry((method.into(), path.into(), status))
+			.or_insert_with(|| Histogram::new(HTTP_BUCKETS))
+			.observe(duration);
+	}
+
+	pub fn observe_compose_action(
+		&self,
+		app: &str,
+		action: &str,
+		duration: Duration,
+		succeeded: bool,
+	) {
+		let key = (app.to_string(), action.to_string());
+
+		let mut inner = self.inner.lock().unwrap();
+		inner
+			.compose_actions
+			.entry(key.clone())
+			.or_insert_with(|| Histogram::new(COMPOSE_BUCKETS))
+			.observe(duration);
+
+		let failures = inner.compose_action_failures.entry(key).or_default();
+		if !succeeded {
+			*failures += 1;
+		}
+	}
+
+	pub fn observe_webhook_deployment(
+		&self,
+		app: &str,
+		status: &str,
+		duration: Duration,
+	) {
+		let mut inner = self.inner.lock().unwrap();
+		*inner
+			.webhook_deployments
+			.entry((app.into(), status.into()))
+			.or_default() += 1;
+		inner
+			.webhook_deployment_durations
+			.entry(app.into())
+			.or_insert_with(|| Histogram::new(COMPOSE_BUCKETS))
+			.observe(duration);
+	}
+
+	/// Returns the registry storage if it was measured within `max_age`
+	pub fn registry_storage(&self, max_age: Duration) -> Option<u64> {
+		let inner = self.inner.lock().unwrap();
+		inner
+			.registry_storage
+			.filter(|(_, measured)| measured.elapsed() < max_age)
+			.map(|(size, _)| size)
+	}
+
+	pub fn set_registry_storage(&self, size: u64) {
+		let mut inner = self.inner.lock().unwrap();
+		inner.registry_storage = Some((size, Instant::now()));
+	}
+
+	/// Writes all recorded metrics
+	pub fn encode(&self, enc: &mut Encoder) {
+		let inner = self.inner.lock().unwrap();
+
+		enc.header(
+			"hostdinghy_http_requests_total",
+			"counter",
+			"Handled http requests",
+		);
+		for ((method, path, status), h) in &inner.http_requests {
+			let status = status.to_string();
+			let labels = [
+				("method", method.as_str()),
+				("path", path),
+				("status", &status),
+			];
+			enc.sample("hostdinghy_http_requests_total", &labels, h.count);
+		}
+
+		enc.header(
+			"hostdinghy_http_request_duration_seconds",
+			"histogram",
+			"How long handling a http request took",
+		);
+		for ((method, path, status), h) in &inner.http_requests {
+			let status = status.to_string();
+			let labels = [
+				("method", method.as_str()),
+				("path", path),
+				("status", &status),
+			];
+			enc.histogram(
+				"hostdinghy_http_request_duration_seconds",
+				&labels,
+				h,
+			);
+		}
+
+		enc.header(
+			"hostdinghy_compose_action_duration_seconds",
+			"histogram",
+			"How long a compose action of an app took",
+		);
+		for ((app, action), h) in &inner.compose_actions {
+			let labels = [("app", app.as_str()), ("action", action)];
+			enc.histogram(
+				"hostdinghy_compose_action_duration_seconds",
+				&labels,
+				h,
+			);
+		}
+
+		enc.header(
+			"hostdinghy_compose_action_failures_total",
+			"counter",
+			"Failed compose actions of an app",
+		);
+		for ((app, action), count) in &inner.compose_action_failures {
+			let labels = [("app", app.as_str()), ("action", action)];
+			enc.sample(
+				"hostdinghy_compose_action_failures_total",
+				&labels,
+				count,
+			);
+		}
+
+		enc.header(
+			"hostdinghy_webhook_deployments_total",
+			"counter",
+			"Deployments triggered by a push to the registry",
+		);
+		for ((app, status), count) in &inner.webhook_deployments {
+			let labels = [("app", app.as_str()), ("status", status)];
+			enc.sample("hostdinghy_webhook_deployments_total", &labels, count);
+		}
+
+		enc.header(
+			"hostdinghy_webhook_deployment_duration_seconds",
+			"histogram",
+			"How long a deployment triggered by the registry took",
+		);
+		for (app, h) in &inner.webhook_deployment_durations {
+			enc.histogram(
+				"hostdinghy_webhook_deployment_duration_seconds",
+				&[("app", app)],
+				h,
+			);
+		}
+	}
+}
+
+/// Creates a new metrics token and prints it
+pub async fn new_token() -> Result<(), CliError> {
+	let hostdinghy_dir = hostdinghy_dir()?;
+	let mut cfg = Config::read(&hostdinghy_dir)
+		.await
+		.with_message("Failed to read config")?;
+
+	let token = MetricsToken::new();
+	cfg.metrics = Some(MetricsConfig {
+		token: token.clone(),
+	});
+	cfg.write(&hostdinghy_dir)
+		.await
+		.with_message("Failed to write config")?;
+
+	info!("Metrics token created.");
+	eprintln!("{token}");
+
+	Ok(())
+}
+
+/// Records the method, the route, the status and the duration of every
+/// request
+pub async fn track_requests(
+	State(metrics): State<Metrics>,
+	req: Request,
+	next: Next,
+) -> Response {
+	let method = req.method().to_string();
+	// use the route instead of the uri to keep the amount of labels small
+	let path = req
+		.extensions()
+		.get::<MatchedPath>()
+		.map(|p| p.as_str().to_string());
+
+	let start = Instant::now();
+	let res = next.run(req).await;
+
+	if let Some(path) = path {
+		metrics.observe_http_request(
+			&method,
+			&path,
+			res.status().as_u16(),
+			start.elapsed(),
+		);
+	}
+
+	res
+}
+
+/// Writes metrics in the prometheus text format
+#[derive(Debug, Default)]
+pub struct Encoder {
+	out: String,
+}
+
+fn escape(value: &str) -> String {
+	value
+		.replace('\\', "\\\\")
+		.replace('"', "\\\"")
+		.replace('\n', "\\n")
+}
+
+impl Encoder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn header(&mut self, name: &str, kind: &str, help: &str) {
+		let _ = writeln!(self.out, "# HELP {name} {help}");
+		let _ = writeln!(self.out, "# TYPE {name} {kind}");
+	}
+
+	pub fn sample(
+		&mut self,
+		name: &str,
+		labels: &[(&str, &str)],
+		value: impl fmt::Display,
+	) {
+		self.out.push_str(name);
+
+		if !labels.is_empty() {
+			let labels = labels
+				.iter()
+				.map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+				.collect::<Vec<_>>()
+				.join(",");
+			let _ = write!(self.out, "{{{labels}}}");
+		}
+
+		let _ = writeln!(self.out, " {value}");
+	}
+
+	fn histogram(
+		&mut self,
+		name: &str,
+		labels: &[(&str, &str)],
+		h: &Histogram,
+	) {
+		let bucket = format!("{name}_bucket");
+
+		let mut cumulative = 0;
+		for (bound, count) in h.bounds.iter().zip(&h.buckets) {
+			cumulative += count;
+
+			let le = bound.to_string();
+			let mut labels = labels.to_vec();
+			labels.push(("le", &le));
+			self.sample(&bucket, &labels, cumulative);
+		}
+
+		let mut inf_labels = labels.to_vec();
+		inf_labels.push(("le", "+Inf"));
+		self.sample(&bucket, &inf_labels, h.count);
+
+		self.sample(&format!("{name}_sum"), labels, h.sum);
+		self.sample(&format!("{name}_count"), labels, h.count);
+	}
+
+	pub fn finish(self) -> String {
+		self.out
+	}
+}
@@ -0,0 +1,185 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use api::error::Error;
+use axum::{
+	Router,
+	extract::State,
+	response::{IntoResponse, Response},
+	routing::get,
+};
+use hyper::{
+	HeaderMap,
+	header::{AUTHORIZATION, CONTENT_TYPE},
+};
+use subtle::ConstantTimeEq as _;
+use tracing::warn;
+
+use crate::{
+	docker::Docker,
+	metrics::{Encoder, Metrics, MetricsToken},
+	postgres::Client,
+	registry::gc::dir_size,
+	server::{router::AppState, tokens::ApiTokens},
+	utils::hostdinghy_dir,
+};
+
+/// Measuring the registry walks the whole directory
+const REGISTRY_STORAGE_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+async fn containers(docker: &Docker, enc: &mut Encoder) {
+	let containers = match docker.compose_containers().await {
+		Ok(c) => c,
+		Err(e) => {
+			warn!("failed to list containers for the metrics {e}");
+			return;
+		}
+	};
+
+	// (app, service, state)
+	let mut counts: BTreeMap<(String, String, String), u64> = BTreeMap::new();
+	for container in containers {
+		let labels = container.labels.unwrap_or_default();
+		let Some(app) = labels.get("com.docker.compose.project") else {
+			continue;
+		};
+		let service = labels
+			.get("com.docker.compose.service")
+			.cloned()
+			.unwrap_or_default();
+		let state = container
+			.state
+			.map(|s| s.to_string())
+			.unwrap_or_else(|| "unknown".into());
+
+		*counts.entry((app.clone(), service, state)).or_default() += 1;
+	}
+
+	enc.header(
+		"hostdinghy_containers",
+		"gauge",
+		"Containers of an app per state",
+	);
+	for ((app, service, state), count) in counts {
+		enc.sample(
+			"hostdinghy_containers",
+			&[("app", &app), ("service", &service), ("state", &state)],
+			count,
+		);
+	}
+}
+
+async fn database_sizes(enc: &mut Encoder) {
+	let sizes = match Client::new().await {
+		Ok(client) => client.database_sizes().await,
+		Err(e) => Err(e),
+	};
+	let sizes = match sizes {
+		Ok(s) => s,
+		Err(e) => {
+			warn!("failed to get the database sizes for the metrics {e}");
+			return;
+		}
+	};
+
+	enc.header(
+		"hostdinghy_postgres_database_size_bytes",
+		"gauge",
+		"Size of a postgres database",
+	);
+	for (database, size) in sizes {
+		enc.sample(
+			"hostdinghy_postgres_database_size_bytes",
+			&[("database", &database)],
+			size,
+		);
+	}
+}
+
+async fn registry_storage(metrics: &Metrics, enc: &mut Encoder) {
+	let size = match metrics.registry_storage(REGISTRY_STORAGE_MAX_AGE) {
+		Some(size) => size,
+		None => {
+			let data_dir = hostdinghy_dir().map(|d| d.join("registry/data"));
+			let size = match data_dir {
+				Ok(dir) => dir_size(dir).await,
+				Err(e) => Err(e.into()),
+			};
+
+			match size {
+				Ok(size) => {
+					metrics.set_registry_storage(size);
+					size
+				}
+				Err(e) => {
+					warn!(
+						"failed to get the registry size for the metrics {e}"
+					);
+					return;
+				}
+			}
+		}
+	};
+
+	enc.header(
+		"hostdinghy_registry_storage_bytes",
+		"gauge",
+		"Storage used by the registry",
+	);
+	enc.sample("hostdinghy_registry_storage_bytes", &[], size);
+}
+
+/// Uses its own token, so a scraper does not get access to the api
+///
+/// The token is reloaded together with the api tokens, so a new one works
+/// without a restart.
+async fn authenticate(
+	tokens: &ApiTokens,
+	headers: &HeaderMap,
+) -> Result<(), Error> {
+	let token: MetricsToken = headers
+		.get(AUTHORIZATION)
+		.and_then(|v| v.to_str().ok())
+		.and_then(|s| s.strip_prefix("Bearer "))
+		.and_then(|s| s.parse().ok())
+		.ok_or(Error::MissingApiToken)?;
+
+	let expected =
+		tokens.metrics_token().await.ok_or(Error::InvalidApiToken)?;
+
+	// the endpoint is public, don't let the timing leak the token
+	let choice = expected.as_ref().ct_eq(token.as_ref());
+	if !bool::from(choice) {
+		return Err(Error::InvalidApiToken);
+	}
+
+	Ok(())
+}
+
+/// URL: `/metrics`
+/// Method: `GET`
+/// Return Body: `text/plain` in the prometheus text format
+/// Authentication: Yes, with the metrics token
+async fn metrics(
+	State(tokens): State<ApiTokens>,
+	State(docker): State<Docker>,
+	State(metrics): State<Metrics>,
+	headers: HeaderMap,
+) -> Result<Response, Error> {
+	authenticate(&tokens, &headers).await?;
+
+	let mut enc = Encoder::new();
+	metrics.encode(&mut enc);
+	containers(&docker, &mut enc).await;
+	database_sizes(&mut enc).await;
+	registry_storage(&metrics, &mut enc).await;
+
+	Ok((
+		[(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+		enc.finish(),
+	)
+		.into_response())
+}
+
+pub fn routes() -> Router<AppState> {
+	Router::new().route("/metrics", get(metrics))
+}
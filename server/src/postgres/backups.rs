@@ -0,0 +1,498 @@
+/*!
+Backups of Postgres databases are stored in
+`$HOSTDINGHY_DIR/backups/<database>/<backup>.dump` using the custom
+`pg_dump` format.
+
+The backup id is the time of the backup in milliseconds since the unix epoch.
+If configured, the `serve` process creates backups on a schedule and prunes
+old ones afterwards.
+*/
+
+use std::{
+	cmp::Reverse,
+	collections::HashSet,
+	hash::Hash,
+	io::ErrorKind,
+	path::{Path, PathBuf},
+};
+
+use api::{
+	error::{Error, WithMessage as _},
+	postgres::PostgresBackup,
+};
+use chrono::{Datelike as _, NaiveDate};
+use chuchi_postgres::time::DateTime;
+use serde::{Deserialize, Serialize};
+use tokio::{
+	fs::{self, File},
+	io,
+	time::sleep,
+};
+use tracing::{error, info, warn};
+
+use crate::{
+	postgres::{Client, schedule::Schedule, utils},
+	utils::{hostdinghy_dir, is_file},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PostgresBackupsConfig {
+	/// A cron like expression in UTC, for example `0 3 * * *`
+	pub schedule: Schedule,
+	/// If empty all databases get backed up
+	#[serde(default)]
+	pub databases: Vec<String>,
+	#[serde(default)]
+	pub retention: BackupRetention,
+}
+
+/// How many backups to keep, for every period the newest backup is kept
+///
+/// A backup is only removed if no rule wants to keep it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BackupRetention {
+	pub daily: usize,
+	pub weekly: usize,
+	pub monthly: usize,
+}
+
+impl Default for BackupRetention {
+	fn default() -> Self {
+		Self {
+			daily: 7,
+			weekly: 4,
+			monthly: 6,
+		}
+	}
+}
+
+fn database_dir(database: &str) -> Result<PathBuf, Error> {
+	Ok(hostdinghy_dir()?.join("backups").join(database))
+}
+
+fn backup_path(dir: impl AsRef<Path>, id: u64) -> PathBuf {
+	dir.as_ref().join(format!("{id}.dump"))
+}
+
+/// Returns the path to the backup or BackupNotFound
+pub async fn path(database: &str, id: u64) -> Result<PathBuf, Error> {
+	let path = backup_path(database_dir(database)?, id);
+	if !is_file(&path).await {
+		return Err(Error::BackupNotFound);
+	}
+
+	Ok(path)
+}
+
+pub async fn create(database: &str) -> Result<PostgresBackup, Error> {
+	let dir = database_dir(database)?;
+	fs::create_dir_all(&dir)
+		.await
+		.with_message("Failed to create backups directory")?;
+
+	let created_on = DateTime::now();
+	let id = created_on.inner().timestamp_millis() as u64;
+
+	// write to a temporary file first so a failed dump
+	// never shows up as a backup
+	let path = backup_path(&dir, id);
+	let tmp_path = path.with_extension("dump.tmp");
+
+	let res = async {
+		let mut file = File::create(&tmp_path)
+			.await
+			.with_message("Failed to create backup file")?;
+
+		let mut child = utils::dump_database(database).await?;
+		io::copy(&mut child, &mut file)
+			.await
+			.with_message("Failed to write backup")?;
+		child.wait().await?;
+
+		file.sync_all()
+			.await
+			.with_message("Failed to write backup")?;
+
+		Ok::<_, Error>(())
+	}
+	.await;
+
+	if let Err(e) = res {
+		let _ = fs::remove_file(&tmp_path).await;
+		return Err(e);
+	}
+
+	fs::rename(&tmp_path, &path)
+		.await
+		.with_message("Failed to move backup")?;
+
+	let size = fs::metadata(&path)
+		.await
+		.with_message("Failed to read backup")?
+		.len();
+
+	Ok(PostgresBackup {
+		id,
+		database: database.to_string(),
+		created_on,
+		size,
+	})
+}
+
+/// Returns the backups sorted by the newest first
+pub async fn list(database: &str) -> Result<Vec<PostgresBackup>, Error> {
+	let dir = database_dir(database)?;
+	let mut entries = match fs::read_dir(&dir).await {
+		Ok(e) => e,
+		// no backup was created yet
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+		Err(e) => {
+			return Err(Error::any("Failed to read backups directory", e));
+		}
+	};
+
+	let mut backups = vec![];
+	while let Some(entry) = entries
+		.next_entry()
+		.await
+		.with_message("Failed to read backups directory")?
+	{
+		let path = entry.path();
+		let Some(id) = path
+			.file_name()
+			.and_then(|n| n.to_str())
+			.and_then(|n| n.strip_suffix(".dump"))
+			.and_then(|n| n.parse::<u64>().ok())
+		else {
+			continue;
+		};
+
+		let size = entry
+			.metadata()
+			.await
+			.with_message("Failed to read backup")?
+			.len();
+
+		backups.push(PostgresBackup {
+			id,
+			database: database.to_string(),
+			created_on: DateTime::from_ms(id),
+			size,
+		});
+	}
+
+	backups.sort_by_key(|b| Reverse(b.id));
+
+	Ok(backups)
+}
+
+pub async fn restore(database: &str, id: u64) -> Result<(), Error> {
+	let path = path(database, id).await?;
+
+	let mut file = File::open(&path)
+		.await
+		.with_message("Failed to open backup")?;
+
+	let mut child = utils::restore_database(database).await?;
+
+	io::copy(&mut file, &mut child)
+		.await
+		.with_message("Failed to restore Postgres database")?;
+
+	child
+		.wait()
+		.await
+		.with_message("Postgres restore process failed")
+}
+
+/// Keeps the newest backup of the `count` newest periods
+fn keep_per_period<K: Hash + Eq>(
+	backups: &[PostgresBackup],
+	count: usize,
+	period: impl Fn(NaiveDate) -> K,
+	keep: &mut HashSet<u64>,
+) {
+	let mut seen = HashSet::new();
+
+	// the backups are sorted by the newest first
+	for backup in backups {
+		if seen.len() >= count {
+			break;
+		}
+
+		if seen.insert(period(backup.created_on.inner().date_naive())) {
+			keep.insert(backup.id);
+		}
+	}
+}
+
+/// Returns the ids of the backups the retention wants to keep, the backups
+/// need to be sorted by the newest first
+fn retained(
+	backups: &[PostgresBackup],
+	retention: &BackupRetention,
+) -> HashSet<u64> {
+	let mut keep = HashSet::new();
+	// never remove the newest backup
+	keep.extend(backups.first().map(|b| b.id));
+	keep_per_period(backups, retention.daily, |d| d, &mut keep);
+	// the iso week contains the year, so the week around new year is only
+	// counted once
+	keep_per_period(backups, retention.weekly, |d| d.iso_week(), &mut keep);
+	keep_per_period(
+		backups,
+		retention.monthly,
+		|d| (d.year(), d.month()),
+		&mut keep,
+	);
+
+	keep
+}
+
+/// Removes all backups which are not needed anymore by the retention
+///
+/// Returns the ids of the removed backups
+pub async fn prune(
+	database: &str,
+	retention: &BackupRetention,
+) -> Result<Vec<u64>, Error> {
+	let backups = list(database).await?;
+	let keep = retained(&backups, retention);
+
+	let dir = database_dir(database)?;
+	let mut removed = vec![];
+	for backup in backups {
+		if keep.contains(&backup.id) {
+			continue;
+		}
+
+		fs::remove_file(backup_path(&dir, backup.id))
+			.await
+			.with_message("Failed to remove backup")?;
+		removed.push(backup.id);
+	}
+
+	Ok(removed)
+}
+
+async fn run_backups(cfg: &PostgresBackupsConfig) -> Result<(), Error> {
+	let databases = if cfg.databases.is_empty() {
+		Client::new().await?.list_databases().await?
+	} else {
+		cfg.databases.clone()
+	};
+
+	for database in databases {
+		match create(&database).await {
+			Ok(backup) => {
+				info!("created backup {} of database {database}", backup.id)
+			}
+			Err(e) => {
+				error!("failed to create backup of database {database}: {e}");
+				// don't prune if the backup failed
+				continue;
+			}
+		}
+
+		match prune(&database, &cfg.retention).await {
+			Ok(removed) if !removed.is_empty() => {
+				info!("removed backups {removed:?} of database {database}")
+			}
+			Ok(_) => {}
+			Err(e) => {
+				error!("failed to prune backups of database {database}: {e}")
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Runs forever and creates the backups according to the schedule
+pub async fn run_scheduler(cfg: PostgresBackupsConfig) {
+	info!("Postgres backups scheduled with \"{}\"", cfg.schedule);
+
+	loop {
+		let now = DateTime::now();
+		let Some(next) = cfg.schedule.next_after(now.into_inner()) else {
+			warn!(
+				"Postgres backup schedule \"{}\" never matches",
+				cfg.schedule
+			);
+			return;
+		};
+
+		// the duration is always positive since next is after now
+		sleep((next - now.into_inner()).to_std().unwrap_or_default()).await;
+
+		if let Err(e) = run_backups(&cfg).await {
+			error!("Postgres backups failed: {e}");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::{TimeZone as _, Utc};
+
+	use super::*;
+
+	/// Returns the backups sorted by the newest first like `list`
+	fn backups(dates: &[(i32, u32, u32)]) -> Vec<PostgresBackup> {
+		let mut backups = dates
+			.iter()
+			.map(|&(y, m, d)| {
+				let time = Utc.with_ymd_and_hms(y, m, d, 3, 0, 0).unwrap();
+				let id = time.timestamp_millis() as u64;
+				PostgresBackup {
+					id,
+					database: "db".into(),
+					created_on: DateTime::from_ms(id),
+					size: 0,
+				}
+			})
+			.collect::<Vec<_>>();
+		backups.sort_by_key(|b| Reverse(b.id));
+
+		backups
+	}
+
+	fn retained_dates(
+		backups: &[PostgresBackup],
+		retention: BackupRetention,
+	) -> Vec<String> {
+		let keep = retained(backups, &retention);
+		backups
+			.iter()
+			.filter(|b| keep.contains(&b.id))
+			.map(|b| b.created_on.inner().date_naive().to_string())
+			.collect()
+	}
+
+	#[test]
+	fn keeps_newest_per_day() {
+		let mut all = backups(&[(2025, 3, 10), (2025, 3, 9), (2025, 3, 8)]);
+		// a second backup on the newest day
+		let mut second = all[0].clone();
+		second.id += 1000;
+		second.created_on = DateTime::from_ms(second.id);
+		all.insert(0, second.clone());
+
+		let keep = retained(
+			&all,
+			&BackupRetention {
+				daily: 2,
+				weekly: 0,
+				monthly: 0,
+			},
+		);
+
+		assert!(keep.contains(&second.id));
+		assert!(!keep.contains(&all[1].id));
+		assert!(keep.contains(&all[2].id));
+		assert!(!keep.contains(&all[3].id));
+	}
+
+	#[test]
+	fn weeks_across_new_year() {
+		// the 29th of december 2024 is a sunday, monday the 30th starts
+		// iso week 1 of 2025
+		let all = backups(&[
+			(2025, 1, 2),
+			(2024, 12, 31),
+			(2024, 12, 30),
+			(2024, 12, 29),
+			(2024, 12, 22),
+			(2024, 12, 15),
+		]);
+
+		let dates = retained_dates(
+			&all,
+			BackupRetention {
+				daily: 0,
+				weekly: 3,
+				monthly: 0,
+			},
+		);
+
+		assert_eq!(dates, ["2025-01-02", "2024-12-29", "2024-12-22"]);
+	}
+
+	#[test]
+	fn months_across_years() {
+		let all = backups(&[
+			(2025, 2, 3),
+			(2025, 1, 31),
+			(2025, 1, 1),
+			(2024, 12, 31),
+			(2024, 12, 1),
+			(2024, 2, 29),
+			(2023, 2, 28),
+		]);
+
+		let dates = retained_dates(
+			&all,
+			BackupRetention {
+				daily: 0,
+				weekly: 0,
+				monthly: 5,
+			},
+		);
+
+		// february 2024 and 2023 are different months
+		assert_eq!(
+			dates,
+			[
+				"2025-02-03",
+				"2025-01-31",
+				"2024-12-31",
+				"2024-02-29",
+				"2023-02-28"
+			]
+		);
+	}
+
+	#[test]
+	fn rules_are_combined() {
+		let all = backups(&[
+			(2025, 3, 3),
+			(2025, 3, 2),
+			(2025, 3, 1),
+			(2025, 2, 28),
+			(2025, 2, 20),
+			(2025, 1, 10),
+		]);
+
+		let dates = retained_dates(
+			&all,
+			BackupRetention {
+				daily: 2,
+				weekly: 2,
+				monthly: 3,
+			},
+		);
+
+		assert_eq!(
+			dates,
+			["2025-03-03", "2025-03-02", "2025-02-28", "2025-01-10"]
+		);
+	}
+
+	#[test]
+	fn keeps_newest_without_rules() {
+		let all = backups(&[(2025, 3, 3), (2025, 3, 2)]);
+
+		let dates = retained_dates(
+			&all,
+			BackupRetention {
+				daily: 0,
+				weekly: 0,
+				monthly: 0,
+			},
+		);
+
+		assert_eq!(dates, ["2025-03-03"]);
+	}
+}
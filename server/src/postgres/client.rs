@@ -128,6 +128,35 @@ impl Client {
 		Ok(databases)
 	}
 
+	/// Returns the name and the size in bytes of every database
+	pub async fn database_sizes(&self) -> Result<Vec<(String, i64)>, CliError> {
+		let sql = "SELECT datname, pg_database_size(datname) FROM pg_database \
+			WHERE datistemplate = false";
+
+		let rows = self
+			.client
+			.query(sql, &[])
+			.await
+			.with_message("Failed to get the database sizes")?;
+
+		let sizes = rows
+			.into_iter()
+			.map(|row| (row.get::<_, String>(0), row.get::<_, i64>(1)))
+			.collect();
+
+		Ok(sizes)
+	}
+
+	pub async fn version(&self) -> Result<String, CliError> {
+		let row = self
+			.client
+			.query_one("SHOW server_version", &[])
+			.await
+			.with_message("Failed to get the PostgreSQL version")?;
+
+		Ok(row.get::<_, String>(0))
+	}
+
 	pub async fn database_exists(&self, name: &str) -> Result<bool, CliError> {
 		let sql = "SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)";
 
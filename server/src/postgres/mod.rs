@@ -1,5 +1,7 @@
+pub mod backups;
 pub mod client;
 pub mod routes;
+mod schedule;
 pub mod utils;
 
 use chuchi_crypto::token::Token;
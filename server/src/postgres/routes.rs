@@ -5,7 +5,7 @@ use api::{
 	error::WithMessage,
 	postgres::{
 		CreateDatabaseReq, CreateDatabaseRes, DatabaseName, NewPasswordRes,
-		PostgresDatabasesRes,
+		PostgresBackup, PostgresBackupsRes, PostgresDatabasesRes,
 	},
 };
 use axum::{
@@ -16,16 +16,16 @@ use axum::{
 };
 use chuchi_crypto::token::Token;
 use futures::TryStreamExt;
-use tokio::{io, time::sleep};
+use tokio::{fs::File, io, time::sleep};
 use tokio_util::io::{ReaderStream, StreamReader};
 
 use crate::{
-	postgres::{Client, utils},
-	server::{Authenticated, router::AppState},
+	postgres::{Client, backups, utils},
+	server::{Authenticated, ScopePostgres, ScopeRead, router::AppState},
 };
 
 async fn databases(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRead>,
 ) -> Result<Json<PostgresDatabasesRes>, Error> {
 	let client = Client::new().await?;
 
@@ -37,7 +37,7 @@ async fn databases(
 }
 
 async fn create_database(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopePostgres>,
 	Json(req): Json<CreateDatabaseReq>,
 ) -> Result<Json<CreateDatabaseRes>, Error> {
 	let client = Client::new().await?;
@@ -70,7 +70,7 @@ async fn create_database(
 }
 
 async fn new_password(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopePostgres>,
 	Path(name): Path<DatabaseName>,
 ) -> Result<Json<NewPasswordRes>, Error> {
 	let client = Client::new().await?;
@@ -97,7 +97,7 @@ async fn new_password(
 }
 
 async fn restore_database(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopePostgres>,
 	Path(name): Path<DatabaseName>,
 	body: Body,
 ) -> Result<(), Error> {
@@ -141,7 +141,7 @@ async fn restore_database(
 }
 
 async fn dump_database(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopePostgres>,
 	Path(name): Path<DatabaseName>,
 ) -> Result<Body, Error> {
 	let client = Client::new().await?;
@@ -175,10 +175,75 @@ async fn dump_database(
 	Ok(Body::from_stream(ReaderStream::new(child)))
 }
 
+async fn database_backups(
+	_auth: Authenticated<ScopeRead>,
+	Path(name): Path<DatabaseName>,
+) -> Result<Json<PostgresBackupsRes>, Error> {
+	backups::list(name.as_ref())
+		.await
+		.map(|b| Json(PostgresBackupsRes(b)))
+}
+
+async fn create_backup(
+	_auth: Authenticated<ScopePostgres>,
+	Path(name): Path<DatabaseName>,
+) -> Result<Json<PostgresBackup>, Error> {
+	let client = Client::new().await?;
+
+	if !client
+		.database_exists(name.as_ref())
+		.await
+		.with_message("db error")?
+	{
+		return Err(Error::DatabaseNotFound);
+	}
+
+	backups::create(name.as_ref()).await.map(Json)
+}
+
+async fn download_backup(
+	_auth: Authenticated<ScopePostgres>,
+	Path((name, backup)): Path<(DatabaseName, u64)>,
+) -> Result<Body, Error> {
+	let path = backups::path(name.as_ref(), backup).await?;
+
+	let file = File::open(&path)
+		.await
+		.with_message("Failed to open backup")?;
+
+	Ok(Body::from_stream(ReaderStream::new(file)))
+}
+
+async fn restore_backup(
+	_auth: Authenticated<ScopePostgres>,
+	Path((name, backup)): Path<(DatabaseName, u64)>,
+) -> Result<(), Error> {
+	let client = Client::new().await?;
+
+	if !client
+		.database_exists(name.as_ref())
+		.await
+		.with_message("db error")?
+	{
+		return Err(Error::DatabaseNotFound);
+	}
+
+	backups::restore(name.as_ref(), backup).await
+}
+
 pub fn routes() -> Router<AppState> {
 	Router::new()
 		.route("/databases", get(databases).post(create_database))
 		.route("/databases/{name}/password", post(new_password))
 		.route("/databases/{name}/restore", put(restore_database))
 		.route("/databases/{name}/dump", get(dump_database))
+		.route(
+			"/databases/{name}/backups",
+			get(database_backups).post(create_backup),
+		)
+		.route("/databases/{name}/backups/{backup}", get(download_backup))
+		.route(
+			"/databases/{name}/backups/{backup}/restore",
+			post(restore_backup),
+		)
 }
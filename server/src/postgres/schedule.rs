@@ -0,0 +1,379 @@
+/*!
+A cron like schedule with the fields `minute hour day-of-month month
+day-of-week`.
+
+Every field supports `*`, single values, ranges `1-5`, lists `1,15` and
+steps `0-30/10`, a step can also be used with `*`. The day of the week starts
+with 0 for sunday, 7 is also accepted as sunday. All times are in UTC.
+
+Like in cron, if both the day of the month and the day of the week are
+restricted, a day matches if either of them matches.
+*/
+
+use std::{fmt, str::FromStr};
+
+use chrono::{
+	DateTime, Datelike as _, Duration, NaiveDate, TimeZone as _, Timelike as _,
+	Utc,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[error("Invalid schedule {schedule}: {message}")]
+pub struct ScheduleError {
+	schedule: String,
+	message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+	raw: String,
+	minutes: u64,
+	hours: u64,
+	days: u64,
+	months: u64,
+	weekdays: u64,
+	days_restricted: bool,
+	weekdays_restricted: bool,
+}
+
+/// Parses a field into a bitset, returns if the field was restricted
+fn parse_field(field: &str, min: u32, max: u32) -> Result<(u64, bool), String> {
+	let mut bits = 0u64;
+
+	for part in field.split(',') {
+		let (range, step) = match part.split_once('/') {
+			Some((range, step)) => {
+				let step = step
+					.parse::<u32>()
+					.ok()
+					.filter(|s| *s > 0)
+					.ok_or_else(|| format!("invalid step {step}"))?;
+				(range, step)
+			}
+			None => (part, 1),
+		};
+
+		let parse_num = |s: &str| {
+			s.parse::<u32>()
+				.ok()
+				.filter(|n| (min..=max).contains(n))
+				.ok_or_else(|| {
+					format!("{s} is not a number between {min} and {max}")
+				})
+		};
+
+		let (start, end) = match range {
+			"*" => (min, max),
+			r => match r.split_once('-') {
+				Some((start, end)) => (parse_num(start)?, parse_num(end)?),
+				// a single value with a step means until the max
+				None if step > 1 => (parse_num(r)?, max),
+				None => {
+					let n = parse_num(r)?;
+					(n, n)
+				}
+			},
+		};
+
+		if start > end {
+			return Err(format!("invalid range {range}"));
+		}
+
+		for n in (start..=end).step_by(step as usize) {
+			bits |= 1 << n;
+		}
+	}
+
+	Ok((bits, field != "*"))
+}
+
+fn has(bits: u64, n: u32) -> bool {
+	bits & (1 << n) != 0
+}
+
+impl Schedule {
+	fn matches_day(&self, date: NaiveDate) -> bool {
+		let day = has(self.days, date.day());
+		let weekday = has(self.weekdays, date.weekday().num_days_from_sunday());
+
+		match (self.days_restricted, self.weekdays_restricted) {
+			(true, true) => day || weekday,
+			(true, false) => day,
+			(false, true) => weekday,
+			(false, false) => true,
+		}
+	}
+
+	/// Returns the next time after `after` (exclusive) matching the schedule
+	///
+	/// Returns None if no time within the next 5 years matches, for example
+	/// for the 31st of february
+	pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+		let mut time = after
+			.with_second(0)
+			.and_then(|t| t.with_nanosecond(0))
+			.unwrap() + Duration::minutes(1);
+		let limit = after + Duration::days(5 * 366);
+
+		while time < limit {
+			let date = time.date_naive();
+
+			if !has(self.months, date.month()) {
+				// jump to the first day of the next month
+				let (year, month) = match date.month() {
+					12 => (date.year() + 1, 1),
+					m => (date.year(), m + 1),
+				};
+				time = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).unwrap();
+				continue;
+			}
+
+			if !self.matches_day(date) {
+				time = Utc
+					.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+					+ Duration::days(1);
+				continue;
+			}
+
+			if !has(self.hours, time.hour()) {
+				time = time.with_minute(0).unwrap() + Duration::hours(1);
+				continue;
+			}
+
+			if !has(self.minutes, time.minute()) {
+				time += Duration::minutes(1);
+				continue;
+			}
+
+			return Some(time);
+		}
+
+		None
+	}
+}
+
+impl FromStr for Schedule {
+	type Err = ScheduleError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let err = |message: String| ScheduleError {
+			schedule: s.to_string(),
+			message,
+		};
+
+		let fields = s.split_whitespace().collect::<Vec<_>>();
+		let [minutes, hours, days, months, weekdays] = fields[..] else {
+			return Err(err("expected 5 fields".into()));
+		};
+
+		let (minutes, _) = parse_field(minutes, 0, 59).map_err(err)?;
+		let (hours, _) = parse_field(hours, 0, 23).map_err(err)?;
+		let (days, days_restricted) = parse_field(days, 1, 31).map_err(err)?;
+		let (months, _) = parse_field(months, 1, 12).map_err(err)?;
+		let (mut weekdays, weekdays_restricted) =
+			parse_field(weekdays, 0, 7).map_err(err)?;
+
+		// 7 is also sunday
+		if has(weekdays, 7) {
+			weekdays |= 1;
+		}
+
+		Ok(Self {
+			raw: fields.join(" "),
+			minutes,
+			hours,
+			days,
+			months,
+			weekdays,
+			days_restricted,
+			weekdays_restricted,
+		})
+	}
+}
+
+impl fmt::Display for Schedule {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.raw)
+	}
+}
+
+impl Serialize for Schedule {
+	fn serialize<S: Serializer>(
+		&self,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&self.raw)
+	}
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+	fn deserialize<D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		s.parse().map_err(de::Error::custom)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn time(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+		Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+	}
+
+	fn schedule(s: &str) -> Schedule {
+		s.parse().unwrap()
+	}
+
+	/// Returns the next `n` times after `after`
+	fn next_n(s: &str, after: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+		let schedule = schedule(s);
+		let mut times = vec![];
+		let mut after = after;
+		for _ in 0..n {
+			after = schedule.next_after(after).unwrap();
+			times.push(after);
+		}
+
+		times
+	}
+
+	#[test]
+	fn fields() {
+		let (bits, restricted) = parse_field("*", 0, 59).unwrap();
+		assert_eq!(bits, (1 << 60) - 1);
+		assert!(!restricted);
+
+		let (bits, restricted) = parse_field("*/15", 0, 59).unwrap();
+		assert_eq!(bits, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+		assert!(restricted);
+
+		let (bits, _) = parse_field("1-5", 0, 59).unwrap();
+		assert_eq!(bits, 0b11_1110);
+
+		let (bits, _) = parse_field("0-30/10,45", 0, 59).unwrap();
+		assert_eq!(bits, 1 | 1 << 10 | 1 << 20 | 1 << 30 | 1 << 45);
+
+		// a single value with a step runs until the max
+		let (bits, _) = parse_field("20/2", 0, 23).unwrap();
+		assert_eq!(bits, 1 << 20 | 1 << 22);
+
+		let (bits, _) = parse_field("1,15", 1, 31).unwrap();
+		assert_eq!(bits, 1 << 1 | 1 << 15);
+	}
+
+	#[test]
+	fn invalid_fields() {
+		assert!(parse_field("60", 0, 59).is_err());
+		assert!(parse_field("0", 1, 31).is_err());
+		assert!(parse_field("5-1", 0, 59).is_err());
+		assert!(parse_field("*/0", 0, 59).is_err());
+		assert!(parse_field("", 0, 59).is_err());
+		assert!(parse_field("a", 0, 59).is_err());
+		assert!("* * * *".parse::<Schedule>().is_err());
+		assert!("* * * * * *".parse::<Schedule>().is_err());
+	}
+
+	#[test]
+	fn steps_ranges_and_lists() {
+		assert_eq!(
+			next_n("*/20 9-10 * * *", time(2025, 3, 10, 10, 30), 3),
+			[
+				time(2025, 3, 10, 10, 40),
+				time(2025, 3, 11, 9, 0),
+				time(2025, 3, 11, 9, 20),
+			]
+		);
+
+		assert_eq!(
+			next_n("30 3 1,15 * *", time(2025, 3, 10, 0, 0), 3),
+			[
+				time(2025, 3, 15, 3, 30),
+				time(2025, 4, 1, 3, 30),
+				time(2025, 4, 15, 3, 30),
+			]
+		);
+	}
+
+	#[test]
+	fn next_is_exclusive() {
+		let s = schedule("0 3 * * *");
+		assert_eq!(
+			s.next_after(time(2025, 3, 10, 3, 0)),
+			Some(time(2025, 3, 11, 3, 0))
+		);
+		assert_eq!(
+			s.next_after(time(2025, 3, 10, 2, 59)),
+			Some(time(2025, 3, 10, 3, 0))
+		);
+	}
+
+	#[test]
+	fn month_and_year_boundaries() {
+		assert_eq!(
+			next_n("0 0 1 */6 *", time(2025, 8, 20, 0, 0), 2),
+			[time(2026, 1, 1, 0, 0), time(2026, 7, 1, 0, 0)]
+		);
+
+		// only leap years have a 29th of february
+		assert_eq!(
+			schedule("0 0 29 2 *").next_after(time(2025, 1, 1, 0, 0)),
+			Some(time(2028, 2, 29, 0, 0))
+		);
+		assert_eq!(
+			schedule("0 0 31 2 *").next_after(time(2025, 1, 1, 0, 0)),
+			None
+		);
+	}
+
+	#[test]
+	fn day_of_month_or_day_of_week() {
+		// the 10th of march 2025 is a monday
+		// only the day of the week is restricted
+		assert_eq!(
+			next_n("0 0 * * 0", time(2025, 3, 10, 0, 0), 2),
+			[time(2025, 3, 16, 0, 0), time(2025, 3, 23, 0, 0)]
+		);
+
+		// 7 is also sunday
+		assert_eq!(
+			schedule("0 0 * * 7").next_after(time(2025, 3, 10, 0, 0)),
+			Some(time(2025, 3, 16, 0, 0))
+		);
+
+		// only the day of the month is restricted
+		assert_eq!(
+			next_n("0 0 13 * *", time(2025, 3, 10, 0, 0), 2),
+			[time(2025, 3, 13, 0, 0), time(2025, 4, 13, 0, 0)]
+		);
+
+		// both are restricted, either of them matches
+		assert_eq!(
+			next_n("0 0 13 * 5", time(2025, 3, 10, 0, 0), 3),
+			[
+				time(2025, 3, 13, 0, 0),
+				time(2025, 3, 14, 0, 0),
+				time(2025, 3, 21, 0, 0),
+			]
+		);
+
+		// a step counts as restricted
+		assert_eq!(
+			next_n("0 0 */10 * 1", time(2025, 3, 10, 0, 0), 3),
+			[
+				time(2025, 3, 11, 0, 0),
+				time(2025, 3, 17, 0, 0),
+				time(2025, 3, 21, 0, 0),
+			]
+		);
+	}
+
+	#[test]
+	fn display_normalizes_whitespace() {
+		assert_eq!(schedule(" 0  3 * *\t* ").to_string(), "0 3 * * *");
+	}
+}
@@ -0,0 +1,356 @@
+/*!
+A client for the HTTP API v2 of the local registry.
+
+The registry container is reached directly over the docker network so no
+tls is required. For authentication the credentials stored by
+`docker login` (which happens in `hostdinghy setup registry`) are used.
+*/
+
+use std::{collections::HashMap, env, path::PathBuf};
+
+use api::{
+	error::{Error, WithMessage as _},
+	registry::{RegistryRepository, RegistryTag},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use bollard::secret::ContainerSummaryStateEnum;
+use chuchi_postgres::time::DateTime;
+use futures::future;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, de::DeserializeOwned};
+use tokio::fs;
+
+use crate::docker::Docker;
+
+const MANIFEST_ACCEPT: &str = "application/vnd.docker.distribution.manifest.v2+json, \
+	application/vnd.oci.image.manifest.v1+json, \
+	application/vnd.docker.distribution.manifest.list.v2+json, \
+	application/vnd.oci.image.index.v1+json";
+
+#[derive(Debug, Deserialize)]
+struct DockerConfig {
+	#[serde(default)]
+	auths: HashMap<String, DockerAuth>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DockerAuth {
+	auth: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Catalog {
+	#[serde(default)]
+	repositories: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagList {
+	// the registry returns null if the repository has no tags
+	tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Descriptor {
+	digest: String,
+	size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Platform {
+	os: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexEntry {
+	digest: String,
+	platform: Option<Platform>,
+}
+
+/// Either an image manifest or an index (multi platform image)
+#[derive(Debug, Deserialize)]
+struct Manifest {
+	config: Option<Descriptor>,
+	#[serde(default)]
+	layers: Vec<Descriptor>,
+	#[serde(default)]
+	manifests: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImageConfig {
+	created: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistryClient {
+	inner: reqwest::Client,
+	base_url: String,
+	username: String,
+	password: String,
+}
+
+impl RegistryClient {
+	/// Finds the registry container and reads the credentials
+	pub async fn new(docker: &Docker, domain: &str) -> Result<Self, Error> {
+		let (username, password) = docker_credentials(domain).await?;
+
+		Ok(Self {
+			inner: reqwest::Client::new(),
+			base_url: format!("http://{}:5000", registry_ip(docker).await?),
+			username,
+			password,
+		})
+	}
+
+	fn get(&self, uri: &str) -> RequestBuilder {
+		self.inner
+			.get(format!("{}{uri}", self.base_url))
+			.basic_auth(&self.username, Some(&self.password))
+	}
+
+	fn delete(&self, uri: &str) -> RequestBuilder {
+		self.inner
+			.delete(format!("{}{uri}", self.base_url))
+			.basic_auth(&self.username, Some(&self.password))
+	}
+
+	async fn send(&self, req: RequestBuilder) -> Result<Response, Error> {
+		let response = req
+			.send()
+			.await
+			.with_message("failed to send registry request")?;
+
+		match response.status() {
+			s if s.is_success() => Ok(response),
+			StatusCode::NOT_FOUND => Err(Error::RepositoryNotFound),
+			StatusCode::METHOD_NOT_ALLOWED => Err(Error::any(
+				"The registry does not allow deleting",
+				"enable storage.delete in $HOSTDINGHY_DIR/registry/config.yml",
+			)),
+			_ => {
+				let text = response
+					.text()
+					.await
+					.with_message("failed to read error response")?;
+
+				Err(Error::any("registry request failed", text))
+			}
+		}
+	}
+
+	async fn send_json<Res>(&self, req: RequestBuilder) -> Result<Res, Error>
+	where
+		Res: DeserializeOwned,
+	{
+		self.send(req)
+			.await?
+			.json()
+			.await
+			.with_message("failed to parse registry response")
+	}
+
+	pub async fn catalog(&self) -> Result<Vec<String>, Error> {
+		self.send_json::<Catalog>(self.get("/v2/_catalog?n=10000"))
+			.await
+			.map(|c| c.repositories)
+	}
+
+	/// Returns all repositories following the `app_id/service` naming
+	pub async fn repositories(&self) -> Result<Vec<RegistryRepository>, Error> {
+		let mut repositories = self
+			.catalog()
+			.await?
+			.into_iter()
+			.filter_map(|repo| {
+				let (app_id, service) = repo.split_once('/')?;
+
+				Some(RegistryRepository {
+					app_id: app_id.parse().ok()?,
+					service: service.to_string(),
+				})
+			})
+			.collect::<Vec<_>>();
+		repositories.sort_by(|a, b| {
+			(a.app_id.as_ref(), &a.service)
+				.cmp(&(b.app_id.as_ref(), &b.service))
+		});
+
+		Ok(repositories)
+	}
+
+	pub async fn tags(&self, repository: &str) -> Result<Vec<String>, Error> {
+		self.send_json::<TagList>(
+			self.get(&format!("/v2/{repository}/tags/list")),
+		)
+		.await
+		.map(|t| t.tags.unwrap_or_default())
+	}
+
+	/// Returns all tags with their details sorted by the newest first
+	pub async fn repository_tags(
+		&self,
+		repository: &str,
+	) -> Result<Vec<RegistryTag>, Error> {
+		let tags = self.tags(repository).await?;
+
+		let mut tags = future::try_join_all(
+			tags.iter().map(|tag| self.tag(repository, tag)),
+		)
+		.await?;
+		tags.sort_by(|a, b| {
+			b.created_on
+				.cmp(&a.created_on)
+				.then_with(|| a.name.cmp(&b.name))
+		});
+
+		Ok(tags)
+	}
+
+	/// Returns the digest and the manifest
+	async fn manifest(
+		&self,
+		repository: &str,
+		reference: &str,
+	) -> Result<(String, Manifest), Error> {
+		let response = self
+			.send(
+				self.get(&format!("/v2/{repository}/manifests/{reference}"))
+					.header("Accept", MANIFEST_ACCEPT),
+			)
+			.await?;
+
+		let digest = response
+			.headers()
+			.get("Docker-Content-Digest")
+			.and_then(|d| d.to_str().ok())
+			.unwrap_or(reference)
+			.to_string();
+
+		let manifest = response
+			.json()
+			.await
+			.with_message("failed to parse manifest")?;
+
+		Ok((digest, manifest))
+	}
+
+	pub async fn tag(
+		&self,
+		repository: &str,
+		tag: &str,
+	) -> Result<RegistryTag, Error> {
+		let (digest, mut manifest) = self.manifest(repository, tag).await?;
+
+		// an index only references other manifests, the size and creation
+		// date are taken from the first real platform (attestations use
+		// the os unknown)
+		if manifest.config.is_none() {
+			let entry = manifest
+				.manifests
+				.iter()
+				.find(|m| m.platform.as_ref().is_none_or(|p| p.os != "unknown"))
+				.ok_or_else(|| {
+					Error::any(
+						"invalid manifest",
+						format!("{repository}:{tag}"),
+					)
+				})?;
+
+			manifest = self.manifest(repository, &entry.digest).await?.1;
+		}
+
+		let config = manifest.config.ok_or_else(|| {
+			Error::any("manifest without config", format!("{repository}:{tag}"))
+		})?;
+
+		let image_config: ImageConfig = self
+			.send_json(
+				self.get(&format!("/v2/{repository}/blobs/{}", config.digest)),
+			)
+			.await?;
+
+		Ok(RegistryTag {
+			name: tag.to_string(),
+			digest,
+			created_on: image_config
+				.created
+				.and_then(|c| DateTime::parse_from_iso8601(&c).ok()),
+			size: config.size
+				+ manifest.layers.iter().map(|l| l.size).sum::<u64>(),
+		})
+	}
+
+	/// Deletes the manifest, this removes every tag pointing to it
+	pub async fn delete_manifest(
+		&self,
+		repository: &str,
+		digest: &str,
+	) -> Result<(), Error> {
+		self.send(self.delete(&format!("/v2/{repository}/manifests/{digest}")))
+			.await
+			.map(|_| ())
+	}
+}
+
+/// Returns the ip address of the running registry container
+async fn registry_ip(docker: &Docker) -> Result<String, Error> {
+	let containers = docker
+		.containers_by_composer_project("registry")
+		.await
+		.with_message("Failed to list registry containers")?;
+
+	containers
+		.into_iter()
+		.filter(|c| c.state == Some(ContainerSummaryStateEnum::RUNNING))
+		.filter_map(|c| c.network_settings?.networks)
+		.flat_map(|n| n.into_values())
+		.filter_map(|n| n.ip_address)
+		.find(|ip| !ip.is_empty())
+		.ok_or_else(|| {
+			Error::any(
+				"Could not find the registry container",
+				"is the registry running?",
+			)
+		})
+}
+
+fn docker_config_path() -> PathBuf {
+	match env::var("DOCKER_CONFIG") {
+		Ok(dir) => PathBuf::from(dir).join("config.json"),
+		Err(_) => {
+			PathBuf::from(env::var("HOME").unwrap_or_else(|_| "/root".into()))
+				.join(".docker/config.json")
+		}
+	}
+}
+
+async fn docker_credentials(domain: &str) -> Result<(String, String), Error> {
+	let path = docker_config_path();
+	let config = fs::read_to_string(&path)
+		.await
+		.with_message("Failed to read docker config")?;
+	let config: DockerConfig = serde_json::from_str(&config)
+		.with_message("Failed to parse docker config")?;
+
+	let auth = config
+		.auths
+		.get(domain)
+		.and_then(|a| a.auth.as_ref())
+		.ok_or_else(|| {
+			Error::any(
+				"No registry credentials found",
+				format!("run `docker login {domain}`"),
+			)
+		})?;
+
+	let auth = STANDARD
+		.decode(auth)
+		.ok()
+		.and_then(|a| String::from_utf8(a).ok())
+		.ok_or_else(|| Error::any("Invalid registry credentials", domain))?;
+
+	auth.split_once(':')
+		.map(|(u, p)| (u.to_string(), p.to_string()))
+		.ok_or_else(|| Error::any("Invalid registry credentials", domain))
+}
@@ -0,0 +1,172 @@
+/*!
+Garbage collection of the registry.
+
+First all tags outside of the retention get deleted through the registry api,
+afterwards `registry garbage-collect` removes the blobs which are no longer
+referenced by any manifest.
+
+The garbage collection does not lock the registry, pushing while it runs
+might lead to missing layers in the pushed image.
+*/
+
+use std::{collections::HashSet, path::Path};
+
+use api::{
+	apps::AppId,
+	error::{Error, WithMessage as _},
+	registry::{RegistryDeletedTag, RegistryGcRes},
+};
+use compose_yml::{Compose, ComposeImage, same_registry};
+use tokio::fs;
+use tracing::info;
+
+use crate::{
+	docker::Docker,
+	registry::client::RegistryClient,
+	utils::{cmd::cmd, compose, hostdinghy_dir, is_file},
+};
+
+/// Returns the `(repository, tag)` and `(repository, digest)` of every image in
+/// the registry referenced by the compose file of an app
+async fn referenced_images(
+	hostdinghy_dir: &Path,
+	registry: &str,
+) -> Result<HashSet<(String, String)>, Error> {
+	let mut entries = fs::read_dir(hostdinghy_dir)
+		.await
+		.with_message("Failed to read $HOSTDINGHY_DIR")?;
+
+	let mut images = HashSet::new();
+	while let Some(entry) = entries
+		.next_entry()
+		.await
+		.with_message("Failed to read $HOSTDINGHY_DIR")?
+	{
+		let Some(app_id) = entry
+			.file_name()
+			.to_str()
+			.and_then(|n| n.parse::<AppId>().ok())
+		else {
+			continue;
+		};
+
+		let compose_file = entry.path().join("compose.yml");
+		if !is_file(&compose_file).await {
+			continue;
+		}
+
+		// if a compose file cannot be parsed we don't know which images
+		// are used, so it is better to abort
+		let compose = fs::read_to_string(&compose_file)
+			.await
+			.with_message(format!("Failed to read compose file of {app_id}"))?
+			.parse::<Compose>()?;
+
+		for service in compose.services.values() {
+			if let ComposeImage::Valid {
+				registry: reg,
+				app_id,
+				service,
+				tag,
+				digest,
+				..
+			} = service.parse_image()
+				&& same_registry(&reg, registry)
+			{
+				let repository = format!("{app_id}/{service}");
+				// docker ignores the tag if a digest is set
+				let reference =
+					digest.or(tag).unwrap_or_else(|| "latest".into());
+				images.insert((repository, reference));
+			}
+		}
+	}
+
+	Ok(images)
+}
+
+/// Returns the size of the directory in bytes
+pub(crate) async fn dir_size(dir: impl AsRef<Path>) -> Result<u64, Error> {
+	let out = cmd(&["du", "-sb", &dir.as_ref().to_string_lossy()])
+		.as_root()
+		.run()
+		.await?;
+
+	out.split_whitespace()
+		.next()
+		.and_then(|s| s.parse().ok())
+		.ok_or_else(|| Error::any("Failed to parse du output", out.clone()))
+}
+
+/// Deletes every tag which is not one of the `keep_last` newest of its
+/// repository and not referenced by an app, then runs the garbage collection
+pub async fn gc(
+	docker: &Docker,
+	domain: &str,
+	keep_last: usize,
+) -> Result<RegistryGcRes, Error> {
+	let hostdinghy_dir = hostdinghy_dir()?;
+	let registry_dir = hostdinghy_dir.join("registry");
+	let data_dir = registry_dir.join("data");
+
+	let client = RegistryClient::new(docker, domain).await?;
+	let referenced = referenced_images(&hostdinghy_dir, domain).await?;
+
+	let size_before = dir_size(&data_dir).await?;
+
+	let mut deleted = vec![];
+	for repo in client.repositories().await? {
+		let repository = format!("{}/{}", repo.app_id, repo.service);
+		let tags = client.repository_tags(&repository).await?;
+
+		let (keep, remove): (Vec<_>, Vec<_>) =
+			tags.into_iter().enumerate().partition(|(i, tag)| {
+				*i < keep_last
+					|| referenced
+						.contains(&(repository.clone(), tag.name.clone()))
+					|| referenced
+						.contains(&(repository.clone(), tag.digest.clone()))
+			});
+
+		// deleting a manifest removes all tags pointing to it, so a manifest
+		// which is still used by a kept tag cannot be deleted
+		let keep_digests =
+			keep.iter().map(|(_, t)| &t.digest).collect::<HashSet<_>>();
+		let mut deleted_digests = HashSet::new();
+
+		for (_, tag) in &remove {
+			if keep_digests.contains(&tag.digest) {
+				continue;
+			}
+
+			if deleted_digests.insert(&tag.digest) {
+				client.delete_manifest(&repository, &tag.digest).await?;
+			}
+
+			info!("deleted tag {repository}:{}", tag.name);
+			deleted.push(RegistryDeletedTag {
+				app_id: repo.app_id.clone(),
+				service: repo.service.clone(),
+				tag: tag.name.clone(),
+			});
+		}
+	}
+
+	compose::exec(
+		registry_dir.join("compose.yml"),
+		"registry",
+		&[
+			"registry",
+			"garbage-collect",
+			"/etc/docker/registry/config.yml",
+		],
+	)
+	.await?;
+
+	let size_after = dir_size(&data_dir).await?;
+
+	Ok(RegistryGcRes {
+		deleted,
+		freed_bytes: size_before.saturating_sub(size_after),
+	})
+}
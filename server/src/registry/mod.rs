@@ -1,3 +1,5 @@
+mod client;
+pub(crate) mod gc;
 pub mod routes;
 
 use std::path::Path;
@@ -9,9 +11,13 @@ use serde::{Deserialize, Serialize};
 use tokio::{fs, io::AsyncWriteExt as _};
 use tracing::{error, info};
 
-use crate::utils::{
-	cli::{CliError, WithMessage as _},
-	compose, hostdinghy_dir, verify_root,
+use crate::{
+	config::Config,
+	docker::Docker,
+	utils::{
+		cli::{CliError, WithMessage as _},
+		compose, hostdinghy_dir, verify_root,
+	},
 };
 
 pub type WebhookToken = Token<32>;
@@ -28,6 +34,15 @@ enum SubCommand {
 	RemoveUser(RemoveUser),
 	ListUsers,
 	Restart,
+	Gc(Gc),
+}
+
+#[derive(Debug, Parser)]
+pub struct Gc {
+	/// How many of the newest tags to keep per repository, tags used by an
+	/// app are always kept
+	#[clap(long, default_value_t = 10)]
+	pub keep_last: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +50,14 @@ enum SubCommand {
 pub struct RegistryConfig {
 	pub domain: String,
 	pub webhook_token: WebhookToken,
+	/// How many seconds the services need to keep running after a deployment
+	/// by the webhook, otherwise the previous images are restored
+	#[serde(default = "default_deploy_grace_period")]
+	pub deploy_grace_period: u64,
+}
+
+fn default_deploy_grace_period() -> u64 {
+	30
 }
 
 impl RegistryConfig {
@@ -47,6 +70,7 @@ impl RegistryConfig {
 		Self {
 			domain,
 			webhook_token: WebhookToken::new(),
+			deploy_grace_period: default_deploy_grace_period(),
 		}
 	}
 }
@@ -95,6 +119,27 @@ pub async fn inner_registry(registry: Registry) -> Result<(), CliError> {
 
 			info!("Registry restarted successfully.");
 		}
+		SubCommand::Gc(g) => {
+			let hostdinghy_dir = hostdinghy_dir()?;
+			let cfg = Config::read(&hostdinghy_dir)
+				.await
+				.with_message("Failed to read config")?;
+			let docker = Docker::new()?;
+
+			let res = gc::gc(&docker, &cfg.registry.domain, g.keep_last)
+				.await
+				.with_message("Registry garbage collection failed")?;
+
+			if res.deleted.is_empty() {
+				info!("No tags deleted.");
+			} else {
+				info!("Deleted tags:");
+				for tag in res.deleted {
+					info!("- {}/{}:{}", tag.app_id, tag.service, tag.tag);
+				}
+			}
+			info!("Freed {} bytes.", res.freed_bytes);
+		}
 	}
 
 	Ok(())
@@ -106,6 +151,7 @@ async fn restart_registry(
 	compose::restart(
 		hostdinghy_dir.as_ref().join("registry/compose.yml"),
 		None,
+		&[],
 	)
 	.await?;
 
@@ -168,10 +214,10 @@ pub async fn add_user(add_user: &mut AddUser) -> Result<(), CliError> {
 
 #[derive(Debug, Parser)]
 pub struct RemoveUser {
-	username: String,
+	pub username: String,
 }
 
-async fn remove_user(remove_user: RemoveUser) -> Result<(), CliError> {
+pub async fn remove_user(remove_user: RemoveUser) -> Result<(), CliError> {
 	let hostdinghy_dir = hostdinghy_dir()?;
 	let password_file = hostdinghy_dir.join("registry/registry.password");
 	let content = fs::read_to_string(&password_file).await.with_message(
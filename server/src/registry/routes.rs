@@ -1,27 +1,45 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+	time::{Duration, Instant},
+};
 
 use api::{
 	Error,
-	apps::AppId,
-	registry::{CreateUserReq, CreateUserRes, RegistryUsersRes},
+	apps::{AppId, DeploymentStatus},
+	error::WithMessage as _,
+	registry::{
+		CreateUserReq, CreateUserRes, RegistryGcReq, RegistryGcRes,
+		RegistryRepositoriesRes, RegistryTagsRes, RegistryUsersRes,
+	},
 };
 use axum::{
 	Json, Router,
 	extract::{Path, State},
 	routing::{delete, get, post},
 };
+use compose_yml::{Compose, ComposeImage, same_registry};
 use hyper::{HeaderMap, header::AUTHORIZATION};
 use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
+use tokio::fs;
 use tracing::{error, info, warn};
 
 use crate::{
+	apps::{
+		deployments::{self, DeployLocks},
+		secrets,
+	},
 	config::Config,
+	docker::Docker,
+	metrics::Metrics,
 	registry::{
-		AddUser, RemoveUser, WebhookToken, add_user, list_users, remove_user,
+		AddUser, RemoveUser, WebhookToken, add_user, client::RegistryClient,
+		gc, list_users, remove_user,
 	},
-	server::{Authenticated, router::AppState},
-	utils::{compose, hostdinghy_dir, is_file},
+	server::{Authenticated, ScopeRead, ScopeRegistry, router::AppState},
+	traefik::client::Traefik,
+	utils::{hostdinghy_dir, is_file},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +75,10 @@ pub struct PushTarget {
 
 async fn webhook(
 	State(cfg): State<Arc<Config>>,
+	State(docker): State<Docker>,
+	State(traefik): State<Traefik>,
+	State(metrics): State<Metrics>,
+	State(locks): State<DeployLocks>,
 	headers: HeaderMap,
 	body: String,
 ) -> Result<(), Error> {
@@ -93,7 +115,10 @@ async fn webhook(
 		info!("Received registry webhook:\n{:?}", events.events);
 	}
 
-	let apps_involved: HashSet<AppId> = events
+	// the pushed images (service, tag) per app
+	let mut apps_involved: HashMap<AppId, HashSet<(String, String)>> =
+		HashMap::new();
+	events
 		.events
 		.into_iter()
 		// we only care about push events
@@ -105,41 +130,159 @@ async fn webhook(
 			// lets try to parse target repository
 			let repo = e.target.repository;
 
-			let Some((app_id, _service)) =
+			let Some((app_id, service)) =
 				repo.split_once('/').and_then(|(app_id, service)| {
-					app_id.parse::<AppId>().ok().map(|a| (a, service))
+					app_id
+						.parse::<AppId>()
+						.ok()
+						.map(|a| (a, service.to_string()))
 				})
 			else {
 				warn!("unknown repository {repo}");
 				return None;
 			};
 
-			Some(app_id)
+			Some((app_id, service, e.target.tag))
 		})
-		.collect();
+		.for_each(|(app_id, service, tag)| {
+			apps_involved
+				.entry(app_id)
+				.or_default()
+				.insert((service, tag));
+		});
 
 	info!("Apps involved in the webhook: {:?}", apps_involved);
 
 	let hostdinghy_dir = hostdinghy_dir()?;
-	for app in apps_involved {
+	for (app, pushed) in apps_involved {
 		let app_dir = hostdinghy_dir.join(app.to_string());
-		let compose_file = app_dir.join("compose.yml");
-		if !is_file(&compose_file).await {
-			warn!("No compose file found for app {app}");
-			continue;
-		}
 
-		if let Err(e) = compose::up(compose_file, None).await {
-			error!("Failed to start app {app} after pull {e}");
-			continue;
-		}
+		// the registry expects a fast response, so the deployment and
+		// watching the services happens in the background
+		let docker = docker.clone();
+		let traefik = traefik.clone();
+		let metrics = metrics.clone();
+		let locks = locks.clone();
+		let cfg = cfg.clone();
+		tokio::spawn(async move {
+			// a second push waits until the previous deployment is done,
+			// the compose file and secrets are read afterwards so an edit
+			// made in the meantime is not overridden
+			let _lock = locks.lock(&app).await;
+
+			let compose_file = app_dir.join("compose.yml");
+			if !is_file(&compose_file).await {
+				warn!("No compose file found for app {app}");
+				return;
+			}
+
+			let compose = match fs::read_to_string(&compose_file)
+				.await
+				.with_message("Failed to read compose file")
+				.and_then(|c| c.parse::<Compose>().map_err(Error::from))
+			{
+				Ok(c) => c,
+				Err(e) => {
+					error!("Failed to read compose file of app {app} {e}");
+					return;
+				}
+			};
+
+			let services = services_using_images(
+				&compose,
+				&cfg.registry.domain,
+				&app,
+				&pushed,
+			);
+			if services.is_empty() {
+				info!(
+					"No service of app {app} uses the pushed images {pushed:?}"
+				);
+				return;
+			}
+
+			let env = match secrets::compose_env(&app_dir, &cfg.secret).await {
+				Ok(env) => env,
+				Err(e) => {
+					error!("Failed to read secrets of app {app} {e}");
+					return;
+				}
+			};
+
+			info!("Redeploying services {services:?} of app {app}");
+
+			let start = Instant::now();
+			let deployment = deployments::deploy(
+				&docker,
+				&traefik,
+				&app,
+				&app_dir,
+				&compose,
+				&services,
+				&env,
+				Duration::from_secs(cfg.registry.deploy_grace_period),
+			)
+			.await;
+
+			metrics.observe_webhook_deployment(
+				app.as_ref(),
+				&deployment.status.to_string(),
+				start.elapsed(),
+			);
+
+			match deployment.status {
+				DeploymentStatus::Succeeded => {
+					info!("Deployed services {services:?} of app {app}")
+				}
+				status => error!(
+					"Deployment of app {app} failed with {status:?}: {}",
+					deployment.message.unwrap_or_default()
+				),
+			}
+		});
 	}
 
 	Ok(())
 }
 
+/// Returns the compose services which reference one of the pushed images
+///
+/// A service without a tag references `latest`, a service pinned to a digest
+/// is never updated.
+fn services_using_images(
+	compose: &Compose,
+	registry: &str,
+	app: &AppId,
+	pushed: &HashSet<(String, String)>,
+) -> Vec<String> {
+	let mut services = compose
+		.services
+		.iter()
+		.filter(|(_, service)| match service.parse_image() {
+			ComposeImage::Valid {
+				registry: reg,
+				app_id,
+				service,
+				tag,
+				digest: None,
+				..
+			} => {
+				let tag = tag.unwrap_or_else(|| "latest".into());
+				same_registry(&reg, registry)
+					&& app_id == app.as_ref()
+					&& pushed.contains(&(service, tag))
+			}
+			_ => false,
+		})
+		.map(|(name, _)| name.clone())
+		.collect::<Vec<_>>();
+	services.sort();
+
+	services
+}
+
 async fn all_users(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRead>,
 ) -> Result<Json<RegistryUsersRes>, Error> {
 	list_users()
 		.await
@@ -149,7 +292,7 @@ async fn all_users(
 }
 
 async fn create_user(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRegistry>,
 	Json(req): Json<CreateUserReq>,
 ) -> Result<Json<CreateUserRes>, Error> {
 	let mut au = AddUser {
@@ -165,7 +308,7 @@ async fn create_user(
 }
 
 async fn delete_user(
-	_auth: Authenticated,
+	_auth: Authenticated<ScopeRegistry>,
 	Path(username): Path<String>,
 ) -> Result<Json<()>, Error> {
 	remove_user(RemoveUser { username })
@@ -174,9 +317,55 @@ async fn delete_user(
 		.map_err(Into::into)
 }
 
+async fn repositories(
+	_auth: Authenticated<ScopeRead>,
+	State(cfg): State<Arc<Config>>,
+	State(docker): State<Docker>,
+) -> Result<Json<RegistryRepositoriesRes>, Error> {
+	let client = RegistryClient::new(&docker, &cfg.registry.domain).await?;
+
+	client
+		.repositories()
+		.await
+		.map(RegistryRepositoriesRes)
+		.map(Json)
+}
+
+async fn repository_tags(
+	_auth: Authenticated<ScopeRead>,
+	State(cfg): State<Arc<Config>>,
+	State(docker): State<Docker>,
+	Path((app_id, service)): Path<(AppId, String)>,
+) -> Result<Json<RegistryTagsRes>, Error> {
+	let client = RegistryClient::new(&docker, &cfg.registry.domain).await?;
+
+	client
+		.repository_tags(&format!("{app_id}/{service}"))
+		.await
+		.map(RegistryTagsRes)
+		.map(Json)
+}
+
+async fn registry_gc(
+	_auth: Authenticated<ScopeRegistry>,
+	State(cfg): State<Arc<Config>>,
+	State(docker): State<Docker>,
+	Json(req): Json<RegistryGcReq>,
+) -> Result<Json<RegistryGcRes>, Error> {
+	gc::gc(&docker, &cfg.registry.domain, req.keep_last as usize)
+		.await
+		.map(Json)
+}
+
 pub fn routes() -> Router<AppState> {
 	Router::new()
 		.route("/webhook", post(webhook))
 		.route("/users", get(all_users).post(create_user))
 		.route("/users/{username}", delete(delete_user))
+		.route("/repositories", get(repositories))
+		.route(
+			"/repositories/{app_id}/{service}/tags",
+			get(repository_tags),
+		)
+		.route("/gc", post(registry_gc))
 }
@@ -1,11 +1,50 @@
-use std::path::{Path, PathBuf};
+/*!
+The internal api uses a self-signed certificate.
 
-use api::error::{Error, WithMessage as _};
-use rcgen::generate_simple_self_signed;
-use tokio::fs;
+The certificate is valid for a year and gets renewed a month before it
+expires. Since clients pin the public key (SPKI) of the server the key is
+kept by default. If `rotate-tls-key` is enabled a new key is used for every
+renewal, the key for the next renewal is created beforehand so its
+fingerprint can be published in `InfoRes` and pinned by the clients. A next
+key younger than a week is not used yet, the current key is kept for that
+renewal instead.
+*/
+
+use std::{
+	path::{Path, PathBuf},
+	sync::{Arc, RwLock},
+	time::Duration,
+};
+
+use api::{
+	error::{Error, WithMessage as _},
+	requests::TlsInfo,
+	tls::spki_fingerprint,
+};
+use chrono::{Datelike as _, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chuchi_postgres::time::DateTime;
+use rcgen::{CertificateParams, KeyPair, PublicKeyData as _, date_time_ymd};
+use tokio::{
+	fs::{self, File},
+	io::AsyncWriteExt as _,
+	time::sleep,
+};
+use tokio_rustls::rustls::{
+	crypto::aws_lc_rs,
+	pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _},
+	server::{ClientHello, ParsedCertificate, ResolvesServerCert},
+	sign::CertifiedKey,
+};
+use tracing::{error, info};
 
 use crate::config::Config;
 
+const VALIDITY: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(12 * 60 * 60);
+/// How long the next key is published before it gets used
+const NEXT_KEY_GRACE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
 pub fn key_path(hostdinghy_dir: impl AsRef<Path>) -> PathBuf {
 	hostdinghy_dir.as_ref().join("key.pem")
 }
@@ -14,6 +53,11 @@ pub fn cert_path(hostdinghy_dir: impl AsRef<Path>) -> PathBuf {
 	hostdinghy_dir.as_ref().join("cert.pem")
 }
 
+/// The key which will be used for the next renewal
+fn next_key_path(hostdinghy_dir: impl AsRef<Path>) -> PathBuf {
+	hostdinghy_dir.as_ref().join("next-key.pem")
+}
+
 pub async fn maybe_create_cert(
 	cfg: &Config,
 	hostdinghy_dir: impl AsRef<Path>,
@@ -23,29 +67,124 @@ pub async fn maybe_create_cert(
 		return Ok(());
 	}
 
-	create_cert(&cfg.domain, hostdinghy_dir).await?;
+	let key = KeyPair::generate().with_message("failed to generate key")?;
+	create_cert(&cfg.domain, &key, &hostdinghy_dir).await?;
+
+	if cfg.server.rotate_tls_key {
+		create_next_key(&hostdinghy_dir).await?;
+	}
 
 	Ok(())
 }
 
+/// Writes the file next to `path` and returns the temporary path, it then
+/// needs to be renamed
+async fn write_tmp(path: &Path, contents: &str) -> Result<PathBuf, Error> {
+	let tmp_path = path.with_extension("pem.tmp");
+	let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+	let res = async {
+		let mut file = File::create(&tmp_path).await?;
+		file.write_all(contents.as_bytes()).await?;
+		file.sync_all().await
+	}
+	.await;
+
+	if let Err(e) = res {
+		let _ = fs::remove_file(&tmp_path).await;
+		return Err(Error::any(
+			format!("failed to write $HOSTDINGHY_DIR/{name}"),
+			e,
+		));
+	}
+
+	Ok(tmp_path)
+}
+
+async fn rename(tmp_path: &Path, path: &Path) -> Result<(), Error> {
+	let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+	fs::rename(tmp_path, path)
+		.await
+		.with_message(format!("failed to write $HOSTDINGHY_DIR/{name}"))
+}
+
 async fn create_cert(
 	domain: &str,
+	key: &KeyPair,
 	hostdinghy_dir: impl AsRef<Path>,
 ) -> Result<(), Error> {
-	let cert = generate_simple_self_signed(vec![domain.to_string()])
+	let mut params = CertificateParams::new(vec![domain.to_string()])
+		.with_message("failed to create certificate params")?;
+
+	let now = Utc::now();
+	let not_after = now + VALIDITY;
+	params.not_before =
+		date_time_ymd(now.year(), now.month() as u8, now.day() as u8);
+	params.not_after = date_time_ymd(
+		not_after.year(),
+		not_after.month() as u8,
+		not_after.day() as u8,
+	);
+
+	let cert = params
+		.self_signed(key)
 		.with_message("failed to generate self signed cert")?;
 
 	let key_path = key_path(&hostdinghy_dir);
 	let cert_path = cert_path(&hostdinghy_dir);
 
-	fs::write(&key_path, cert.signing_key.serialize_pem())
-		.await
-		.with_message("failed to write $HOSTDINGHY_DIR/key.pem")?;
-	fs::write(&cert_path, cert.cert.pem())
+	// both files are written completely before one gets replaced, so a
+	// failed write keeps the old key and certificate
+	let tmp_key_path = write_tmp(&key_path, &key.serialize_pem()).await?;
+	let tmp_cert_path = match write_tmp(&cert_path, &cert.pem()).await {
+		Ok(p) => p,
+		Err(e) => {
+			let _ = fs::remove_file(&tmp_key_path).await;
+			return Err(e);
+		}
+	};
+
+	rename(&tmp_key_path, &key_path).await?;
+	rename(&tmp_cert_path, &cert_path).await
+}
+
+async fn create_next_key(
+	hostdinghy_dir: impl AsRef<Path>,
+) -> Result<(), Error> {
+	let key = KeyPair::generate().with_message("failed to generate key")?;
+
+	let path = next_key_path(&hostdinghy_dir);
+	let tmp_path = write_tmp(&path, &key.serialize_pem()).await?;
+	rename(&tmp_path, &path).await
+}
+
+/// Returns true if the next key was published long enough to be used
+///
+/// The rotation might have been enabled after the certificate was created,
+/// a missing next key gets created but cannot be used yet.
+async fn next_key_ready(
+	hostdinghy_dir: impl AsRef<Path>,
+) -> Result<bool, Error> {
+	match fs::metadata(next_key_path(&hostdinghy_dir)).await {
+		Ok(meta) => Ok(meta
+			.modified()
+			.ok()
+			.and_then(|m| m.elapsed().ok())
+			.is_some_and(|age| age >= NEXT_KEY_GRACE)),
+		Err(_) => {
+			create_next_key(&hostdinghy_dir).await?;
+			Ok(false)
+		}
+	}
+}
+
+async fn read_key(path: impl AsRef<Path>) -> Result<KeyPair, Error> {
+	let key = fs::read_to_string(path)
 		.await
-		.with_message("failed to write $HOSTDINGHY_DIR/cert.pem")?;
+		.with_message("failed to read key")?;
 
-	Ok(())
+	KeyPair::from_pem(&key).with_message("failed to parse key")
 }
 
 pub async fn read_cert(
@@ -57,3 +196,397 @@ pub async fn read_cert(
 		.with_message("failed to read $HOSTDINGHY_DIR/cert.pem")?;
 	Ok(cert)
 }
+
+/// Renews the certificate if it expires soon
+///
+/// Returns true if the certificate was renewed.
+pub async fn maybe_renew_cert(
+	cfg: &Config,
+	hostdinghy_dir: impl AsRef<Path>,
+) -> Result<bool, Error> {
+	let next_key_path = next_key_path(&hostdinghy_dir);
+	let use_next_key =
+		cfg.server.rotate_tls_key && next_key_ready(&hostdinghy_dir).await?;
+
+	let cert = read_cert(&hostdinghy_dir).await?;
+	let cert = CertificateDer::from_pem_slice(cert.as_bytes())
+		.with_message("failed to parse certificate")?;
+	let expires_on = not_after(&cert)?;
+
+	if DateTime::now() + RENEW_BEFORE < expires_on {
+		return Ok(false);
+	}
+
+	let key = if use_next_key {
+		read_key(&next_key_path).await?
+	} else {
+		read_key(key_path(&hostdinghy_dir)).await?
+	};
+
+	create_cert(&cfg.domain, &key, &hostdinghy_dir).await?;
+
+	if use_next_key {
+		create_next_key(&hostdinghy_dir).await?;
+	}
+
+	Ok(true)
+}
+
+/// Reads the length of a DER value and returns the header length and the
+/// length of the content
+fn der_len(der: &[u8]) -> Option<(usize, usize)> {
+	let first = *der.get(1)? as usize;
+	if first < 0x80 {
+		return Some((2, first));
+	}
+
+	let n = first & 0x7f;
+	if n == 0 || n > 4 {
+		return None;
+	}
+
+	let len = der
+		.get(2..2 + n)?
+		.iter()
+		.fold(0, |len, b| (len << 8) | *b as usize);
+
+	Some((2 + n, len))
+}
+
+/// Splits the next DER value into its tag, its content and the rest
+fn der_next(der: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+	let tag = *der.first()?;
+	let (header, len) = der_len(der)?;
+	let end = header.checked_add(len)?;
+
+	Some((tag, der.get(header..end)?, der.get(end..)?))
+}
+
+/// Parses an UTCTime or a GeneralizedTime
+fn der_time(tag: u8, time: &[u8]) -> Option<DateTime> {
+	let time = std::str::from_utf8(time).ok()?.strip_suffix('Z')?;
+
+	let (year, rest) = match tag {
+		// UTCTime
+		0x17 => {
+			let year = time.get(..2)?.parse::<i32>().ok()?;
+			let year = if year < 50 { 2000 + year } else { 1900 + year };
+			(year, time.get(2..)?)
+		}
+		// GeneralizedTime
+		0x18 => (time.get(..4)?.parse().ok()?, time.get(4..)?),
+		_ => return None,
+	};
+
+	let num = |i: usize| rest.get(i..i + 2)?.parse::<u32>().ok();
+
+	let date = NaiveDate::from_ymd_opt(year, num(0)?, num(2)?)?;
+	let time = NaiveTime::from_hms_opt(num(4)?, num(6)?, num(8)?)?;
+
+	Some(NaiveDateTime::new(date, time).and_utc().into())
+}
+
+/// Returns when the certificate expires
+///
+/// ```text
+/// Certificate ::= SEQUENCE {
+///     tbsCertificate ::= SEQUENCE {
+///         version [0] EXPLICIT Version DEFAULT v1,
+///         serialNumber, signature, issuer,
+///         validity ::= SEQUENCE { notBefore, notAfter },
+///         ...
+/// ```
+fn not_after(cert: &CertificateDer) -> Result<DateTime, Error> {
+	let parse = || {
+		let (_, cert, _) = der_next(cert)?;
+		let (_, tbs, _) = der_next(cert)?;
+
+		let (tag, _, mut rest) = der_next(tbs)?;
+		// the version is optional
+		if tag != 0xa0 {
+			rest = tbs;
+		}
+
+		// skip the serial number, the signature algorithm and the issuer
+		for _ in 0..3 {
+			rest = der_next(rest)?.2;
+		}
+
+		let (_, validity, _) = der_next(rest)?;
+		let (_, _, validity) = der_next(validity)?;
+		let (tag, not_after, _) = der_next(validity)?;
+
+		der_time(tag, not_after)
+	};
+
+	parse().ok_or_else(|| {
+		Error::any("failed to parse certificate", "invalid validity")
+	})
+}
+
+/// The certificate which is currently used by the server
+#[derive(Debug, Clone)]
+pub struct Certs {
+	hostdinghy_dir: PathBuf,
+	inner: Arc<RwLock<Current>>,
+}
+
+#[derive(Debug)]
+struct Current {
+	key: Arc<CertifiedKey>,
+	info: TlsInfo,
+}
+
+impl Certs {
+	pub async fn load(hostdinghy_dir: PathBuf) -> Result<Self, Error> {
+		let current = Self::read(&hostdinghy_dir).await?;
+
+		Ok(Self {
+			hostdinghy_dir,
+			inner: Arc::new(RwLock::new(current)),
+		})
+	}
+
+	async fn read(hostdinghy_dir: &Path) -> Result<Current, Error> {
+		let key = fs::read(key_path(hostdinghy_dir))
+			.await
+			.with_message("failed to read $HOSTDINGHY_DIR/key.pem")?;
+		let key = PrivateKeyDer::from_pem_slice(&key)
+			.with_message("failed to read private key")?;
+
+		let cert = read_cert(hostdinghy_dir).await?;
+		let certs = CertificateDer::pem_slice_iter(cert.as_bytes())
+			.collect::<Result<Vec<_>, _>>()
+			.with_message("failed to read certificate")?;
+		let cert = certs.first().ok_or_else(|| {
+			Error::any("failed to read certificate", "no certificate found")
+		})?;
+
+		let spki = ParsedCertificate::try_from(cert)
+			.with_message("failed to parse certificate")?
+			.subject_public_key_info();
+
+		let next_key_path = next_key_path(hostdinghy_dir);
+		let next_spki_fingerprint =
+			if fs::metadata(&next_key_path).await.is_ok() {
+				let key = read_key(&next_key_path).await?;
+				Some(spki_fingerprint(&key.subject_public_key_info()))
+			} else {
+				None
+			};
+
+		let info = TlsInfo {
+			spki_fingerprint: spki_fingerprint(&spki),
+			next_spki_fingerprint,
+			expires_on: not_after(cert)?,
+		};
+
+		let key =
+			CertifiedKey::from_der(certs, key, &aws_lc_rs::default_provider())
+				.with_message("failed to create certified key")?;
+
+		Ok(Current {
+			key: Arc::new(key),
+			info,
+		})
+	}
+
+	/// Reads the certificate again, new connections will use it
+	pub async fn reload(&self) -> Result<(), Error> {
+		let current = Self::read(&self.hostdinghy_dir).await?;
+		*self.inner.write().unwrap() = current;
+
+		Ok(())
+	}
+
+	pub fn info(&self) -> TlsInfo {
+		self.inner.read().unwrap().info.clone()
+	}
+}
+
+impl ResolvesServerCert for Certs {
+	fn resolve(
+		&self,
+		_client_hello: ClientHello<'_>,
+	) -> Option<Arc<CertifiedKey>> {
+		Some(self.inner.read().unwrap().key.clone())
+	}
+}
+
+/// Checks regularly if the certificate needs to be renewed
+pub async fn run_renewal(cfg: Arc<Config>, certs: Certs) {
+	loop {
+		match maybe_renew_cert(&cfg, &certs.hostdinghy_dir).await {
+			Ok(true) => {
+				info!("TLS certificate renewed");
+
+				if let Err(e) = certs.reload().await {
+					error!("Failed to load the renewed certificate: {e}");
+				}
+			}
+			Ok(false) => {}
+			Err(e) => error!("TLS certificate renewal failed: {e}"),
+		}
+
+		sleep(CHECK_INTERVAL).await;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::TimeZone as _;
+
+	use super::*;
+
+	fn cert(not_after: (i32, u8, u8)) -> CertificateDer<'static> {
+		let (y, m, d) = not_after;
+		let mut params =
+			CertificateParams::new(vec!["hostdinghy.local".into()]).unwrap();
+		params.not_before = date_time_ymd(2025, 1, 1);
+		params.not_after = date_time_ymd(y, m, d);
+
+		let key = KeyPair::generate().unwrap();
+		params.self_signed(&key).unwrap().der().clone()
+	}
+
+	fn date(y: i32, m: u32, d: u32) -> DateTime {
+		Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap().into()
+	}
+
+	/// Encodes a DER value with the length in the short or the long form
+	fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+		let mut out = vec![tag];
+		match content.len() {
+			len @ 0..0x80 => out.push(len as u8),
+			len @ 0x80..0x100 => out.extend([0x81, len as u8]),
+			len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+		}
+		out.extend(content);
+
+		out
+	}
+
+	/// A certificate without the optional version, like v1 certificates
+	fn cert_v1(not_after: &[u8]) -> CertificateDer<'static> {
+		let validity = [der(0x17, b"250101000000Z"), not_after.to_vec()];
+		let tbs = [
+			der(0x02, &[1]),
+			der(0x30, &der(0x06, &[0x2a, 0x86, 0x48])),
+			der(0x30, &[]),
+			der(0x30, &validity.concat()),
+			der(0x30, &[]),
+			der(0x30, &[0; 200]),
+		];
+		let cert = [der(0x30, &tbs.concat()), der(0x30, &[]), der(0x03, &[0])];
+
+		CertificateDer::from(der(0x30, &cert.concat()))
+	}
+
+	#[test]
+	fn der_lengths() {
+		assert_eq!(der_len(&[0x30, 0x05]), Some((2, 5)));
+		assert_eq!(der_len(&[0x30, 0x81, 0x80]), Some((3, 0x80)));
+		assert_eq!(der_len(&[0x30, 0x82, 0x01, 0x00]), Some((4, 0x100)));
+		// indefinite length and lengths which don't fit are rejected
+		assert_eq!(der_len(&[0x30, 0x80]), None);
+		assert_eq!(der_len(&[0x30, 0x85, 1, 1, 1, 1, 1]), None);
+		assert_eq!(der_len(&[0x30, 0x82, 0x01]), None);
+		assert_eq!(der_len(&[0x30]), None);
+
+		let value = der(0x04, &[7; 300]);
+		let (tag, content, rest) = der_next(&value).unwrap();
+		assert_eq!((tag, content.len(), rest.len()), (0x04, 300, 0));
+
+		// the content is longer than the input
+		assert_eq!(der_next(&[0x04, 0x05, 1, 2]), None);
+	}
+
+	#[test]
+	fn der_times() {
+		assert_eq!(
+			der_time(0x17, b"491231235959Z"),
+			Some({
+				Utc.with_ymd_and_hms(2049, 12, 31, 23, 59, 59)
+					.unwrap()
+					.into()
+			})
+		);
+		assert_eq!(der_time(0x17, b"500101000000Z"), Some(date(1950, 1, 1)));
+		assert_eq!(der_time(0x18, b"20600101000000Z"), Some(date(2060, 1, 1)));
+
+		assert_eq!(der_time(0x17, b"500101000000"), None);
+		assert_eq!(der_time(0x17, b"501301000000Z"), None);
+		assert_eq!(der_time(0x18, b"2060010100Z"), None);
+		assert_eq!(der_time(0x04, b"500101000000Z"), None);
+	}
+
+	#[test]
+	fn not_after_of_generated_certs() {
+		// until 2049 the time is encoded as UTCTime
+		assert_eq!(not_after(&cert((2026, 3, 1))).unwrap(), date(2026, 3, 1));
+		assert_eq!(
+			not_after(&cert((2049, 12, 31))).unwrap(),
+			date(2049, 12, 31)
+		);
+		// afterwards as GeneralizedTime
+		assert_eq!(not_after(&cert((2050, 1, 1))).unwrap(), date(2050, 1, 1));
+		assert_eq!(not_after(&cert((2100, 6, 15))).unwrap(), date(2100, 6, 15));
+	}
+
+	#[test]
+	fn not_after_without_version() {
+		let cert = cert_v1(&der(0x17, b"300101000000Z"));
+		assert_eq!(not_after(&cert).unwrap(), date(2030, 1, 1));
+
+		let cert = cert_v1(&der(0x18, b"20600101000000Z"));
+		assert_eq!(not_after(&cert).unwrap(), date(2060, 1, 1));
+
+		assert!(not_after(&CertificateDer::from(vec![0x30, 0x00])).is_err());
+	}
+
+	#[tokio::test]
+	async fn create_cert_replaces_files() {
+		let dir = tempfile::tempdir().unwrap();
+		let key = KeyPair::generate().unwrap();
+
+		create_cert("hostdinghy.local", &key, dir.path())
+			.await
+			.unwrap();
+		create_cert("hostdinghy.local", &key, dir.path())
+			.await
+			.unwrap();
+
+		let mut files = std::fs::read_dir(dir.path())
+			.unwrap()
+			.map(|e| e.unwrap().file_name().into_string().unwrap())
+			.collect::<Vec<_>>();
+		files.sort();
+		assert_eq!(files, ["cert.pem", "key.pem"]);
+
+		let cert = read_cert(dir.path()).await.unwrap();
+		let cert = CertificateDer::from_pem_slice(cert.as_bytes()).unwrap();
+		let expires_on = not_after(&cert).unwrap();
+		assert!(expires_on > DateTime::now() + RENEW_BEFORE);
+	}
+
+	#[tokio::test]
+	async fn next_key_needs_to_be_published() {
+		let dir = tempfile::tempdir().unwrap();
+
+		// a missing key gets created but is not ready yet
+		assert!(!next_key_ready(dir.path()).await.unwrap());
+		let path = next_key_path(dir.path());
+		let key = std::fs::read_to_string(&path).unwrap();
+		assert!(!next_key_ready(dir.path()).await.unwrap());
+
+		let published = std::time::SystemTime::now() - NEXT_KEY_GRACE;
+		std::fs::File::options()
+			.write(true)
+			.open(&path)
+			.unwrap()
+			.set_modified(published)
+			.unwrap();
+		assert!(next_key_ready(dir.path()).await.unwrap());
+		assert_eq!(std::fs::read_to_string(&path).unwrap(), key);
+	}
+}
@@ -1,16 +1,127 @@
-use api::requests::ApiToken;
+use std::time::Duration;
+
+use api::requests::{ApiToken, ApiTokenInfo, ApiTokenScope};
+use chuchi_postgres::time::DateTime;
 use serde::{Deserialize, Serialize};
 
+/// The token created by the setup
+pub const DEFAULT_TOKEN: &str = "default";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case", from = "RawServerConfig")]
 pub struct ServerConfig {
-	pub api_token: ApiToken,
+	pub tokens: Vec<ApiTokenConfig>,
+	/// If true a new tls key is used every time the certificate is renewed
+	pub rotate_tls_key: bool,
+}
+
+/// Before multiple tokens were supported the config contained a single
+/// `api-token`, it gets migrated to the default token with all rights
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawServerConfig {
+	api_token: Option<ApiToken>,
+	#[serde(default)]
+	tokens: Vec<ApiTokenConfig>,
+	#[serde(default)]
+	rotate_tls_key: bool,
+}
+
+impl From<RawServerConfig> for ServerConfig {
+	fn from(raw: RawServerConfig) -> Self {
+		let mut tokens = raw.tokens;
+
+		if let Some(token) = raw.api_token
+			&& !tokens.iter().any(|t| t.name == DEFAULT_TOKEN)
+		{
+			tokens.push(ApiTokenConfig {
+				token,
+				..ApiTokenConfig::new(
+					DEFAULT_TOKEN.into(),
+					vec![ApiTokenScope::Admin],
+					None,
+				)
+			});
+		}
+
+		Self {
+			tokens,
+			rotate_tls_key: raw.rotate_tls_key,
+		}
+	}
 }
 
 impl ServerConfig {
 	pub fn new_from_user() -> Self {
 		Self {
-			api_token: ApiToken::new(),
+			tokens: vec![ApiTokenConfig::new(
+				DEFAULT_TOKEN.into(),
+				vec![ApiTokenScope::Admin],
+				None,
+			)],
+			rotate_tls_key: false,
+		}
+	}
+
+	pub fn token(&self, name: &str) -> Option<&ApiTokenConfig> {
+		self.tokens.iter().find(|t| t.name == name)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ApiTokenConfig {
+	pub name: String,
+	pub token: ApiToken,
+	pub scopes: Vec<ApiTokenScope>,
+	pub created_on: DateTime,
+	/// If None the token never expires
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub expires_on: Option<DateTime>,
+	/// The token which got replaced by the last rotation
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub previous: Option<PreviousApiToken>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PreviousApiToken {
+	pub token: ApiToken,
+	pub valid_until: DateTime,
+}
+
+impl ApiTokenConfig {
+	pub fn new(
+		name: String,
+		scopes: Vec<ApiTokenScope>,
+		valid_for: Option<Duration>,
+	) -> Self {
+		let now = DateTime::now();
+
+		Self {
+			name,
+			token: ApiToken::new(),
+			scopes,
+			created_on: now,
+			expires_on: valid_for.map(|d| now + d),
+			previous: None,
+		}
+	}
+
+	pub fn is_expired(&self, now: &DateTime) -> bool {
+		self.expires_on.is_some_and(|e| e <= *now)
+	}
+
+	pub fn allows(&self, scope: ApiTokenScope) -> bool {
+		self.scopes.iter().any(|s| s.allows(scope))
+	}
+
+	pub fn info(&self) -> ApiTokenInfo {
+		ApiTokenInfo {
+			name: self.name.clone(),
+			scopes: self.scopes.clone(),
+			created_on: self.created_on,
+			expires_on: self.expires_on,
 		}
 	}
 }
@@ -1,34 +1,66 @@
-use std::{path::Path, sync::Arc};
+use std::sync::Arc;
 
 use api::error::{Error, WithMessage as _};
 use axum::extract::Request;
+use clap::Parser;
 use hyper::body::Incoming;
 use hyper_util::rt::{TokioExecutor, TokioIo};
-use tokio::{net::TcpListener, task};
-use tokio_rustls::{
-	TlsAcceptor,
-	rustls::{
-		ServerConfig,
-		pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _},
-	},
-};
+use tokio::net::TcpListener;
+use tokio_rustls::{TlsAcceptor, rustls::ServerConfig};
 
 use tower::Service as _;
 use tracing::{error, info, warn};
 
 use crate::{
 	config::Config,
-	utils::{hostdinghy_dir, is_file, verify_root},
+	metrics,
+	postgres::backups,
+	utils::{cli::CliError, hostdinghy_dir, is_file, verify_root},
 };
 
 mod cert;
 pub mod config;
 pub mod error;
 pub mod router;
+mod system;
+pub mod tokens;
 mod utils;
 
-pub use cert::{maybe_create_cert, read_cert};
-pub use utils::Authenticated;
+pub use cert::{Certs, maybe_create_cert, read_cert};
+pub use utils::{
+	Authenticated, ScopeApps, ScopePostgres, ScopeRead, ScopeRegistry,
+};
+
+#[derive(Debug, Parser)]
+pub struct Server {
+	#[clap(subcommand)]
+	cmd: SubCommand,
+}
+
+#[derive(Debug, Parser)]
+enum SubCommand {
+	/// Manage the tokens for the internal api
+	Token(tokens::Token),
+	/// Create a new token for `/metrics`, replacing the current one
+	MetricsToken,
+}
+
+pub async fn server(server: Server) {
+	let res = inner_server(server).await;
+
+	if let Err(e) = res {
+		error!("Server command failed: {e}");
+	}
+}
+
+async fn inner_server(server: Server) -> Result<(), CliError> {
+	verify_root().await?;
+
+	match server.cmd {
+		SubCommand::Token(t) => tokens::token(t).await,
+		SubCommand::MetricsToken => metrics::new_token().await,
+	}
+}
 
 pub async fn serve() {
 	let res = inner_serve().await;
@@ -51,20 +83,25 @@ async fn inner_serve() -> Result<(), Error> {
 		));
 	}
 
-	// this is not really necessary since we don't do anything async but
-	// since everything else is async, might be good practice
-	let n_hostdinghy_dir = hostdinghy_dir.clone();
-	let rustls_config =
-		task::spawn_blocking(move || rustls_server_config(n_hostdinghy_dir))
-			.await
-			.unwrap()?;
+	if cert::maybe_renew_cert(&cfg, &hostdinghy_dir).await? {
+		info!("TLS certificate renewed");
+	}
+
+	let certs = Certs::load(hostdinghy_dir.clone()).await?;
+	tokio::spawn(cert::run_renewal(Arc::new(cfg.clone()), certs.clone()));
+
+	let rustls_config = rustls_server_config(certs.clone());
 
 	let tls_acceptor = TlsAcceptor::from(Arc::new(rustls_config));
 	let tcp_listener = TcpListener::bind("[::]:4242")
 		.await
 		.with_message("failed to bind to [::]:4242")?;
 
-	let app = router::app(cfg).await?;
+	if let Some(backups_cfg) = cfg.postgres_backups.clone() {
+		tokio::spawn(backups::run_scheduler(backups_cfg));
+	}
+
+	let app = router::app(cfg, hostdinghy_dir, certs).await?;
 
 	info!("Server is running on [::]:4242");
 
@@ -106,32 +143,12 @@ async fn inner_serve() -> Result<(), Error> {
 	}
 }
 
-fn rustls_server_config(
-	hostdinghy_dir: impl AsRef<Path>,
-) -> Result<ServerConfig, Error> {
-	let key = cert::key_path(&hostdinghy_dir);
-	let cert = cert::cert_path(&hostdinghy_dir);
-
-	let key = PrivateKeyDer::from_pem_file(key)
-		.with_message("failed to read private key")?;
-
-	let certs = CertificateDer::pem_file_iter(cert)
-		.with_message("failed to read certificate")?
-		.filter_map(|cert| match cert {
-			Ok(c) => Some(c),
-			Err(e) => {
-				error!("failed to parse certificate: {e}");
-				None
-			}
-		})
-		.collect();
-
+fn rustls_server_config(certs: Certs) -> ServerConfig {
 	let mut config = ServerConfig::builder()
 		.with_no_client_auth()
-		.with_single_cert(certs, key)
-		.with_message("failed to create server config")?;
+		.with_cert_resolver(Arc::new(certs));
 
 	config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
 
-	Ok(config)
+	config
 }
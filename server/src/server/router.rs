@@ -1,22 +1,29 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use api::{
 	error::Error,
-	requests::{InfoRes, PingRes},
+	requests::{InfoRes, PingRes, RotateApiTokenRes, SystemInfoRes},
 };
 use axum::{
 	Json, Router,
 	extract::{FromRef, State},
-	routing::get,
+	middleware,
+	routing::{get, post},
 };
 use chuchi_postgres::time::DateTime;
 use tower_http::trace::TraceLayer;
 
 use crate::{
-	apps,
+	apps::{self, deployments::DeployLocks},
 	docker::Docker,
+	metrics::{self, Metrics},
 	postgres, registry,
-	server::{Config, utils::Authenticated},
+	server::{
+		Certs, Config,
+		system::system_info,
+		tokens::ApiTokens,
+		utils::{Authenticated, ScopeRead},
+	},
 	traefik::client::Traefik,
 };
 
@@ -25,6 +32,10 @@ pub struct AppState {
 	pub docker: Docker,
 	pub traefik: Traefik,
 	pub cfg: Arc<Config>,
+	pub tokens: ApiTokens,
+	pub certs: Certs,
+	pub metrics: Metrics,
+	pub deploy_locks: DeployLocks,
 }
 
 impl FromRef<AppState> for Docker {
@@ -39,25 +50,64 @@ impl FromRef<AppState> for Traefik {
 	}
 }
 
+impl FromRef<AppState> for ApiTokens {
+	fn from_ref(state: &AppState) -> Self {
+		state.tokens.clone()
+	}
+}
+
+impl FromRef<AppState> for Certs {
+	fn from_ref(state: &AppState) -> Self {
+		state.certs.clone()
+	}
+}
+
+impl FromRef<AppState> for Metrics {
+	fn from_ref(state: &AppState) -> Self {
+		state.metrics.clone()
+	}
+}
+
+impl FromRef<AppState> for DeployLocks {
+	fn from_ref(state: &AppState) -> Self {
+		state.deploy_locks.clone()
+	}
+}
+
 impl FromRef<AppState> for Arc<Config> {
 	fn from_ref(state: &AppState) -> Self {
 		state.cfg.clone()
 	}
 }
 
-pub async fn app(cfg: Config) -> Result<Router<()>, Error> {
+pub async fn app(
+	cfg: Config,
+	hostdinghy_dir: PathBuf,
+	certs: Certs,
+) -> Result<Router<()>, Error> {
 	let state = AppState {
 		docker: Docker::new()?,
 		traefik: Traefik::new(cfg.traefik.clone()),
+		tokens: ApiTokens::new(hostdinghy_dir, &cfg),
 		cfg: Arc::new(cfg),
+		certs,
+		metrics: Metrics::new(),
+		deploy_locks: DeployLocks::new(),
 	};
 
 	let router = Router::new()
 		.route("/ping", get(ping_req))
 		.route("/info", get(info_req))
+		.route("/system", get(system_req))
+		.route("/tokens/rotate", post(rotate_token))
 		.nest("/apps", apps::routes::routes())
 		.nest("/registry", registry::routes::routes())
 		.nest("/postgres", postgres::routes::routes())
+		.merge(metrics::routes::routes())
+		.route_layer(middleware::from_fn_with_state(
+			state.clone(),
+			metrics::track_requests,
+		))
 		.layer(TraceLayer::new_for_http())
 		.with_state(state);
 
@@ -71,8 +121,9 @@ async fn ping_req() -> Json<PingRes> {
 }
 
 async fn info_req(
-	_auth: Authenticated,
+	auth: Authenticated<ScopeRead>,
 	State(cfg): State<Arc<Config>>,
+	State(certs): State<Certs>,
 ) -> Json<InfoRes> {
 	Json(InfoRes {
 		registry_domain: cfg.registry.domain.clone(),
@@ -80,5 +131,28 @@ async fn info_req(
 		commit: option_env!("GIT_COMMIT_HASH").map(|s| s.to_string()),
 		build_date: option_env!("BUILD_DATE")
 			.and_then(|s| DateTime::parse_from_iso8601(s).ok()),
+		token: Some(auth.token.info()),
+		tls: Some(certs.info()),
 	})
 }
+
+async fn system_req(
+	_auth: Authenticated<ScopeRead>,
+	State(docker): State<Docker>,
+) -> Result<Json<SystemInfoRes>, Error> {
+	system_info(&docker).await.map(Json)
+}
+
+/// Every token can rotate itself
+async fn rotate_token(
+	auth: Authenticated<ScopeRead>,
+	State(tokens): State<ApiTokens>,
+) -> Result<Json<RotateApiTokenRes>, Error> {
+	let (token, previous_valid_until) = tokens.rotate(&auth.token.name).await?;
+
+	Ok(Json(RotateApiTokenRes {
+		info: token.info(),
+		token: token.token,
+		previous_valid_until,
+	}))
+}
@@ -0,0 +1,159 @@
+/*!
+Information about the host, most of it is read from `/proc`.
+*/
+
+use std::{
+	ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt as _, path::Path,
+	thread::available_parallelism,
+};
+
+use api::{
+	error::{Error, WithMessage as _},
+	requests::{DiskUsage, MemoryUsage, SystemInfoRes},
+};
+use tokio::fs;
+use tracing::warn;
+
+use crate::{
+	docker::Docker,
+	postgres::Client,
+	utils::{compose, hostdinghy_dir},
+};
+
+async fn os_name() -> Option<String> {
+	let os_release = fs::read_to_string("/etc/os-release").await.ok()?;
+
+	os_release.lines().find_map(|l| {
+		let name = l.strip_prefix("PRETTY_NAME=")?;
+		Some(name.trim_matches('"').to_string())
+	})
+}
+
+async fn kernel() -> Result<String, Error> {
+	fs::read_to_string("/proc/sys/kernel/osrelease")
+		.await
+		.map(|s| s.trim().to_string())
+		.with_message("Failed to read the kernel version")
+}
+
+async fn uptime() -> Result<u64, Error> {
+	let uptime = fs::read_to_string("/proc/uptime")
+		.await
+		.with_message("Failed to read /proc/uptime")?;
+
+	uptime
+		.split_whitespace()
+		.next()
+		.and_then(|s| s.parse::<f64>().ok())
+		.map(|s| s as u64)
+		.ok_or_else(|| Error::any("Failed to parse /proc/uptime", uptime))
+}
+
+async fn load_average() -> Result<[f64; 3], Error> {
+	let loadavg = fs::read_to_string("/proc/loadavg")
+		.await
+		.with_message("Failed to read /proc/loadavg")?;
+
+	let mut values = loadavg.split_whitespace().map(|s| s.parse().ok());
+	let mut next = || values.next().flatten();
+
+	match (next(), next(), next()) {
+		(Some(a), Some(b), Some(c)) => Ok([a, b, c]),
+		_ => Err(Error::any("Failed to parse /proc/loadavg", loadavg)),
+	}
+}
+
+async fn memory() -> Result<MemoryUsage, Error> {
+	let meminfo = fs::read_to_string("/proc/meminfo")
+		.await
+		.with_message("Failed to read /proc/meminfo")?;
+
+	// the values are in kB
+	let value = |key: &str| {
+		meminfo.lines().find_map(|l| {
+			let value = l.strip_prefix(key)?.strip_prefix(':')?;
+			let kb = value.trim().strip_suffix("kB")?.trim();
+			kb.parse::<u64>().ok().map(|kb| kb * 1024)
+		})
+	};
+
+	match (value("MemTotal"), value("MemAvailable")) {
+		(Some(total), Some(available)) => Ok(MemoryUsage { total, available }),
+		_ => Err(Error::any("Failed to parse /proc/meminfo", "")),
+	}
+}
+
+fn disk_usage(path: &Path) -> Result<DiskUsage, Error> {
+	let c_path = CString::new(path.as_os_str().as_bytes())
+		.with_message("Invalid path")?;
+
+	let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+	let res = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+	if res != 0 {
+		return Err(Error::any(
+			format!("Failed to get the disk usage of {}", path.display()),
+			std::io::Error::last_os_error(),
+		));
+	}
+	let stat = unsafe { stat.assume_init() };
+
+	let block_size = stat.f_frsize;
+
+	Ok(DiskUsage {
+		path: path.to_string_lossy().into_owned(),
+		total: stat.f_blocks * block_size,
+		available: stat.f_bavail * block_size,
+	})
+}
+
+/// Returns the disk usage of the docker data root
+async fn docker_disk(docker: &Docker) -> Result<DiskUsage, Error> {
+	let info = docker.info().await?;
+	let root = info.docker_root_dir.ok_or_else(|| {
+		Error::any("Failed to get the docker data root", "not set")
+	})?;
+
+	disk_usage(Path::new(&root))
+}
+
+/// Versions of the other software are optional, the server should still
+/// respond if for example postgres is down
+pub async fn system_info(docker: &Docker) -> Result<SystemInfoRes, Error> {
+	let docker_disk = docker_disk(docker)
+		.await
+		.inspect_err(|e| warn!("failed to get the docker disk usage {e}"))
+		.ok();
+
+	let docker_version = docker
+		.version()
+		.await
+		.inspect_err(|e| warn!("failed to get the docker version {e}"))
+		.ok()
+		.and_then(|v| v.version);
+
+	let compose_version = compose::version()
+		.await
+		.inspect_err(|e| warn!("failed to get the compose version {e}"))
+		.ok();
+
+	let postgres_version = match Client::new().await {
+		Ok(client) => client.version().await,
+		Err(e) => Err(e),
+	}
+	.inspect_err(|e| warn!("failed to get the postgres version {e}"))
+	.ok();
+
+	Ok(SystemInfoRes {
+		os: os_name().await,
+		kernel: kernel().await?,
+		uptime: uptime().await?,
+		cpus: available_parallelism().map_or(1, |n| n.get() as u32),
+		load_average: load_average().await?,
+		memory: memory().await?,
+		hostdinghy_disk: disk_usage(&hostdinghy_dir()?)?,
+		docker_disk,
+		docker_version,
+		compose_version,
+		postgres_version,
+	})
+}
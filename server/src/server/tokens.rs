@@ -0,0 +1,277 @@
+/*!
+The api tokens and the metrics token are stored in
+`$HOSTDINGHY_DIR/config.toml`. Since they can be created and revoked with the
+cli while the server is running, they get reloaded whenever the config file
+changes.
+*/
+
+use std::{
+	path::PathBuf,
+	sync::Arc,
+	time::{Duration, SystemTime},
+};
+
+use api::{
+	error::Error,
+	requests::{ApiToken, ApiTokenScope},
+};
+use chuchi_postgres::time::DateTime;
+use clap::Parser;
+use subtle::ConstantTimeEq as _;
+use tokio::{fs, sync::Mutex};
+use tracing::{info, warn};
+
+use crate::{
+	config::Config,
+	metrics::MetricsToken,
+	server::config::{ApiTokenConfig, PreviousApiToken},
+	utils::{
+		cli::{CliError, WithMessage as _},
+		hostdinghy_dir,
+	},
+};
+
+/// How long the previous token keeps working after a rotation
+const ROTATION_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone)]
+pub struct ApiTokens {
+	hostdinghy_dir: PathBuf,
+	inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+	modified: Option<SystemTime>,
+	tokens: Vec<ApiTokenConfig>,
+	metrics: Option<MetricsToken>,
+}
+
+/// Compares in constant time so the response time does not reveal how many
+/// bytes of a guessed token are correct
+fn eq(a: &ApiToken, b: &ApiToken) -> bool {
+	a.as_ref().ct_eq(b.as_ref()).into()
+}
+
+impl ApiTokens {
+	pub fn new(hostdinghy_dir: PathBuf, cfg: &Config) -> Self {
+		Self {
+			hostdinghy_dir,
+			inner: Arc::new(Mutex::new(Inner {
+				modified: None,
+				tokens: cfg.server.tokens.clone(),
+				metrics: cfg.metrics.as_ref().map(|m| m.token.clone()),
+			})),
+		}
+	}
+
+	async fn modified(&self) -> Option<SystemTime> {
+		fs::metadata(self.hostdinghy_dir.join("config.toml"))
+			.await
+			.and_then(|m| m.modified())
+			.ok()
+	}
+
+	/// Reloads the tokens if the config file changed, if the file cannot be
+	/// read the current tokens are kept
+	async fn reload(&self, inner: &mut Inner) {
+		let modified = self.modified().await;
+		if modified.is_none() || modified == inner.modified {
+			return;
+		}
+
+		match Config::read(&self.hostdinghy_dir).await {
+			Ok(cfg) => {
+				inner.tokens = cfg.server.tokens;
+				inner.metrics = cfg.metrics.map(|m| m.token);
+				inner.modified = modified;
+			}
+			Err(e) => warn!("failed to reload the api tokens {e}"),
+		}
+	}
+
+	/// Returns the config of the token
+	pub async fn authenticate(
+		&self,
+		token: &ApiToken,
+	) -> Result<ApiTokenConfig, Error> {
+		let mut inner = self.inner.lock().await;
+		self.reload(&mut inner).await;
+
+		let now = DateTime::now();
+
+		for cfg in &inner.tokens {
+			if eq(&cfg.token, token) {
+				if cfg.is_expired(&now) {
+					return Err(Error::ExpiredApiToken);
+				}
+
+				return Ok(cfg.clone());
+			}
+
+			if let Some(prev) = &cfg.previous
+				&& eq(&prev.token, token)
+			{
+				if prev.valid_until <= now || cfg.is_expired(&now) {
+					return Err(Error::ExpiredApiToken);
+				}
+
+				return Ok(cfg.clone());
+			}
+		}
+
+		Err(Error::InvalidApiToken)
+	}
+
+	/// Returns the token for `/metrics`, if one was created
+	pub async fn metrics_token(&self) -> Option<MetricsToken> {
+		let mut inner = self.inner.lock().await;
+		self.reload(&mut inner).await;
+
+		inner.metrics.clone()
+	}
+
+	/// Replaces the token with a new one, the old one stays valid for
+	/// [`ROTATION_GRACE_PERIOD`]
+	///
+	/// The new token expires after the same duration the old one had.
+	/// Returns the new config and until when the old token stays valid.
+	pub async fn rotate(
+		&self,
+		name: &str,
+	) -> Result<(ApiTokenConfig, DateTime), Error> {
+		let mut inner = self.inner.lock().await;
+
+		let mut cfg = Config::read(&self.hostdinghy_dir).await?;
+		let token = cfg
+			.server
+			.tokens
+			.iter_mut()
+			.find(|t| t.name == name)
+			// the token was revoked in the meantime
+			.ok_or(Error::InvalidApiToken)?;
+
+		let now = DateTime::now();
+		let mut valid_until = now + ROTATION_GRACE_PERIOD;
+		if let Some(expires_on) = token.expires_on {
+			valid_until = valid_until.min(expires_on);
+		}
+
+		let expires_on = token
+			.expires_on
+			.map(|e| now + e.abs_diff(&token.created_on).unwrap_or_default());
+
+		let previous = std::mem::replace(&mut token.token, ApiToken::new());
+		token.previous = Some(PreviousApiToken {
+			token: previous,
+			valid_until,
+		});
+		token.created_on = now;
+		token.expires_on = expires_on;
+		let token = token.clone();
+
+		cfg.write(&self.hostdinghy_dir).await?;
+
+		inner.tokens = cfg.server.tokens;
+		inner.metrics = cfg.metrics.map(|m| m.token);
+		inner.modified = self.modified().await;
+
+		Ok((token, valid_until))
+	}
+}
+
+#[derive(Debug, Parser)]
+pub struct Token {
+	#[clap(subcommand)]
+	cmd: TokenCommand,
+}
+
+#[derive(Debug, Parser)]
+enum TokenCommand {
+	Create(CreateToken),
+	List,
+	Revoke(RevokeToken),
+}
+
+#[derive(Debug, Parser)]
+struct CreateToken {
+	name: String,
+	/// Can be passed multiple times, one of read-only, apps, registry,
+	/// postgres or admin
+	#[clap(long = "scope", required = true)]
+	scopes: Vec<ApiTokenScope>,
+	/// If not set the token never expires
+	#[clap(long)]
+	expires_in_days: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+struct RevokeToken {
+	name: String,
+}
+
+pub async fn token(token: Token) -> Result<(), CliError> {
+	let hostdinghy_dir = hostdinghy_dir()?;
+	let mut cfg = Config::read(&hostdinghy_dir)
+		.await
+		.with_message("Failed to read config")?;
+
+	match token.cmd {
+		TokenCommand::Create(ct) => {
+			if cfg.server.token(&ct.name).is_some() {
+				return Err(CliError::any("token already exists", ct.name));
+			}
+
+			let valid_for = ct
+				.expires_in_days
+				.map(|d| Duration::from_secs(d * 24 * 60 * 60));
+			let token = ApiTokenConfig::new(ct.name, ct.scopes, valid_for);
+			cfg.server.tokens.push(token.clone());
+			cfg.write(&hostdinghy_dir)
+				.await
+				.with_message("Failed to write config")?;
+
+			info!("Token {} created.", token.name);
+			eprintln!("{}", token.token);
+		}
+		TokenCommand::List => {
+			let now = DateTime::now();
+
+			if cfg.server.tokens.is_empty() {
+				info!("No tokens found.");
+			}
+			for token in &cfg.server.tokens {
+				let scopes = token
+					.scopes
+					.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>()
+					.join(", ");
+				let expires = match &token.expires_on {
+					Some(_) if token.is_expired(&now) => "expired".into(),
+					Some(e) => format!("expires on {e}"),
+					None => "never expires".into(),
+				};
+
+				info!(
+					"- {} [{scopes}] created on {}, {expires}",
+					token.name, token.created_on
+				);
+			}
+		}
+		TokenCommand::Revoke(rt) => {
+			let len = cfg.server.tokens.len();
+			cfg.server.tokens.retain(|t| t.name != rt.name);
+			if cfg.server.tokens.len() == len {
+				return Err(CliError::any("token not found", rt.name));
+			}
+
+			cfg.write(&hostdinghy_dir)
+				.await
+				.with_message("Failed to write config")?;
+
+			info!("Token {} revoked.", rt.name);
+		}
+	}
+
+	Ok(())
+}
@@ -1,15 +1,24 @@
-use api::{error::Error, requests::ApiToken};
+use std::marker::PhantomData;
+
+use api::{
+	error::Error,
+	requests::{ApiToken, ApiTokenScope},
+};
 use axum::{extract::FromRequestParts, http::request::Parts};
 use hyper::header::AUTHORIZATION;
-use subtle::ConstantTimeEq as _;
 
-use crate::server::router::AppState;
+use crate::server::{config::ApiTokenConfig, router::AppState};
 
-pub struct Authenticated {
+pub struct Authenticated<ScopeCheck> {
 	// addr: SocketAddr,
+	pub token: ApiTokenConfig,
+	scope_check: PhantomData<ScopeCheck>,
 }
 
-impl FromRequestParts<AppState> for Authenticated {
+impl<SC> FromRequestParts<AppState> for Authenticated<SC>
+where
+	SC: ScopeCheck + Send + Sync + 'static,
+{
 	type Rejection = Error;
 
 	fn from_request_parts(
@@ -26,13 +35,60 @@ impl FromRequestParts<AppState> for Authenticated {
 				.and_then(|s| s.parse().ok())
 				.ok_or(Error::MissingApiToken)?;
 
-			// this is probably not necessary but why not
-			let choice =
-				state.cfg.server.api_token.as_ref().ct_eq(token.as_ref());
+			let token = state.tokens.authenticate(&token).await?;
+
+			if !token.allows(SC::SCOPE) {
+				return Err(Error::InsufficientScope {
+					scope: SC::SCOPE.to_string(),
+				});
+			}
+
+			Ok(Self {
+				token,
+				scope_check: PhantomData,
+			})
+		}
+	}
+}
 
-			bool::from(choice)
-				.then_some(Self {})
-				.ok_or(Error::InvalidApiToken)
+impl<SC> Authenticated<SC> {
+	/// Checks an additional scope, for requests which only sometimes need it
+	pub fn require(&self, scope: ApiTokenScope) -> Result<(), Error> {
+		if self.token.allows(scope) {
+			Ok(())
+		} else {
+			Err(Error::InsufficientScope {
+				scope: scope.to_string(),
+			})
 		}
 	}
 }
+
+pub trait ScopeCheck {
+	const SCOPE: ApiTokenScope;
+}
+
+/// Allowed for every token
+pub struct ScopeRead;
+
+impl ScopeCheck for ScopeRead {
+	const SCOPE: ApiTokenScope = ApiTokenScope::ReadOnly;
+}
+
+pub struct ScopeApps;
+
+impl ScopeCheck for ScopeApps {
+	const SCOPE: ApiTokenScope = ApiTokenScope::Apps;
+}
+
+pub struct ScopeRegistry;
+
+impl ScopeCheck for ScopeRegistry {
+	const SCOPE: ApiTokenScope = ApiTokenScope::Registry;
+}
+
+pub struct ScopePostgres;
+
+impl ScopeCheck for ScopePostgres {
+	const SCOPE: ApiTokenScope = ApiTokenScope::Postgres;
+}
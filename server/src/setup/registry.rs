@@ -51,6 +51,8 @@ storage:
     blobdescriptor: inmemory
   filesystem:
     rootdirectory: /var/lib/registry
+  delete:
+    enabled: true
 auth:
   htpasswd:
     realm: Registry
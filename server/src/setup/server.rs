@@ -6,7 +6,7 @@ use tracing::info;
 
 use crate::{
 	config::Config,
-	server::{maybe_create_cert, read_cert},
+	server::{config::DEFAULT_TOKEN, maybe_create_cert, read_cert},
 	utils::{
 		cli::{CliError, WithMessage as _},
 		cmd::cmd,
@@ -34,6 +34,12 @@ pub async fn setup() -> Result<(), CliError> {
 	let cfg = Config::read(&hostdinghy_dir)
 		.await
 		.with_message("Failed to read config")?;
+	let token = cfg.server.token(DEFAULT_TOKEN).ok_or_else(|| {
+		CliError::any(
+			"could not find the default api token",
+			"run `hostdinghy server token create default --scope admin`",
+		)
+	})?;
 
 	maybe_create_cert(&cfg, &hostdinghy_dir)
 		.await
@@ -76,7 +82,7 @@ pub async fn setup() -> Result<(), CliError> {
 	eprintln!(
 		"With the following information you can add the server \
 					to the hostdinghy ui:\n\n{}\n{cert}",
-		cfg.server.api_token,
+		token.token,
 	);
 
 	Ok(())
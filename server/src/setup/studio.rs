@@ -9,7 +9,7 @@ use tokio::{fs, time::sleep};
 use crate::{
 	config::Config,
 	postgres::Client,
-	server::read_cert,
+	server::{config::DEFAULT_TOKEN, read_cert},
 	utils::{
 		cli::{CliError, WithMessage},
 		compose, hostdinghy_dir, is_file, write_toml,
@@ -72,6 +72,12 @@ pub async fn setup(args: Studio) -> Result<(), CliError> {
 	let cfg = Config::read(&hostdinghy_dir)
 		.await
 		.with_message("Failed to read config")?;
+	let token = cfg.server.token(DEFAULT_TOKEN).ok_or_else(|| {
+		CliError::any(
+			"could not find the default api token",
+			"run `hostdinghy server token create default --scope admin`",
+		)
+	})?;
 
 	let studio_dir = hostdinghy_dir.join("hostdinghy");
 	fs::create_dir_all(&studio_dir)
@@ -148,7 +154,7 @@ pub async fn setup(args: Studio) -> Result<(), CliError> {
 			"main",
 			&user.team_id,
 			&cfg.domain,
-			&cfg.server.api_token.to_string(),
+			&token.token.to_string(),
 			&cert,
 		],
 	)
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,3 +9,47 @@ pub struct TraefikRoute {
 	// todo type correctly
 	pub status: String,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraefikService {
+	pub name: String,
+	pub status: String,
+	pub load_balancer: Option<TraefikLoadBalancer>,
+	/// The status per server url, only present if traefik runs health checks
+	#[serde(default)]
+	pub server_status: HashMap<String, String>,
+}
+
+impl TraefikService {
+	/// Returns true if a server with this ip is registered and not marked
+	/// as down
+	pub fn has_server_up(&self, ip: &str) -> bool {
+		self.load_balancer
+			.iter()
+			.flat_map(|lb| &lb.servers)
+			.filter(|s| url_host(&s.url) == Some(ip))
+			.any(|s| self.server_status.get(&s.url).is_none_or(|s| s == "UP"))
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraefikLoadBalancer {
+	#[serde(default)]
+	pub servers: Vec<TraefikServer>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraefikServer {
+	pub url: String,
+}
+
+/// Returns the host of an url like `http://172.18.0.5:80`
+fn url_host(url: &str) -> Option<&str> {
+	let rest = url.split_once("://").map_or(url, |(_, r)| r);
+	let host = rest.split(['/', '?']).next()?;
+
+	Some(host.rsplit_once(':').map_or(host, |(h, _)| h))
+}
@@ -5,7 +5,10 @@ use hyper::Method;
 use reqwest::RequestBuilder;
 use serde::de::DeserializeOwned;
 
-use crate::traefik::{TraefikConfig, api::TraefikRoute};
+use crate::traefik::{
+	TraefikConfig,
+	api::{TraefikRoute, TraefikService},
+};
 
 #[derive(Debug, Clone)]
 pub struct Traefik {
@@ -69,13 +72,21 @@ impl Traefik {
 		}
 	}
 
-	// /// for example: crelte-tut-2-craft@docker
-	// pub async fn service_by_name(
-	// 	&self,
-	// 	name: &str,
-	// ) -> Result<TraefikService, Error> {
-	// 	self.send(self.get(&format!("/api/services/{}", name)))
-	// }
+	/// for example: crelte-tut-2-craft@docker
+	pub async fn service_by_name(
+		&self,
+		name: &str,
+	) -> Result<TraefikService, Error> {
+		self.send(self.get(&format!("/api/http/services/{name}")))
+			.await
+	}
+
+	/// Returns all http routers of all providers
+	pub async fn routers(&self) -> Result<Vec<TraefikRoute>, Error> {
+		// the api is paginated, the default page only has 100 routers
+		self.send(self.get("/api/http/routers?per_page=10000"))
+			.await
+	}
 
 	pub async fn routers_by_service(
 		&self,
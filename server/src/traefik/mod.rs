@@ -1,6 +1,5 @@
 pub mod api;
 pub mod client;
-pub mod utils;
 
 use chuchi_crypto::token::Token;
 use dialoguer::{Input, theme::ColorfulTheme};
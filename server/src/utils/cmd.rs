@@ -1,4 +1,5 @@
 use std::{
+	ffi::OsStr,
 	future::poll_fn,
 	io,
 	path::Path,
@@ -55,6 +56,15 @@ impl CmdBuilder {
 		self
 	}
 
+	pub fn envs<K, V>(mut self, envs: impl IntoIterator<Item = (K, V)>) -> Self
+	where
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>,
+	{
+		self.inner.envs(envs);
+		self
+	}
+
 	pub fn arg_opt(mut self, arg: Option<&str>) -> Self {
 		if let Some(a) = arg {
 			self.inner.arg(a);
@@ -124,7 +134,7 @@ impl CmdBuilder {
 
 		Ok(ChildWritableStdin {
 			display: self.display,
-			stdin: child.stdin.take().unwrap(),
+			stdin: child.stdin.take(),
 			stdout: StdioReader::new(child.stdout.take().unwrap()),
 			stderr: StdioReader::new(child.stderr.take().unwrap()),
 			child,
@@ -311,13 +321,17 @@ impl Drop for ChildReadableStdout {
 pub struct ChildWritableStdin {
 	display: String,
 	child: Child,
-	stdin: ChildStdin,
+	/// gets taken once waiting so the child receives an EOF
+	stdin: Option<ChildStdin>,
 	stdout: StdioReader<ChildStdout>,
 	stderr: StdioReader<ChildStderr>,
 }
 
 impl ChildWritableStdin {
 	pub async fn wait(mut self) -> Result<(), CmdError> {
+		// close stdin, some commands read until EOF
+		drop(self.stdin.take());
+
 		// read stderr to drive status progress
 		let stderr = self.stderr.read().await.unwrap_or_else(|e| e.to_string());
 
@@ -366,21 +380,30 @@ impl AsyncWrite for ChildWritableStdin {
 		let _ = Pin::new(&mut self.stdout).poll_read(cx);
 		let _ = Pin::new(&mut self.stderr).poll_read(cx);
 
-		Pin::new(&mut self.stdin).poll_write(cx, buf)
+		match &mut self.stdin {
+			Some(stdin) => Pin::new(stdin).poll_write(cx, buf),
+			None => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+		}
 	}
 
 	fn poll_flush(
 		mut self: Pin<&mut Self>,
 		cx: &mut task::Context<'_>,
 	) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.stdin).poll_flush(cx)
+		match &mut self.stdin {
+			Some(stdin) => Pin::new(stdin).poll_flush(cx),
+			None => Poll::Ready(Ok(())),
+		}
 	}
 
 	fn poll_shutdown(
 		mut self: Pin<&mut Self>,
 		cx: &mut task::Context<'_>,
 	) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.stdin).poll_shutdown(cx)
+		match &mut self.stdin {
+			Some(stdin) => Pin::new(stdin).poll_shutdown(cx),
+			None => Poll::Ready(Ok(())),
+		}
 	}
 }
 
@@ -5,6 +5,20 @@ use crate::utils::cmd::{CmdError, cmd};
 pub async fn up(
 	file: impl AsRef<Path>,
 	service: Option<&str>,
+) -> Result<(), CmdError> {
+	up_with_env(file, service, true, &[]).await
+}
+
+/// The env gets passed to docker compose and can be used with interpolation,
+/// every other command on the app needs the same env or compose fails to
+/// interpolate the file
+///
+/// If `pull` is false only local images are used.
+pub async fn up_with_env(
+	file: impl AsRef<Path>,
+	service: Option<&str>,
+	pull: bool,
+	env: &[(String, String)],
 ) -> Result<(), CmdError> {
 	cmd(&[
 		"docker",
@@ -14,18 +28,124 @@ pub async fn up(
 		"up",
 		"-d",
 		"--pull",
-		"always",
+		if pull { "always" } else { "never" },
 		"--remove-orphans",
 	])
 	.arg_opt(service)
+	.envs(env.iter().map(|(k, v)| (k, v)))
+	.run()
+	.await
+	.map(|_| ())
+}
+
+/// Pulls the images of the given services
+pub async fn pull(
+	file: impl AsRef<Path>,
+	services: &[&str],
+	env: &[(String, String)],
+) -> Result<(), CmdError> {
+	let file = file.as_ref().to_string_lossy();
+	let mut args = vec!["docker", "compose", "-f", &file, "pull"];
+	args.extend(services);
+
+	cmd(&args)
+		.envs(env.iter().map(|(k, v)| (k, v)))
+		.run()
+		.await
+		.map(|_| ())
+}
+
+/// Recreates only the given services, if `pull` is false only local
+/// images are used
+///
+/// Dependencies of the services are not touched.
+pub async fn up_services_with_env(
+	file: impl AsRef<Path>,
+	services: &[&str],
+	pull: bool,
+	env: &[(String, String)],
+) -> Result<(), CmdError> {
+	let file = file.as_ref().to_string_lossy();
+	let mut args = vec![
+		"docker",
+		"compose",
+		"-f",
+		&file,
+		"up",
+		"-d",
+		"--pull",
+		if pull { "always" } else { "never" },
+		"--no-deps",
+	];
+	args.extend(services);
+
+	cmd(&args)
+		.envs(env.iter().map(|(k, v)| (k, v)))
+		.run()
+		.await
+		.map(|_| ())
+}
+
+/// Pulls the image and starts containers until the service has `replicas`
+/// containers, existing containers are not recreated
+pub async fn scale_service_with_env(
+	file: impl AsRef<Path>,
+	service: &str,
+	replicas: usize,
+	env: &[(String, String)],
+) -> Result<(), CmdError> {
+	cmd(&[
+		"docker",
+		"compose",
+		"-f",
+		&file.as_ref().to_string_lossy(),
+		"up",
+		"-d",
+		"--pull",
+		"always",
+		"--no-deps",
+		"--no-recreate",
+		"--scale",
+		&format!("{service}={replicas}"),
+		service,
+	])
+	.envs(env.iter().map(|(k, v)| (k, v)))
 	.run()
 	.await
 	.map(|_| ())
 }
 
+/// Validates the compose file without touching any containers
+///
+/// Relative paths inside the file are resolved from `project_dir` if provided.
+pub async fn config(
+	file: impl AsRef<Path>,
+	project_dir: Option<&Path>,
+	env: &[(String, String)],
+) -> Result<(), CmdError> {
+	let file = file.as_ref().to_string_lossy();
+	let mut args = vec!["docker", "compose", "-f", &file];
+
+	let project_dir_string;
+	if let Some(dir) = project_dir {
+		args.push("--project-directory");
+		project_dir_string = dir.to_string_lossy();
+		args.push(&project_dir_string);
+	}
+
+	args.extend(["config", "--quiet"]);
+
+	cmd(&args)
+		.envs(env.iter().map(|(k, v)| (k, v)))
+		.run()
+		.await
+		.map(|_| ())
+}
+
 pub async fn start(
 	file: impl AsRef<Path>,
 	service: Option<&str>,
+	env: &[(String, String)],
 ) -> Result<(), CmdError> {
 	cmd(&[
 		"docker",
@@ -35,6 +155,7 @@ pub async fn start(
 		"start",
 	])
 	.arg_opt(service)
+	.envs(env.iter().map(|(k, v)| (k, v)))
 	.run()
 	.await
 	.map(|_| ())
@@ -43,6 +164,7 @@ pub async fn start(
 pub async fn restart(
 	file: impl AsRef<Path>,
 	service: Option<&str>,
+	env: &[(String, String)],
 ) -> Result<(), CmdError> {
 	cmd(&[
 		"docker",
@@ -52,6 +174,7 @@ pub async fn restart(
 		"restart",
 	])
 	.arg_opt(service)
+	.envs(env.iter().map(|(k, v)| (k, v)))
 	.run()
 	.await
 	.map(|_| ())
@@ -60,6 +183,7 @@ pub async fn restart(
 pub async fn stop(
 	file: impl AsRef<Path>,
 	service: Option<&str>,
+	env: &[(String, String)],
 ) -> Result<(), CmdError> {
 	cmd(&[
 		"docker",
@@ -69,6 +193,27 @@ pub async fn stop(
 		"stop",
 	])
 	.arg_opt(service)
+	.envs(env.iter().map(|(k, v)| (k, v)))
+	.run()
+	.await
+	.map(|_| ())
+}
+
+pub async fn down(
+	file: impl AsRef<Path>,
+	volumes: bool,
+	env: &[(String, String)],
+) -> Result<(), CmdError> {
+	cmd(&[
+		"docker",
+		"compose",
+		"-f",
+		&file.as_ref().to_string_lossy(),
+		"down",
+		"--remove-orphans",
+	])
+	.arg_opt(volumes.then_some("--volumes"))
+	.envs(env.iter().map(|(k, v)| (k, v)))
 	.run()
 	.await
 	.map(|_| ())
@@ -77,6 +222,7 @@ pub async fn stop(
 pub async fn logs(
 	file: impl AsRef<Path>,
 	lines: Option<u32>,
+	env: &[(String, String)],
 ) -> Result<String, CmdError> {
 	let file = file.as_ref().to_string_lossy();
 	let mut args = vec!["docker", "compose", "-f", &file, "logs"];
@@ -88,7 +234,7 @@ pub async fn logs(
 		args.push(&lines_string);
 	}
 
-	cmd(&args).run().await
+	cmd(&args).envs(env.iter().map(|(k, v)| (k, v))).run().await
 }
 
 pub async fn exec(
@@ -102,3 +248,10 @@ pub async fn exec(
 
 	cmd(&args).run().await
 }
+
+pub async fn version() -> Result<String, CmdError> {
+	cmd(&["docker", "compose", "version", "--short"])
+		.run()
+		.await
+		.map(|v| v.trim().to_string())
+}
@@ -5,7 +5,7 @@ use std::{
 };
 
 use serde::{Deserialize, Serialize};
-use tokio::fs;
+use tokio::{fs, io::AsyncWriteExt as _};
 
 use crate::utils::cli::{CliError, WithMessage};
 
@@ -31,12 +31,37 @@ pub async fn write_toml<T: Serialize, P: AsRef<Path>>(
 	let s = toml::to_string(data)
 		.with_message("Failed to serialize data to TOML")?;
 
-	fs::write(path, s)
+	write_atomic(path, s)
 		.await
 		.with_message("Failed to write TOML file")
 }
 
-#[allow(dead_code)]
+/// Writes the file to `<path>.tmp` first and then renames it, so a crash
+/// never leaves a half written file behind.
+pub async fn write_atomic(
+	path: impl AsRef<Path>,
+	contents: impl AsRef<[u8]>,
+) -> io::Result<()> {
+	let path = path.as_ref();
+	let mut tmp_path = path.as_os_str().to_owned();
+	tmp_path.push(".tmp");
+	let tmp_path = PathBuf::from(tmp_path);
+
+	let res = async {
+		let mut file = fs::File::create(&tmp_path).await?;
+		file.write_all(contents.as_ref()).await?;
+		file.sync_all().await?;
+		fs::rename(&tmp_path, path).await
+	}
+	.await;
+
+	if res.is_err() {
+		let _ = fs::remove_file(&tmp_path).await;
+	}
+
+	res
+}
+
 pub async fn read_toml<T: for<'de> Deserialize<'de>, P: AsRef<Path>>(
 	path: P,
 ) -> Result<T, CliError> {
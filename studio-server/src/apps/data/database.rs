@@ -115,4 +115,8 @@ impl AppsTrait for Apps<'_> {
 
 		self.apps.insert(&row).await
 	}
+
+	async fn delete(&self, id: &AppId) -> Result<()> {
+		self.apps.delete(whr!(id)).await
+	}
 }
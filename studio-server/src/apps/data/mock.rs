@@ -80,4 +80,10 @@ impl AppsTrait for Arc<Apps> {
 		inner.insert(app.id.clone(), app.clone());
 		Ok(())
 	}
+
+	async fn delete(&self, id: &AppId) -> Result<()> {
+		let mut inner = self.apps.write().unwrap();
+		inner.remove(id);
+		Ok(())
+	}
 }
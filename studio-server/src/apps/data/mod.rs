@@ -45,4 +45,6 @@ pub trait AppsTrait {
 	) -> Result<Option<App>>;
 
 	async fn insert(&self, app: &App) -> Result<()>;
+
+	async fn delete(&self, id: &AppId) -> Result<()>;
 }
@@ -1,8 +1,10 @@
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use internal_api::apps::{
-	AppId, ComposeCommand as ApiComposeCommand, SaveComposeReq,
+	AppId, ComposeCommand as ApiComposeCommand, ComposeRevisionDiffReq,
+	ComposeRevisionDiffRes, ComposeRevisionRes, ComposeRevisionsRes,
+	RollbackComposeReq, SaveComposeReq, ValidateComposeReq, ValidateComposeRes,
 };
 use internal_api::error::Error as ApiError;
 use serde::{Deserialize, Serialize};
@@ -47,7 +49,7 @@ pub async fn set_compose(
 	State(api_client): State<ApiClient>,
 	Path(id): Path<AppId>,
 	conn: ConnOwned,
-	Json(req): Json<SaveComposeReq>,
+	Json(mut req): Json<SaveComposeReq>,
 ) -> Result<Json<String>> {
 	let apps = apps.with_conn(conn.conn());
 	let servers = servers.with_conn(conn.conn());
@@ -55,6 +57,7 @@ pub async fn set_compose(
 	let AppWithServer { api, .. } =
 		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
 
+	req.author = Some(user.user.username.clone());
 	api.apps().set_compose(&id, &req).await?;
 
 	let gcompose = api.apps().get_compose(&id).await?;
@@ -66,6 +69,116 @@ pub async fn set_compose(
 	Ok(Json(gcompose.compose))
 }
 
+pub async fn validate_compose(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+	Json(req): Json<ValidateComposeReq>,
+) -> Result<Json<ValidateComposeRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let res = api.apps().validate_compose(&id, &req).await?;
+
+	Ok(Json(res))
+}
+
+pub async fn compose_revisions(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Json<ComposeRevisionsRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let revisions = match api.apps().compose_revisions(&id).await {
+		Ok(r) => r,
+		Err(ApiError::AppNotFound) => ComposeRevisionsRes(vec![]),
+		Err(e) => return Err(e.into()),
+	};
+
+	Ok(Json(revisions))
+}
+
+pub async fn compose_revision(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path((id, revision)): Path<(AppId, u64)>,
+	conn: ConnOwned,
+) -> Result<Json<ComposeRevisionRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let revision = api.apps().compose_revision(&id, revision).await?;
+
+	Ok(Json(revision))
+}
+
+pub async fn compose_revision_diff(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path((id, revision)): Path<(AppId, u64)>,
+	Query(req): Query<ComposeRevisionDiffReq>,
+	conn: ConnOwned,
+) -> Result<Json<ComposeRevisionDiffRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let diff = api
+		.apps()
+		.compose_revision_diff(&id, revision, &req)
+		.await?;
+
+	Ok(Json(diff))
+}
+
+/// Returns the restored compose file
+pub async fn rollback_compose(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path((id, revision)): Path<(AppId, u64)>,
+	conn: ConnOwned,
+) -> Result<Json<String>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let req = RollbackComposeReq {
+		author: Some(user.user.username.clone()),
+	};
+	api.apps().rollback_compose(&id, revision, &req).await?;
+
+	let gcompose = api.apps().get_compose(&id).await?;
+
+	Ok(Json(gcompose.compose))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum ComposeCommand {
@@ -132,6 +245,17 @@ pub async fn compose_service_command(
 pub fn routes() -> Router<AppState> {
 	Router::new()
 		.route("/{id}/compose", get(get_compose).post(set_compose))
+		.route("/{id}/compose/validate", post(validate_compose))
+		.route("/{id}/compose/revisions", get(compose_revisions))
+		.route("/{id}/compose/revisions/{revision}", get(compose_revision))
+		.route(
+			"/{id}/compose/revisions/{revision}/diff",
+			get(compose_revision_diff),
+		)
+		.route(
+			"/{id}/compose/revisions/{revision}/rollback",
+			post(rollback_compose),
+		)
 		.route("/{id}/compose/{cmd}", post(compose_command))
 		.route(
 			"/{id}/compose/service/{service}/{cmd}",
@@ -0,0 +1,78 @@
+use axum::extract::{Path, State};
+use axum::routing::get;
+use axum::{Json, Router};
+use internal_api::apps::{AppDeploymentsRes, AppId};
+use internal_api::registry::RegistryTag;
+use serde::Serialize;
+
+use crate::AppState;
+use crate::apps::Apps;
+use crate::apps::routes::utils::{AppWithServer, app_with_server};
+use crate::error::Result;
+use crate::internal::ApiClient;
+use crate::servers::Servers;
+use crate::users::utils::{AuthedUser, RightsAny};
+use crate::utils::ConnOwned;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppImage {
+	pub service: String,
+	/// Sorted by the newest first
+	pub tags: Vec<RegistryTag>,
+}
+
+/// Returns the images of this app which were pushed to the registry
+pub async fn images(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Json<Vec<AppImage>>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let repositories = api.registry().repositories().await?;
+
+	let mut images = vec![];
+	for repo in repositories.into_iter().filter(|r| r.app_id == id) {
+		let tags = api.registry().tags(&id, &repo.service).await?;
+		images.push(AppImage {
+			service: repo.service,
+			tags,
+		});
+	}
+
+	Ok(Json(images))
+}
+
+/// Returns the deployments triggered by pushing an image, the newest first
+pub async fn deployments(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Json<AppDeploymentsRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let deployments = api.apps().deployments(&id).await?;
+
+	Ok(Json(deployments))
+}
+
+pub fn routes() -> Router<AppState> {
+	Router::new()
+		.route("/{id}/images", get(images))
+		.route("/{id}/deployments", get(deployments))
+}
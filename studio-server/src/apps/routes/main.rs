@@ -1,9 +1,15 @@
 use std::collections::HashMap;
+use std::convert::Infallible;
 
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::routing::get;
 use axum::{Json, Router};
-use internal_api::apps::{AppId, AppService, ServiceState};
+use futures::{Stream, StreamExt};
+use internal_api::apps::{
+	AppId, AppLogsStreamReq, AppService, AppStatsRes,
+	DeleteAppReq as ApiDeleteAppReq, ServiceState,
+};
 use internal_api::error::Error as ApiError;
 use pg::UniqueId;
 use pg::time::DateTime;
@@ -12,7 +18,7 @@ use serde::{Deserialize, Serialize};
 use crate::AppState;
 use crate::apps::routes::utils::{AppWithServer, app_with_server};
 use crate::apps::{Apps, data};
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::internal::ApiClient;
 use crate::servers::Servers;
 use crate::users::utils::AuthedUser;
@@ -175,6 +181,47 @@ pub async fn create(
 	Ok(Json(app))
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteAppReq {
+	/// Needs to match the id of the app which should be deleted
+	confirm_id: String,
+	#[serde(flatten)]
+	delete: ApiDeleteAppReq,
+}
+
+pub async fn delete(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+	Json(req): Json<DeleteAppReq>,
+) -> Result<Json<()>> {
+	if req.confirm_id != id.as_ref() {
+		return Err(Error::Request(
+			"The confirmation does not match the app id".into(),
+		));
+	}
+
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { app, api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	match api.apps().delete_app(&app.id, &req.delete).await {
+		// the app might never have had a compose file
+		Ok(()) | Err(ApiError::AppNotFound) => {}
+		Err(e) => return Err(e.into()),
+	}
+
+	apps.delete(&app.id).await?;
+
+	Ok(Json(()))
+}
+
 pub async fn logs(
 	user: AuthedUser<RightsAny>,
 	State(apps): State<Apps>,
@@ -196,9 +243,94 @@ pub async fn logs(
 	}
 }
 
+/// Streams the logs of the app as server sent events, each event contains a
+/// json `LogLine` or an `Error` if the event is called `error`
+pub async fn logs_stream(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	Query(req): Query<AppLogsStreamReq>,
+	conn: ConnOwned,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { app, api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let stream = api.apps().app_logs_stream(&app.id, &req).await?;
+
+	let stream = stream.map(|r| {
+		let event = match r {
+			Ok(line) => Event::default().json_data(line),
+			Err(e) => Event::default().event("error").json_data(Error::from(e)),
+		};
+
+		// serializing to json should never fail
+		Ok(event.unwrap())
+	});
+
+	Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+pub async fn stats(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Json<AppStatsRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { app, api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let stats = api.apps().app_stats(&app.id).await?;
+
+	Ok(Json(stats))
+}
+
+/// Streams the resource usage of the app as server sent events, each event
+/// contains a json `ServiceStats` or an `Error` if the event is called `error`
+pub async fn stats_stream(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { app, api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let stream = api.apps().app_stats_stream(&app.id).await?;
+
+	let stream = stream.map(|r| {
+		let event = match r {
+			Ok(stats) => Event::default().json_data(stats),
+			Err(e) => Event::default().event("error").json_data(Error::from(e)),
+		};
+
+		// serializing to json should never fail
+		Ok(event.unwrap())
+	});
+
+	Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 pub fn routes() -> Router<AppState> {
 	Router::new()
 		.route("/", get(all).post(create))
-		.route("/{id}", get(by_id))
+		.route("/{id}", get(by_id).delete(delete))
 		.route("/{id}/logs", get(logs))
+		.route("/{id}/logs/stream", get(logs_stream))
+		.route("/{id}/stats", get(stats))
+		.route("/{id}/stats/stream", get(stats_stream))
 }
@@ -1,11 +1,21 @@
 pub mod compose;
+pub mod images;
 pub mod main;
+pub mod secrets;
+pub mod shell;
 pub mod utils;
+pub mod volumes;
 
 use axum::Router;
 
 use crate::AppState;
 
 pub fn routes() -> Router<AppState> {
-	Router::new().merge(main::routes()).merge(compose::routes())
+	Router::new()
+		.merge(main::routes())
+		.merge(compose::routes())
+		.merge(images::routes())
+		.merge(secrets::routes())
+		.merge(shell::routes())
+		.merge(volumes::routes())
 }
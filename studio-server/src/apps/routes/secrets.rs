@@ -0,0 +1,78 @@
+use axum::extract::{Path, State};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use internal_api::apps::{AppId, AppSecretsRes, SetSecretReq};
+
+use crate::AppState;
+use crate::apps::Apps;
+use crate::apps::routes::utils::{AppWithServer, app_with_server};
+use crate::error::Result;
+use crate::internal::ApiClient;
+use crate::servers::Servers;
+use crate::users::utils::{AuthedUser, RightsAny};
+use crate::utils::ConnOwned;
+
+/// Only the names get returned, the values never leave the server
+pub async fn secrets(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Json<AppSecretsRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let secrets = api.apps().secrets(&id).await?;
+
+	Ok(Json(secrets))
+}
+
+pub async fn set_secret(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path((id, name)): Path<(AppId, String)>,
+	conn: ConnOwned,
+	Json(req): Json<SetSecretReq>,
+) -> Result<Json<()>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	api.apps().set_secret(&id, &name, &req).await?;
+
+	Ok(Json(()))
+}
+
+pub async fn unset_secret(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path((id, name)): Path<(AppId, String)>,
+	conn: ConnOwned,
+) -> Result<Json<()>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	api.apps().unset_secret(&id, &name).await?;
+
+	Ok(Json(()))
+}
+
+pub fn routes() -> Router<AppState> {
+	Router::new()
+		.route("/{id}/secrets", get(secrets))
+		.route("/{id}/secrets/{name}", put(set_secret).delete(unset_secret))
+}
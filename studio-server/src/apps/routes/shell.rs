@@ -0,0 +1,126 @@
+use axum::Router;
+use axum::extract::ws::{CloseFrame, Message, WebSocket, close_code};
+use axum::extract::{FromRef, Path, Query, State, WebSocketUpgrade};
+use axum::response::Response;
+use axum::routing::get;
+use futures::{SinkExt as _, StreamExt as _};
+use internal_api::apps::{AppId, ShellReq};
+use internal_api::client::{ShellConnection, ShellMessage};
+use tracing::warn;
+
+use crate::AppState;
+use crate::apps::Apps;
+use crate::apps::routes::utils::{AppWithServer, app_with_server};
+use crate::error::Result;
+use crate::internal::ApiClient;
+use crate::servers::Servers;
+use crate::users::utils::{AuthedUser, RightsAdmin};
+use crate::utils::ConnOwned;
+
+/// The states needed to find the server of an app
+#[derive(Clone)]
+pub struct ShellState {
+	apps: Apps,
+	servers: Servers,
+	api_client: ApiClient,
+}
+
+impl FromRef<AppState> for ShellState {
+	fn from_ref(state: &AppState) -> Self {
+		Self {
+			apps: Apps::from_ref(state),
+			servers: Servers::from_ref(state),
+			api_client: ApiClient::from_ref(state),
+		}
+	}
+}
+
+/// Opens an interactive shell in a service container.
+///
+/// Binary messages contain the raw terminal input and output, text messages
+/// contain a json `ShellControl`.
+pub async fn shell(
+	user: AuthedUser<RightsAdmin>,
+	State(state): State<ShellState>,
+	Path((id, service)): Path<(AppId, String)>,
+	Query(req): Query<ShellReq>,
+	conn: ConnOwned,
+	ws: WebSocketUpgrade,
+) -> Result<Response> {
+	let apps = state.apps.with_conn(conn.conn());
+	let servers = state.servers.with_conn(conn.conn());
+
+	let AppWithServer { app, api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &state.api_client).await?;
+
+	// the shell on the server is only opened once the browser is connected,
+	// otherwise it would keep running if the upgrade fails
+	Ok(ws.on_upgrade(move |mut socket| async move {
+		match api.apps().service_shell(&app.id, &service, &req).await {
+			Ok(shell) => proxy(socket, shell).await,
+			Err(e) => {
+				warn!("failed to open shell: {e}");
+				let frame = CloseFrame {
+					code: close_code::ERROR,
+					reason: e.to_string().into(),
+				};
+				let _ = socket.send(Message::Close(Some(frame))).await;
+			}
+		}
+	}))
+}
+
+async fn proxy(socket: WebSocket, shell: ShellConnection) {
+	let (mut shell_sink, mut shell_stream) = shell.split();
+	let (mut ws_sink, mut ws_stream) = socket.split();
+
+	let to_shell = async {
+		while let Some(Ok(msg)) = ws_stream.next().await {
+			let msg = match msg {
+				Message::Binary(b) => ShellMessage::Data(b),
+				Message::Text(t) => match serde_json::from_str(t.as_str()) {
+					Ok(c) => ShellMessage::Control(c),
+					Err(_) => continue,
+				},
+				Message::Close(_) => break,
+				_ => continue,
+			};
+
+			if shell_sink.send(msg).await.is_err() {
+				break;
+			}
+		}
+	};
+
+	let to_client = async {
+		while let Some(msg) = shell_stream.next().await {
+			let msg = match msg {
+				Ok(ShellMessage::Data(b)) => Message::Binary(b),
+				Ok(ShellMessage::Control(c)) => {
+					// serializing to json should never fail
+					Message::Text(serde_json::to_string(&c).unwrap().into())
+				}
+				Err(e) => {
+					warn!("shell connection failed: {e}");
+					break;
+				}
+			};
+
+			if ws_sink.send(msg).await.is_err() {
+				break;
+			}
+		}
+
+		let _ = ws_sink.send(Message::Close(None)).await;
+	};
+
+	// as soon as one side is done the other one is not needed anymore
+	tokio::select! {
+		_ = to_shell => {}
+		_ = to_client => {}
+	}
+}
+
+pub fn routes() -> Router<AppState> {
+	Router::new().route("/{id}/service/{service}/shell", get(shell))
+}
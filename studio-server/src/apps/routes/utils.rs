@@ -4,7 +4,10 @@ use crate::{
 	apps::data::{self, AppsWithConn},
 	error::{Error, Result},
 	internal::{ApiClient, ApiServerClient},
-	servers::data::{Server, ServersWithConn},
+	servers::{
+		data::{Server, ServersWithConn},
+		routes::utils::refresh_tls_pins,
+	},
 	users::utils::AuthedUser,
 };
 
@@ -29,6 +32,8 @@ pub async fn app_with_server<R>(
 		.connect(&server)
 		.map_err(|e| Error::InternalApiServer(e.to_string()))?;
 
+	refresh_tls_pins(&server, &api, servers, api_client).await;
+
 	Ok(AppWithServer { app, server, api })
 }
 
@@ -0,0 +1,101 @@
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use futures::StreamExt as _;
+use internal_api::apps::{
+	AppId, AppVolumesRes, VolumesRestoreReq, VolumesSnapshotReq,
+};
+use internal_api::error::WithMessage as _;
+
+use crate::AppState;
+use crate::apps::Apps;
+use crate::apps::routes::utils::{AppWithServer, app_with_server};
+use crate::error::Result;
+use crate::internal::ApiClient;
+use crate::servers::Servers;
+use crate::users::utils::{AuthedUser, RightsAdmin, RightsAny};
+use crate::utils::ConnOwned;
+
+pub async fn volumes(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	conn: ConnOwned,
+) -> Result<Json<AppVolumesRes>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let volumes = api.apps().volumes(&id).await?;
+
+	Ok(Json(volumes))
+}
+
+/// Returns the tar archive directly
+pub async fn volumes_snapshot(
+	user: AuthedUser<RightsAny>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	Query(req): Query<VolumesSnapshotReq>,
+	conn: ConnOwned,
+) -> Result<Body> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	let stream = api
+		.apps()
+		.volumes_snapshot(&id, &req)
+		.await?
+		.map(|r| r.with_message("failed to snapshot volumes"));
+
+	Ok(Body::from_stream(stream))
+}
+
+/// Replaces the content of the volumes, this is why admin rights are
+/// required
+#[allow(clippy::too_many_arguments)]
+pub async fn volumes_restore(
+	user: AuthedUser<RightsAdmin>,
+	State(apps): State<Apps>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	Path(id): Path<AppId>,
+	Query(req): Query<VolumesRestoreReq>,
+	conn: ConnOwned,
+	body: Body,
+) -> Result<Json<()>> {
+	let apps = apps.with_conn(conn.conn());
+	let servers = servers.with_conn(conn.conn());
+
+	let AppWithServer { api, .. } =
+		app_with_server(&id, &user, &apps, &servers, &api_client).await?;
+
+	api.apps()
+		.volumes_restore(
+			&id,
+			&req,
+			body.into_data_stream()
+				.map(|r| r.with_message("failed to read volume snapshot"))
+				.boxed(),
+		)
+		.await?;
+
+	Ok(Json(()))
+}
+
+pub fn routes() -> Router<AppState> {
+	Router::new()
+		.route("/{id}/volumes", get(volumes))
+		.route("/{id}/volumes/snapshot", get(volumes_snapshot))
+		.route("/{id}/volumes/restore", put(volumes_restore))
+}
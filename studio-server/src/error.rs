@@ -90,7 +90,13 @@ impl From<ApiError> for Error {
 		// todo better error matching
 		match e {
 			ApiError::Compose(e) => Self::Compose(e),
-			ApiError::AppNotFound => Self::NotFound,
+			ApiError::AppNotFound
+			| ApiError::ServiceNotFound
+			| ApiError::RevisionNotFound
+			| ApiError::BackupNotFound
+			| ApiError::RepositoryNotFound
+			| ApiError::SecretNotFound => Self::NotFound,
+			ApiError::InvalidSecretName => Self::Request(e.to_string()),
 			ApiError::Any { .. } => Self::InternalApiServer(e.to_string()),
 			e => Self::Internal(e.to_string()),
 		}
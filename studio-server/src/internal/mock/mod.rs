@@ -14,16 +14,31 @@ use crate::{
 
 use bytes::{Bytes, BytesMut};
 use futures::{
-	StreamExt,
+	SinkExt as _, StreamExt,
+	channel::mpsc,
+	future,
 	stream::{self, BoxStream},
 };
 use internal_api::{
-	apps::{AppId, AppInfoRes, ComposeCommand, GetComposeRes, SaveComposeReq},
-	client::Result,
+	apps::{
+		AppDeploymentsRes, AppId, AppInfoRes, AppLogsStreamReq, AppSecretsRes,
+		AppStatsRes, AppVolumesRes, ComposeCommand, ComposeRevisionDiffReq,
+		ComposeRevisionDiffRes, ComposeRevisionRes, ComposeRevisionsRes,
+		DeleteAppReq, GetComposeRes, LogLine, RollbackComposeReq,
+		SaveComposeReq, SaveComposeRes, ServiceStats, SetSecretReq, ShellReq,
+		ValidateComposeReq, ValidateComposeRes, VolumesRestoreReq,
+		VolumesSnapshotReq,
+	},
+	client::{Result, ShellConnection, ShellMessage},
 	error::Error,
-	postgres::{CreateDatabaseRes, DatabaseName, NewPasswordRes},
-	registry::{CreateUserRes, RegistryUsername},
-	requests::{InfoRes, PingRes},
+	postgres::{
+		CreateDatabaseRes, DatabaseName, NewPasswordRes, PostgresBackup,
+	},
+	registry::{
+		CreateUserRes, RegistryGcReq, RegistryGcRes, RegistryRepository,
+		RegistryTag, RegistryUsername,
+	},
+	requests::{InfoRes, PingRes, RotateApiTokenRes, SystemInfoRes},
 };
 use pg::{UniqueId, db::ConnOwned, time::DateTime};
 use rand::Rng;
@@ -101,9 +116,23 @@ impl ApiServerClientTrait for ApiServerClient {
 			version: server.version.clone(),
 			commit: None,
 			build_date: None,
+			token: Some(server.token.clone()),
+			tls: None,
 		})
 	}
 
+	async fn rotate_token(&self) -> Result<RotateApiTokenRes> {
+		let mut server = self.server.lock().unwrap();
+
+		Ok(server.rotate_token())
+	}
+
+	async fn system(&self) -> Result<SystemInfoRes> {
+		let server = self.server.lock().unwrap();
+
+		Ok(server.system())
+	}
+
 	fn apps(&self) -> &dyn ApiServerAppsClientTrait {
 		self
 	}
@@ -124,6 +153,11 @@ impl ApiServerAppsClientTrait for ApiServerClient {
 		server.app_info(id)
 	}
 
+	async fn delete_app(&self, id: &AppId, req: &DeleteAppReq) -> Result<()> {
+		let mut server = self.server.lock().unwrap();
+		server.app_delete(id, req)
+	}
+
 	async fn get_compose(&self, id: &AppId) -> Result<GetComposeRes> {
 		let server = self.server.lock().unwrap();
 		server.app_get_compose(id)
@@ -133,11 +167,57 @@ impl ApiServerAppsClientTrait for ApiServerClient {
 		&self,
 		id: &AppId,
 		req: &SaveComposeReq,
-	) -> Result<()> {
+	) -> Result<SaveComposeRes> {
 		let mut server = self.server.lock().unwrap();
 		server.app_set_compose(id, req)
 	}
 
+	async fn validate_compose(
+		&self,
+		id: &AppId,
+		req: &ValidateComposeReq,
+	) -> Result<ValidateComposeRes> {
+		let server = self.server.lock().unwrap();
+		Ok(server.app_validate_compose(id, req))
+	}
+
+	async fn compose_revisions(
+		&self,
+		id: &AppId,
+	) -> Result<ComposeRevisionsRes> {
+		let server = self.server.lock().unwrap();
+		server.app_compose_revisions(id)
+	}
+
+	async fn compose_revision(
+		&self,
+		id: &AppId,
+		revision: u64,
+	) -> Result<ComposeRevisionRes> {
+		let server = self.server.lock().unwrap();
+		server.app_compose_revision(id, revision)
+	}
+
+	async fn compose_revision_diff(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &ComposeRevisionDiffReq,
+	) -> Result<ComposeRevisionDiffRes> {
+		let server = self.server.lock().unwrap();
+		server.app_compose_revision_diff(id, revision, req)
+	}
+
+	async fn rollback_compose(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &RollbackComposeReq,
+	) -> Result<()> {
+		let mut server = self.server.lock().unwrap();
+		server.app_rollback_compose(id, revision, req)
+	}
+
 	async fn compose_command(
 		&self,
 		id: &AppId,
@@ -161,6 +241,122 @@ impl ApiServerAppsClientTrait for ApiServerClient {
 		let server = self.server.lock().unwrap();
 		server.app_logs(id, lines)
 	}
+
+	async fn app_logs_stream(
+		&self,
+		id: &AppId,
+		req: &AppLogsStreamReq,
+	) -> Result<BoxStream<'static, Result<LogLine>>> {
+		let server = self.server.lock().unwrap();
+		let lines = server.app_logs_stream(id, req)?;
+
+		Ok(stream::iter(lines.into_iter().map(Ok)).boxed())
+	}
+
+	async fn secrets(&self, id: &AppId) -> Result<AppSecretsRes> {
+		let server = self.server.lock().unwrap();
+		server.app_secrets(id)
+	}
+
+	async fn set_secret(
+		&self,
+		id: &AppId,
+		name: &str,
+		req: &SetSecretReq,
+	) -> Result<()> {
+		let mut server = self.server.lock().unwrap();
+		server.app_set_secret(id, name, req)
+	}
+
+	async fn unset_secret(&self, id: &AppId, name: &str) -> Result<()> {
+		let mut server = self.server.lock().unwrap();
+		server.app_unset_secret(id, name)
+	}
+
+	async fn volumes(&self, id: &AppId) -> Result<AppVolumesRes> {
+		let server = self.server.lock().unwrap();
+		server.app_volumes(id)
+	}
+
+	async fn volumes_snapshot(
+		&self,
+		id: &AppId,
+		_req: &VolumesSnapshotReq,
+	) -> Result<BoxStream<'static, Result<Bytes>>> {
+		let server = self.server.lock().unwrap();
+		let bytes = server.app_volumes_snapshot(id)?;
+
+		Ok(stream::once(async move { Ok(bytes) }).boxed())
+	}
+
+	async fn volumes_restore(
+		&self,
+		id: &AppId,
+		_req: &VolumesRestoreReq,
+		mut bytes_stream: BoxStream<'static, Result<Bytes>>,
+	) -> Result<()> {
+		let mut bytes = BytesMut::new();
+		while let Some(b) = bytes_stream.next().await {
+			bytes.extend_from_slice(b?.as_ref());
+		}
+
+		let mut server = self.server.lock().unwrap();
+		server.app_volumes_restore(id, bytes.into())
+	}
+
+	async fn deployments(&self, id: &AppId) -> Result<AppDeploymentsRes> {
+		let server = self.server.lock().unwrap();
+		server.app_deployments(id)
+	}
+
+	async fn service_shell(
+		&self,
+		id: &AppId,
+		service: &str,
+		_req: &ShellReq,
+	) -> Result<ShellConnection> {
+		{
+			let server = self.server.lock().unwrap();
+			// make sure the app exists
+			server.app_get_compose(id)?;
+		}
+
+		// the mock shell just echoes the input back
+		let (tx, rx) = mpsc::unbounded();
+		let sink =
+			tx.sink_map_err(|e| Error::any("Failed to send shell message", e));
+
+		let greeting = Bytes::from(format!("mock shell in {service}\r\n"));
+		let echo = rx.filter_map(|msg| {
+			future::ready(match msg {
+				ShellMessage::Data(b) => {
+					let b = String::from_utf8_lossy(&b).replace('\r', "\r\n");
+					Some(Ok(ShellMessage::Data(b.into())))
+				}
+				ShellMessage::Control(_) => None,
+			})
+		});
+		let stream =
+			stream::once(future::ready(Ok(ShellMessage::Data(greeting))))
+				.chain(echo);
+
+		Ok(ShellConnection::new(Box::pin(sink), stream.boxed()))
+	}
+
+	async fn app_stats(&self, id: &AppId) -> Result<AppStatsRes> {
+		let server = self.server.lock().unwrap();
+		server.app_stats(id)
+	}
+
+	async fn app_stats_stream(
+		&self,
+		id: &AppId,
+	) -> Result<BoxStream<'static, Result<ServiceStats>>> {
+		let server = self.server.lock().unwrap();
+		let stats = server.app_stats(id)?;
+
+		Ok(stream::iter(stats.services.into_iter().map(Ok)).boxed())
+	}
 }
 
 #[async_trait::async_trait]
@@ -182,6 +378,29 @@ impl ApiServerRegistryClientTrait for ApiServerClient {
 		let mut server = self.server.lock().unwrap();
 		server.registry_delete_user(username.as_ref())
 	}
+
+	async fn repositories(&self) -> Result<Vec<RegistryRepository>> {
+		let server = self.server.lock().unwrap();
+		server.registry_repositories()
+	}
+
+	async fn tags(
+		&self,
+		app_id: &AppId,
+		service: &str,
+	) -> Result<Vec<RegistryTag>> {
+		let server = self.server.lock().unwrap();
+		server.registry_tags(app_id, service)
+	}
+
+	async fn gc(&self, _req: &RegistryGcReq) -> Result<RegistryGcRes> {
+		// the mock tags are generated on every request, so there is
+		// nothing to delete
+		Ok(RegistryGcRes {
+			deleted: vec![],
+			freed_bytes: 0,
+		})
+	}
 }
 
 #[async_trait::async_trait]
@@ -230,4 +449,40 @@ impl ApiServerPostgresClientTrait for ApiServerClient {
 
 		Ok(stream::once(async move { Ok(bytes) }).boxed())
 	}
+
+	async fn backups(
+		&self,
+		name: &DatabaseName,
+	) -> Result<Vec<PostgresBackup>> {
+		let server = self.server.lock().unwrap();
+		server.postgres_backups(name.as_ref())
+	}
+
+	async fn create_backup(
+		&self,
+		name: &DatabaseName,
+	) -> Result<PostgresBackup> {
+		let mut server = self.server.lock().unwrap();
+		server.postgres_create_backup(name.as_ref())
+	}
+
+	async fn download_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<BoxStream<'static, Result<Bytes>>> {
+		let server = self.server.lock().unwrap();
+		let bytes = server.postgres_download_backup(name.as_ref(), backup)?;
+
+		Ok(stream::once(async move { Ok(bytes) }).boxed())
+	}
+
+	async fn restore_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<()> {
+		let mut server = self.server.lock().unwrap();
+		server.postgres_restore_backup(name.as_ref(), backup)
+	}
 }
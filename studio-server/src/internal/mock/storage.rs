@@ -1,22 +1,35 @@
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeMap, HashMap, HashSet},
 	sync::{Arc, Mutex},
+	time::Duration,
 };
 
 use bytes::Bytes;
-use compose_yml::Compose;
+use compose_yml::{
+	Compose, SecurityPolicy, diagnostics::diagnose, domains::ClaimedHost,
+};
 use crypto::token::Token;
 use internal_api::{
 	apps::{
-		AppId, AppInfoRes, AppService, ComposeCommand, GetComposeRes,
-		SaveComposeReq, ServiceRoute, ServiceState,
+		AppDeployment, AppDeploymentsRes, AppId, AppInfoRes, AppLogsStreamReq,
+		AppSecretsRes, AppService, AppStatsRes, AppVolume, AppVolumesRes,
+		ComposeCommand, ComposeRevision, ComposeRevisionDiffReq,
+		ComposeRevisionDiffRes, ComposeRevisionRes, ComposeRevisionsRes,
+		DeleteAppReq, DeployedService, DeploymentStatus, GetComposeRes,
+		HealthStatus, LogLine, LogStream, RollbackComposeReq, SaveComposeReq,
+		SaveComposeRes, ServiceHealth, ServiceRoute, ServiceState,
+		ServiceStats, SetSecretReq, ValidateComposeReq, ValidateComposeRes,
 	},
 	client::Result,
 	error::Error,
-	postgres::{CreateDatabaseRes, NewPasswordRes},
-	registry::CreateUserRes,
+	postgres::{CreateDatabaseRes, NewPasswordRes, PostgresBackup},
+	registry::{CreateUserRes, RegistryRepository, RegistryTag},
+	requests::{
+		ApiToken, ApiTokenInfo, ApiTokenScope, DiskUsage, MemoryUsage,
+		RotateApiTokenRes, SystemInfoRes,
+	},
 };
-use pg::UniqueId;
+use pg::{UniqueId, time::DateTime};
 use rand::Rng;
 use semver::Version;
 
@@ -72,9 +85,12 @@ pub struct ServerMock {
 	pub id: UniqueId,
 	pub registry_domain: String,
 	pub version: Version,
+	pub token: ApiTokenInfo,
 	apps: HashMap<AppId, AppMock>,
 	registry_users: HashSet<String>,
 	postgres_databases: HashMap<String, Bytes>,
+	/// newest backup last
+	postgres_backups: HashMap<String, Vec<(PostgresBackup, Bytes)>>,
 }
 
 impl ServerMock {
@@ -83,9 +99,57 @@ impl ServerMock {
 			id: server.id,
 			registry_domain: "registry.local".into(),
 			version: "0.0.0-debug.0".parse().unwrap(),
+			token: ApiTokenInfo {
+				name: "default".into(),
+				scopes: vec![ApiTokenScope::Admin],
+				created_on: server.created_on,
+				expires_on: None,
+			},
 			apps: HashMap::new(),
 			registry_users: HashSet::new(),
 			postgres_databases: HashMap::new(),
+			postgres_backups: HashMap::new(),
+		}
+	}
+
+	pub fn rotate_token(&mut self) -> RotateApiTokenRes {
+		let now = DateTime::now();
+		self.token.created_on = now;
+
+		RotateApiTokenRes {
+			token: ApiToken::new(),
+			info: self.token.clone(),
+			previous_valid_until: now + Duration::from_secs(10 * 60),
+		}
+	}
+
+	pub fn system(&self) -> SystemInfoRes {
+		const GB: u64 = 1024 * 1024 * 1024;
+
+		SystemInfoRes {
+			os: Some("Debian GNU/Linux 12 (bookworm)".into()),
+			kernel: "6.1.0-25-amd64".into(),
+			uptime: 12 * 24 * 60 * 60,
+			cpus: 4,
+			load_average: [0.42, 0.36, 0.31],
+			memory: MemoryUsage {
+				total: 8 * GB,
+				available: 5 * GB,
+			},
+			hostdinghy_disk: DiskUsage {
+				path: "/hostdinghy".into(),
+				total: 80 * GB,
+				available: 31 * GB,
+			},
+			// show what it looks like if a disk is almost full
+			docker_disk: Some(DiskUsage {
+				path: "/var/lib/docker".into(),
+				total: 40 * GB,
+				available: 3 * GB,
+			}),
+			docker_version: Some("28.3.3".into()),
+			compose_version: Some("2.39.1".into()),
+			postgres_version: Some("17.5".into()),
 		}
 	}
 
@@ -98,6 +162,20 @@ impl ServerMock {
 		app.app_info()
 	}
 
+	pub fn app_delete(&mut self, id: &AppId, req: &DeleteAppReq) -> Result<()> {
+		self.apps.remove(id);
+
+		if let Some(database) = &req.database {
+			self.postgres_databases.remove(database.as_ref());
+		}
+
+		for username in &req.registry_users {
+			self.registry_users.remove(username.as_ref());
+		}
+
+		Ok(())
+	}
+
 	pub fn app_get_compose(&self, id: &AppId) -> Result<GetComposeRes> {
 		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
 		app.app_get_compose()
@@ -107,15 +185,88 @@ impl ServerMock {
 		&mut self,
 		id: &AppId,
 		req: &SaveComposeReq,
-	) -> Result<()> {
+	) -> Result<SaveComposeRes> {
 		let parsed = req.compose.parse::<Compose>()?;
 		parsed.validate_for(&self.registry_domain, id.as_ref())?;
+		let warnings = parsed
+			.check_policy(&SecurityPolicy::default(), mock_app_dir(id))?;
+
+		parsed.check_domain_conflicts(&self.claimed_hosts(id))?;
 
 		let app = self
 			.apps
 			.entry(id.clone())
 			.or_insert_with(|| AppMock::new(id.clone()));
-		app.app_set_compose(req)
+		app.app_set_compose(req)?;
+
+		Ok(SaveComposeRes { warnings })
+	}
+
+	/// Returns the hosts used by all other apps
+	fn claimed_hosts(&self, id: &AppId) -> Vec<ClaimedHost> {
+		self.apps
+			.iter()
+			.filter(|(app_id, _)| *app_id != id)
+			.filter_map(|(app_id, app)| {
+				let compose = app.compose.as_ref()?.parse::<Compose>().ok()?;
+				Some(compose.claimed_hosts(Some(app_id.as_ref())))
+			})
+			.flatten()
+			.collect()
+	}
+
+	pub fn app_validate_compose(
+		&self,
+		id: &AppId,
+		req: &ValidateComposeReq,
+	) -> ValidateComposeRes {
+		ValidateComposeRes {
+			diagnostics: diagnose(
+				&req.compose,
+				&self.registry_domain,
+				id.as_ref(),
+				&SecurityPolicy::default(),
+				mock_app_dir(id),
+				&self.claimed_hosts(id),
+			),
+		}
+	}
+
+	pub fn app_compose_revisions(
+		&self,
+		id: &AppId,
+	) -> Result<ComposeRevisionsRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_compose_revisions()
+	}
+
+	pub fn app_compose_revision(
+		&self,
+		id: &AppId,
+		revision: u64,
+	) -> Result<ComposeRevisionRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_compose_revision(revision)
+	}
+
+	pub fn app_compose_revision_diff(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &ComposeRevisionDiffReq,
+	) -> Result<ComposeRevisionDiffRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_compose_revision_diff(revision, req)
+	}
+
+	pub fn app_rollback_compose(
+		&mut self,
+		id: &AppId,
+		revision: u64,
+		req: &RollbackComposeReq,
+	) -> Result<()> {
+		let app = self.apps.get_mut(id).ok_or(Error::AppNotFound)?;
+		app.app_rollback_compose(revision, req)
 	}
 
 	pub fn app_compose_command(
@@ -132,6 +283,88 @@ impl ServerMock {
 		app.app_logs(lines)
 	}
 
+	pub fn app_logs_stream(
+		&self,
+		id: &AppId,
+		req: &AppLogsStreamReq,
+	) -> Result<Vec<LogLine>> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_logs_stream(req)
+	}
+
+	pub fn app_stats(&self, id: &AppId) -> Result<AppStatsRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_stats()
+	}
+
+	pub fn app_secrets(&self, id: &AppId) -> Result<AppSecretsRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		Ok(AppSecretsRes {
+			names: app.secrets.keys().cloned().collect(),
+		})
+	}
+
+	pub fn app_set_secret(
+		&mut self,
+		id: &AppId,
+		name: &str,
+		req: &SetSecretReq,
+	) -> Result<()> {
+		let app = self.apps.get_mut(id).ok_or(Error::AppNotFound)?;
+
+		let valid = name
+			.chars()
+			.next()
+			.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+			&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+		// the same names the server reserves for docker compose
+		let reserved = ["PATH", "HOME", "LD_PRELOAD", "LD_LIBRARY_PATH"]
+			.contains(&name)
+			|| name.starts_with("DOCKER_")
+			|| name.starts_with("COMPOSE_");
+		if !valid || reserved {
+			return Err(Error::InvalidSecretName);
+		}
+
+		app.secrets.insert(name.to_string(), req.value.clone());
+		Ok(())
+	}
+
+	pub fn app_unset_secret(&mut self, id: &AppId, name: &str) -> Result<()> {
+		let app = self.apps.get_mut(id).ok_or(Error::AppNotFound)?;
+		app.secrets
+			.remove(name)
+			.map(|_| ())
+			.ok_or(Error::SecretNotFound)
+	}
+
+	pub fn app_volumes(&self, id: &AppId) -> Result<AppVolumesRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_volumes()
+	}
+
+	pub fn app_volumes_snapshot(&self, id: &AppId) -> Result<Bytes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_volumes()?;
+		Ok(app.volumes_snapshot.clone())
+	}
+
+	pub fn app_volumes_restore(
+		&mut self,
+		id: &AppId,
+		bytes: Bytes,
+	) -> Result<()> {
+		let app = self.apps.get_mut(id).ok_or(Error::AppNotFound)?;
+		app.app_volumes()?;
+		app.volumes_snapshot = bytes;
+		Ok(())
+	}
+
+	pub fn app_deployments(&self, id: &AppId) -> Result<AppDeploymentsRes> {
+		let app = self.apps.get(id).ok_or(Error::AppNotFound)?;
+		app.app_deployments()
+	}
+
 	pub fn registry_users(&self) -> Result<Vec<String>> {
 		Ok(self.registry_users.iter().cloned().collect())
 	}
@@ -159,6 +392,48 @@ impl ServerMock {
 		Ok(())
 	}
 
+	/// Every app with a compose file has a web repository
+	pub fn registry_repositories(&self) -> Result<Vec<RegistryRepository>> {
+		let mut repositories = self
+			.apps
+			.values()
+			.filter(|app| app.compose.is_some())
+			.map(|app| RegistryRepository {
+				app_id: app.id.clone(),
+				service: "web".into(),
+			})
+			.collect::<Vec<_>>();
+		repositories.sort_by(|a, b| a.app_id.as_ref().cmp(b.app_id.as_ref()));
+
+		Ok(repositories)
+	}
+
+	pub fn registry_tags(
+		&self,
+		app_id: &AppId,
+		service: &str,
+	) -> Result<Vec<RegistryTag>> {
+		self.apps
+			.get(app_id)
+			.filter(|app| app.compose.is_some() && service == "web")
+			.ok_or(Error::RepositoryNotFound)?;
+
+		let mut rng = rand::rng();
+		let now = DateTime::now().inner().timestamp_millis() as u64;
+		let week = 7 * 24 * 60 * 60 * 1000;
+
+		Ok(["latest", "v1.2.0", "v1.1.0"]
+			.into_iter()
+			.enumerate()
+			.map(|(i, name)| RegistryTag {
+				name: name.into(),
+				digest: format!("sha256:{}", Token::<32>::new()),
+				created_on: Some(DateTime::from_ms(now - i as u64 * week)),
+				size: rng.random_range(20_000_000..200_000_000),
+			})
+			.collect())
+	}
+
 	pub fn postgres_databases(&self) -> Result<Vec<String>> {
 		Ok(self.postgres_databases.keys().cloned().collect())
 	}
@@ -218,6 +493,61 @@ impl ServerMock {
 			.cloned()
 			.ok_or(Error::DatabaseNotFound)
 	}
+
+	pub fn postgres_backups(&self, name: &str) -> Result<Vec<PostgresBackup>> {
+		Ok(self
+			.postgres_backups
+			.get(name)
+			.map(|b| b.iter().rev().map(|(b, _)| b.clone()).collect())
+			.unwrap_or_default())
+	}
+
+	pub fn postgres_create_backup(
+		&mut self,
+		name: &str,
+	) -> Result<PostgresBackup> {
+		let bytes = self.postgres_dump_database(name)?;
+
+		let created_on = DateTime::now();
+		let backup = PostgresBackup {
+			id: created_on.inner().timestamp_millis() as u64,
+			database: name.to_string(),
+			created_on,
+			size: bytes.len() as u64,
+		};
+
+		self.postgres_backups
+			.entry(name.to_string())
+			.or_default()
+			.push((backup.clone(), bytes));
+
+		Ok(backup)
+	}
+
+	fn postgres_backup(&self, name: &str, backup: u64) -> Result<Bytes> {
+		self.postgres_backups
+			.get(name)
+			.and_then(|b| b.iter().find(|(b, _)| b.id == backup))
+			.map(|(_, bytes)| bytes.clone())
+			.ok_or(Error::BackupNotFound)
+	}
+
+	pub fn postgres_download_backup(
+		&self,
+		name: &str,
+		backup: u64,
+	) -> Result<Bytes> {
+		self.postgres_backup(name, backup)
+	}
+
+	pub fn postgres_restore_backup(
+		&mut self,
+		name: &str,
+		backup: u64,
+	) -> Result<()> {
+		let bytes = self.postgres_backup(name, backup)?;
+		self.postgres_restore_database(name, bytes)
+	}
 }
 
 const MOCK_COMPOSE: &str = include_str!("./mock_compose.yml");
@@ -227,6 +557,11 @@ const MOCK_LOGS: &str = include_str!("./mock_logs.txt");
 pub struct AppMock {
 	id: AppId,
 	compose: Option<String>,
+	/// newest revision last
+	revisions: Vec<(ComposeRevision, String)>,
+	secrets: BTreeMap<String, String>,
+	/// the last restored snapshot, returned as is
+	volumes_snapshot: Bytes,
 	started: Option<bool>,
 }
 
@@ -237,10 +572,51 @@ impl AppMock {
 		Self {
 			id,
 			compose: rng.random_bool(0.5).then(|| MOCK_COMPOSE.to_string()),
+			revisions: vec![],
+			secrets: BTreeMap::new(),
+			volumes_snapshot: Bytes::new(),
 			started: None,
 		}
 	}
 
+	pub fn app_volumes(&self) -> Result<AppVolumesRes> {
+		let _compose = self.compose.as_ref().ok_or(Error::AppNotFound)?;
+
+		Ok(AppVolumesRes {
+			volumes: vec![AppVolume {
+				name: "data".into(),
+				volume_name: format!("{}_data", self.id),
+			}],
+		})
+	}
+
+	pub fn app_deployments(&self) -> Result<AppDeploymentsRes> {
+		let _compose = self.compose.as_ref().ok_or(Error::AppNotFound)?;
+		let now = DateTime::now().inner().timestamp_millis() as u64;
+		let hour = 60 * 60 * 1000;
+
+		let service = |previous: &str, current: &str| DeployedService {
+			name: "web".into(),
+			previous_image_id: Some(format!("sha256:{previous}")),
+			image_id: Some(format!("sha256:{current}")),
+		};
+
+		Ok(AppDeploymentsRes(vec![
+			AppDeployment {
+				created_on: DateTime::from_ms(now - hour),
+				services: vec![service("b2", "c3")],
+				status: DeploymentStatus::Succeeded,
+				message: None,
+			},
+			AppDeployment {
+				created_on: DateTime::from_ms(now - 3 * hour),
+				services: vec![service("b2", "b2")],
+				status: DeploymentStatus::RolledBack,
+				message: Some("service web exited with code 1".into()),
+			},
+		]))
+	}
+
 	pub fn app_info(&self) -> Result<AppInfoRes> {
 		let _compose = self.compose.as_ref().ok_or(Error::AppNotFound)?;
 		let mut rng = rand::rng();
@@ -256,11 +632,12 @@ impl AppMock {
 				name: "craft".to_string(),
 				container_name: format!("{}-craft-1", self.id),
 				state_hr: service_state_to_str(&state).to_string(),
-				state,
-				routes: vec![ServiceRoute {
-					rule: "Host(`craft.example.com`)".to_string(),
-					domains: vec!["craft.example.com".to_string()],
-				}],
+				state: state.clone(),
+				routes: vec![ServiceRoute::from_rule(
+					"Host(`craft.example.com`)".to_string(),
+				)],
+				health: random_service_health(&state),
+				restart_count: random_restart_count(&state),
 			});
 		}
 
@@ -270,11 +647,12 @@ impl AppMock {
 				name: "svelte".to_string(),
 				container_name: format!("{}-svelte-1", self.id),
 				state_hr: service_state_to_str(&state).to_string(),
-				state,
-				routes: vec![ServiceRoute {
-					rule: "Host(`svelte.example.com`)".to_string(),
-					domains: vec!["svelte.example.com".to_string()],
-				}],
+				state: state.clone(),
+				routes: vec![ServiceRoute::from_rule(
+					"Host(`svelte.example.com`)".to_string(),
+				)],
+				health: random_service_health(&state),
+				restart_count: random_restart_count(&state),
 			});
 		}
 
@@ -289,6 +667,87 @@ impl AppMock {
 
 	pub fn app_set_compose(&mut self, req: &SaveComposeReq) -> Result<()> {
 		self.compose = Some(req.compose.clone());
+		self.push_revision(req.compose.clone(), req.author.clone());
+
+		Ok(())
+	}
+
+	fn push_revision(&mut self, compose: String, author: Option<String>) {
+		let created_on = DateTime::now();
+		let mut id = created_on.inner().timestamp_millis() as u64;
+		// make sure the ids stay unique even if we are fast
+		if let Some((last, _)) = self.revisions.last() {
+			id = id.max(last.id + 1);
+		}
+
+		self.revisions.push((
+			ComposeRevision {
+				id,
+				author,
+				created_on,
+			},
+			compose,
+		));
+	}
+
+	fn revision(&self, id: u64) -> Result<&(ComposeRevision, String)> {
+		self.revisions
+			.iter()
+			.find(|(r, _)| r.id == id)
+			.ok_or(Error::RevisionNotFound)
+	}
+
+	pub fn app_compose_revisions(&self) -> Result<ComposeRevisionsRes> {
+		Ok(ComposeRevisionsRes(
+			self.revisions
+				.iter()
+				.rev()
+				.map(|(r, _)| r.clone())
+				.collect(),
+		))
+	}
+
+	pub fn app_compose_revision(
+		&self,
+		revision: u64,
+	) -> Result<ComposeRevisionRes> {
+		let (revision, compose) = self.revision(revision)?.clone();
+
+		Ok(ComposeRevisionRes { revision, compose })
+	}
+
+	pub fn app_compose_revision_diff(
+		&self,
+		revision: u64,
+		req: &ComposeRevisionDiffReq,
+	) -> Result<ComposeRevisionDiffRes> {
+		let original = self.revision(revision)?.1.clone();
+		let modified = match req.against {
+			Some(against) => self.revision(against)?.1.clone(),
+			None => self.compose.clone().unwrap_or_default(),
+		};
+
+		let diff = similar::TextDiff::from_lines(&original, &modified)
+			.unified_diff()
+			.header("original", "modified")
+			.to_string();
+
+		Ok(ComposeRevisionDiffRes {
+			original,
+			modified,
+			diff,
+		})
+	}
+
+	pub fn app_rollback_compose(
+		&mut self,
+		revision: u64,
+		req: &RollbackComposeReq,
+	) -> Result<()> {
+		let compose = self.revision(revision)?.1.clone();
+		self.compose = Some(compose.clone());
+		self.push_revision(compose, req.author.clone());
+		self.started = Some(true);
 
 		Ok(())
 	}
@@ -308,6 +767,32 @@ impl AppMock {
 		Ok(())
 	}
 
+	pub fn app_stats(&self) -> Result<AppStatsRes> {
+		let _compose = self.compose.as_ref().ok_or(Error::AppNotFound)?;
+		if self.started == Some(false) {
+			return Ok(AppStatsRes { services: vec![] });
+		}
+
+		let mut rng = rand::rng();
+
+		let services = ["craft", "svelte"]
+			.into_iter()
+			.map(|name| ServiceStats {
+				name: name.to_string(),
+				container_name: format!("{}-{name}-1", self.id),
+				cpu_percent: rng.random_range(0.0..200.0),
+				memory_usage: rng.random_range(50..1024) * 1024 * 1024,
+				memory_limit: 8 * 1024 * 1024 * 1024,
+				network_rx: Some(rng.random_range(0..10_000_000)),
+				network_tx: Some(rng.random_range(0..10_000_000)),
+				block_read: rng.random_range(0..100_000_000),
+				block_write: rng.random_range(0..100_000_000),
+			})
+			.collect();
+
+		Ok(AppStatsRes { services })
+	}
+
 	pub fn app_logs(&self, lines: Option<u32>) -> Result<String> {
 		let _compose = self.compose.as_ref().ok_or(Error::AppNotFound)?;
 
@@ -320,6 +805,30 @@ impl AppMock {
 
 		Ok(logs.join("\n"))
 	}
+
+	pub fn app_logs_stream(
+		&self,
+		req: &AppLogsStreamReq,
+	) -> Result<Vec<LogLine>> {
+		let _compose = self.compose.as_ref().ok_or(Error::AppNotFound)?;
+		let service = req.service.clone().unwrap_or_else(|| "craft".into());
+
+		// the mock logs don't contain any timestamps so since and until
+		// are ignored
+		let logs = MOCK_LOGS
+			.lines()
+			.take(req.lines.map_or(usize::MAX, |l| l as usize))
+			.map(|line| LogLine {
+				service: service.clone(),
+				stream: LogStream::Stdout,
+				timestamp: None,
+				message: line.to_string(),
+			})
+			.filter(|_| req.stdout)
+			.collect();
+
+		Ok(logs)
+	}
 }
 
 const STATES: &[ServiceState] = &[
@@ -335,6 +844,11 @@ const STATES: &[ServiceState] = &[
 	ServiceState::Unknown,
 ];
 
+/// Where the app would be stored on a real server
+fn mock_app_dir(id: &AppId) -> String {
+	format!("/var/lib/hostdinghy/{id}")
+}
+
 fn random_service_state(started: Option<bool>) -> ServiceState {
 	let mut rng = rand::rng();
 
@@ -353,6 +867,37 @@ fn random_service_state(started: Option<bool>) -> ServiceState {
 	STATES[rng].clone()
 }
 
+fn random_service_health(state: &ServiceState) -> Option<ServiceHealth> {
+	let mut rng = rand::rng();
+
+	match state {
+		ServiceState::Unhealthy => Some(ServiceHealth {
+			status: HealthStatus::Unhealthy,
+			failing_streak: rng.random_range(3..20),
+			last_output: Some(
+				"curl: (7) Failed to connect to localhost port 8080".into(),
+			),
+			last_exit_code: Some(1),
+			last_probe_on: Some(DateTime::now()),
+		}),
+		ServiceState::Running if rng.random_bool(0.5) => Some(ServiceHealth {
+			status: HealthStatus::Healthy,
+			failing_streak: 0,
+			last_output: Some("ok".into()),
+			last_exit_code: Some(0),
+			last_probe_on: Some(DateTime::now()),
+		}),
+		_ => None,
+	}
+}
+
+fn random_restart_count(state: &ServiceState) -> u64 {
+	match state {
+		ServiceState::Restarting => rand::rng().random_range(1..50),
+		_ => 0,
+	}
+}
+
 const fn service_state_to_str(state: &ServiceState) -> &str {
 	match state {
 		ServiceState::Empty => "Empty",
@@ -5,27 +5,45 @@ use std::{
 	collections::HashMap,
 	fmt,
 	sync::{Arc, RwLock},
+	time::{Duration, Instant},
 };
 
 use bytes::Bytes;
 use futures::stream::BoxStream;
 use internal_api::{
-	apps::{AppId, AppInfoRes, ComposeCommand, GetComposeRes, SaveComposeReq},
-	client::{self as int, Result},
-	postgres::{CreateDatabaseRes, DatabaseName, NewPasswordRes},
-	registry::{CreateUserRes, RegistryUsername},
-	requests::{InfoRes, PingRes},
+	apps::{
+		AppDeploymentsRes, AppId, AppInfoRes, AppLogsStreamReq, AppSecretsRes,
+		AppStatsRes, AppVolumesRes, ComposeCommand, ComposeRevisionDiffReq,
+		ComposeRevisionDiffRes, ComposeRevisionRes, ComposeRevisionsRes,
+		DeleteAppReq, GetComposeRes, LogLine, RollbackComposeReq,
+		SaveComposeReq, SaveComposeRes, ServiceStats, SetSecretReq, ShellReq,
+		ValidateComposeReq, ValidateComposeRes, VolumesRestoreReq,
+		VolumesSnapshotReq,
+	},
+	client::{self as int, Result, ShellConnection},
+	postgres::{
+		CreateDatabaseRes, DatabaseName, NewPasswordRes, PostgresBackup,
+	},
+	registry::{
+		CreateUserRes, RegistryGcReq, RegistryGcRes, RegistryRepository,
+		RegistryTag, RegistryUsername,
+	},
+	requests::{InfoRes, PingRes, RotateApiTokenRes, SystemInfoRes},
 };
 use pg::{UniqueId, db::ConnOwned};
 
 use crate::{AppState, servers::data::Server};
 
+/// How often the tls pins of a server are checked when it is used
+const PINS_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 // todo should this be wrappen in a arc?
 // a clone here contains at least two arcs
 #[derive(Clone)]
 pub struct ApiClient {
 	inner: Inner,
 	servers: Arc<RwLock<HashMap<UniqueId, ApiServerClient>>>,
+	pins_checked: Arc<RwLock<HashMap<UniqueId, Instant>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +61,7 @@ impl ApiClient {
 				Inner::Real(int::ApiClient::new())
 			},
 			servers: Arc::new(RwLock::new(HashMap::new())),
+			pins_checked: Arc::new(RwLock::new(HashMap::new())),
 		}
 	}
 
@@ -89,6 +108,28 @@ impl ApiClient {
 
 		Ok(api_server_client)
 	}
+
+	/// Removes the connection of the server, for example after the token
+	/// changed
+	pub fn disconnect(&self, id: &UniqueId) {
+		self.servers.write().unwrap().remove(id);
+	}
+
+	/// Returns true if the tls pins of the server were not checked in the
+	/// last `PINS_CHECK_INTERVAL` and counts them as checked
+	pub fn pins_check_due(&self, id: &UniqueId) -> bool {
+		let mut checked = self.pins_checked.write().unwrap();
+		let now = Instant::now();
+		if checked
+			.get(id)
+			.is_some_and(|c| now.duration_since(*c) < PINS_CHECK_INTERVAL)
+		{
+			return false;
+		}
+
+		checked.insert(*id, now);
+		true
+	}
 }
 
 impl fmt::Debug for ApiClient {
@@ -109,6 +150,10 @@ pub trait ApiServerClientTrait {
 
 	async fn info(&self) -> Result<InfoRes>;
 
+	async fn rotate_token(&self) -> Result<RotateApiTokenRes>;
+
+	async fn system(&self) -> Result<SystemInfoRes>;
+
 	fn apps(&self) -> &dyn ApiServerAppsClientTrait;
 
 	fn registry(&self) -> &dyn ApiServerRegistryClientTrait;
@@ -120,10 +165,46 @@ pub trait ApiServerClientTrait {
 pub trait ApiServerAppsClientTrait {
 	async fn app_info(&self, id: &AppId) -> Result<AppInfoRes>;
 
+	async fn delete_app(&self, id: &AppId, req: &DeleteAppReq) -> Result<()>;
+
 	async fn get_compose(&self, id: &AppId) -> Result<GetComposeRes>;
 
-	async fn set_compose(&self, id: &AppId, req: &SaveComposeReq)
-	-> Result<()>;
+	async fn set_compose(
+		&self,
+		id: &AppId,
+		req: &SaveComposeReq,
+	) -> Result<SaveComposeRes>;
+
+	async fn validate_compose(
+		&self,
+		id: &AppId,
+		req: &ValidateComposeReq,
+	) -> Result<ValidateComposeRes>;
+
+	async fn compose_revisions(
+		&self,
+		id: &AppId,
+	) -> Result<ComposeRevisionsRes>;
+
+	async fn compose_revision(
+		&self,
+		id: &AppId,
+		revision: u64,
+	) -> Result<ComposeRevisionRes>;
+
+	async fn compose_revision_diff(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &ComposeRevisionDiffReq,
+	) -> Result<ComposeRevisionDiffRes>;
+
+	async fn rollback_compose(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &RollbackComposeReq,
+	) -> Result<()>;
 
 	async fn compose_command(
 		&self,
@@ -140,6 +221,54 @@ pub trait ApiServerAppsClientTrait {
 
 	/// How many lines to return, if None all lines are returned
 	async fn app_logs(&self, id: &AppId, lines: Option<u32>) -> Result<String>;
+
+	async fn app_logs_stream(
+		&self,
+		id: &AppId,
+		req: &AppLogsStreamReq,
+	) -> Result<BoxStream<'static, Result<LogLine>>>;
+
+	async fn secrets(&self, id: &AppId) -> Result<AppSecretsRes>;
+
+	async fn set_secret(
+		&self,
+		id: &AppId,
+		name: &str,
+		req: &SetSecretReq,
+	) -> Result<()>;
+
+	async fn unset_secret(&self, id: &AppId, name: &str) -> Result<()>;
+
+	async fn volumes(&self, id: &AppId) -> Result<AppVolumesRes>;
+
+	async fn volumes_snapshot(
+		&self,
+		id: &AppId,
+		req: &VolumesSnapshotReq,
+	) -> Result<BoxStream<'static, Result<Bytes>>>;
+
+	async fn volumes_restore(
+		&self,
+		id: &AppId,
+		req: &VolumesRestoreReq,
+		bytes: BoxStream<'static, Result<Bytes>>,
+	) -> Result<()>;
+
+	async fn deployments(&self, id: &AppId) -> Result<AppDeploymentsRes>;
+
+	async fn service_shell(
+		&self,
+		id: &AppId,
+		service: &str,
+		req: &ShellReq,
+	) -> Result<ShellConnection>;
+
+	async fn app_stats(&self, id: &AppId) -> Result<AppStatsRes>;
+
+	async fn app_stats_stream(
+		&self,
+		id: &AppId,
+	) -> Result<BoxStream<'static, Result<ServiceStats>>>;
 }
 
 #[async_trait::async_trait]
@@ -152,6 +281,16 @@ pub trait ApiServerRegistryClientTrait {
 	) -> Result<CreateUserRes>;
 
 	async fn delete_user(&self, username: &RegistryUsername) -> Result<()>;
+
+	async fn repositories(&self) -> Result<Vec<RegistryRepository>>;
+
+	async fn tags(
+		&self,
+		app_id: &AppId,
+		service: &str,
+	) -> Result<Vec<RegistryTag>>;
+
+	async fn gc(&self, req: &RegistryGcReq) -> Result<RegistryGcRes>;
 }
 
 #[async_trait::async_trait]
@@ -176,4 +315,24 @@ pub trait ApiServerPostgresClientTrait {
 		&self,
 		name: &DatabaseName,
 	) -> Result<BoxStream<'static, Result<Bytes>>>;
+
+	async fn backups(&self, name: &DatabaseName)
+	-> Result<Vec<PostgresBackup>>;
+
+	async fn create_backup(
+		&self,
+		name: &DatabaseName,
+	) -> Result<PostgresBackup>;
+
+	async fn download_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<BoxStream<'static, Result<Bytes>>>;
+
+	async fn restore_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<()>;
 }
@@ -1,11 +1,24 @@
 use bytes::Bytes;
 use futures::stream::BoxStream;
 use internal_api::{
-	apps::{AppId, AppInfoRes, ComposeCommand, GetComposeRes, SaveComposeReq},
-	client::{self as int, Result},
-	postgres::{CreateDatabaseRes, DatabaseName, NewPasswordRes},
-	registry::{CreateUserRes, RegistryUsername},
-	requests::{InfoRes, PingRes},
+	apps::{
+		AppDeploymentsRes, AppId, AppInfoRes, AppLogsStreamReq, AppSecretsRes,
+		AppStatsRes, AppVolumesRes, ComposeCommand, ComposeRevisionDiffReq,
+		ComposeRevisionDiffRes, ComposeRevisionRes, ComposeRevisionsRes,
+		DeleteAppReq, GetComposeRes, LogLine, RollbackComposeReq,
+		SaveComposeReq, SaveComposeRes, ServiceStats, SetSecretReq, ShellReq,
+		ValidateComposeReq, ValidateComposeRes, VolumesRestoreReq,
+		VolumesSnapshotReq,
+	},
+	client::{self as int, Result, ShellConnection},
+	postgres::{
+		CreateDatabaseRes, DatabaseName, NewPasswordRes, PostgresBackup,
+	},
+	registry::{
+		CreateUserRes, RegistryGcReq, RegistryGcRes, RegistryRepository,
+		RegistryTag, RegistryUsername,
+	},
+	requests::{InfoRes, PingRes, RotateApiTokenRes, SystemInfoRes},
 };
 
 use crate::{
@@ -22,9 +35,21 @@ pub struct ApiServerClient {
 
 impl ApiServerClient {
 	pub fn new(client: &int::ApiClient, server: &Server) -> Result<Self> {
-		client
-			.connect(&server.domain, &server.tls_cert, server.api_token.clone())
-			.map(|inner| Self { inner })
+		let inner = if server.tls_pins.is_empty() {
+			client.connect(
+				&server.domain,
+				&server.tls_cert,
+				server.api_token.clone(),
+			)
+		} else {
+			client.connect_pinned(
+				&server.domain,
+				server.tls_pins.clone(),
+				server.api_token.clone(),
+			)
+		};
+
+		inner.map(|inner| Self { inner })
 	}
 }
 
@@ -38,6 +63,14 @@ impl ApiServerClientTrait for ApiServerClient {
 		self.inner.info().await
 	}
 
+	async fn rotate_token(&self) -> Result<RotateApiTokenRes> {
+		self.inner.rotate_token().await
+	}
+
+	async fn system(&self) -> Result<SystemInfoRes> {
+		self.inner.system().await
+	}
+
 	fn apps(&self) -> &dyn ApiServerAppsClientTrait {
 		self
 	}
@@ -57,6 +90,10 @@ impl ApiServerAppsClientTrait for ApiServerClient {
 		self.inner.apps().app_info(id).await
 	}
 
+	async fn delete_app(&self, id: &AppId, req: &DeleteAppReq) -> Result<()> {
+		self.inner.apps().delete_app(id, req).await
+	}
+
 	async fn get_compose(&self, id: &AppId) -> Result<GetComposeRes> {
 		self.inner.apps().get_compose(id).await
 	}
@@ -65,10 +102,54 @@ impl ApiServerAppsClientTrait for ApiServerClient {
 		&self,
 		id: &AppId,
 		req: &SaveComposeReq,
-	) -> Result<()> {
+	) -> Result<SaveComposeRes> {
 		self.inner.apps().set_compose(id, req).await
 	}
 
+	async fn validate_compose(
+		&self,
+		id: &AppId,
+		req: &ValidateComposeReq,
+	) -> Result<ValidateComposeRes> {
+		self.inner.apps().validate_compose(id, req).await
+	}
+
+	async fn compose_revisions(
+		&self,
+		id: &AppId,
+	) -> Result<ComposeRevisionsRes> {
+		self.inner.apps().compose_revisions(id).await
+	}
+
+	async fn compose_revision(
+		&self,
+		id: &AppId,
+		revision: u64,
+	) -> Result<ComposeRevisionRes> {
+		self.inner.apps().compose_revision(id, revision).await
+	}
+
+	async fn compose_revision_diff(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &ComposeRevisionDiffReq,
+	) -> Result<ComposeRevisionDiffRes> {
+		self.inner
+			.apps()
+			.compose_revision_diff(id, revision, req)
+			.await
+	}
+
+	async fn rollback_compose(
+		&self,
+		id: &AppId,
+		revision: u64,
+		req: &RollbackComposeReq,
+	) -> Result<()> {
+		self.inner.apps().rollback_compose(id, revision, req).await
+	}
+
 	async fn compose_command(
 		&self,
 		id: &AppId,
@@ -92,6 +173,76 @@ impl ApiServerAppsClientTrait for ApiServerClient {
 	async fn app_logs(&self, id: &AppId, lines: Option<u32>) -> Result<String> {
 		self.inner.apps().app_logs(id, lines).await
 	}
+
+	async fn app_logs_stream(
+		&self,
+		id: &AppId,
+		req: &AppLogsStreamReq,
+	) -> Result<BoxStream<'static, Result<LogLine>>> {
+		self.inner.apps().app_logs_stream(id, req).await
+	}
+
+	async fn secrets(&self, id: &AppId) -> Result<AppSecretsRes> {
+		self.inner.apps().secrets(id).await
+	}
+
+	async fn set_secret(
+		&self,
+		id: &AppId,
+		name: &str,
+		req: &SetSecretReq,
+	) -> Result<()> {
+		self.inner.apps().set_secret(id, name, req).await
+	}
+
+	async fn unset_secret(&self, id: &AppId, name: &str) -> Result<()> {
+		self.inner.apps().unset_secret(id, name).await
+	}
+
+	async fn volumes(&self, id: &AppId) -> Result<AppVolumesRes> {
+		self.inner.apps().volumes(id).await
+	}
+
+	async fn volumes_snapshot(
+		&self,
+		id: &AppId,
+		req: &VolumesSnapshotReq,
+	) -> Result<BoxStream<'static, Result<Bytes>>> {
+		self.inner.apps().volumes_snapshot(id, req).await
+	}
+
+	async fn volumes_restore(
+		&self,
+		id: &AppId,
+		req: &VolumesRestoreReq,
+		bytes: BoxStream<'static, Result<Bytes>>,
+	) -> Result<()> {
+		self.inner.apps().volumes_restore(id, req, bytes).await
+	}
+
+	async fn deployments(&self, id: &AppId) -> Result<AppDeploymentsRes> {
+		self.inner.apps().deployments(id).await
+	}
+
+	async fn service_shell(
+		&self,
+		id: &AppId,
+		service: &str,
+		req: &ShellReq,
+	) -> Result<ShellConnection> {
+		self.inner.apps().service_shell(id, service, req).await
+	}
+
+	async fn app_stats(&self, id: &AppId) -> Result<AppStatsRes> {
+		self.inner.apps().app_stats(id).await
+	}
+
+	async fn app_stats_stream(
+		&self,
+		id: &AppId,
+	) -> Result<BoxStream<'static, Result<ServiceStats>>> {
+		self.inner.apps().app_stats_stream(id).await
+	}
 }
 
 #[async_trait::async_trait]
@@ -110,6 +261,22 @@ impl ApiServerRegistryClientTrait for ApiServerClient {
 	async fn delete_user(&self, username: &RegistryUsername) -> Result<()> {
 		self.inner.registry().delete_user(username).await
 	}
+
+	async fn repositories(&self) -> Result<Vec<RegistryRepository>> {
+		self.inner.registry().repositories().await
+	}
+
+	async fn tags(
+		&self,
+		app_id: &AppId,
+		service: &str,
+	) -> Result<Vec<RegistryTag>> {
+		self.inner.registry().tags(app_id, service).await
+	}
+
+	async fn gc(&self, req: &RegistryGcReq) -> Result<RegistryGcRes> {
+		self.inner.registry().gc(req).await
+	}
 }
 
 #[async_trait::async_trait]
@@ -146,4 +313,34 @@ impl ApiServerPostgresClientTrait for ApiServerClient {
 	) -> Result<BoxStream<'static, Result<Bytes>>> {
 		self.inner.postgres().dump_database(name).await
 	}
+
+	async fn backups(
+		&self,
+		name: &DatabaseName,
+	) -> Result<Vec<PostgresBackup>> {
+		self.inner.postgres().backups(name).await
+	}
+
+	async fn create_backup(
+		&self,
+		name: &DatabaseName,
+	) -> Result<PostgresBackup> {
+		self.inner.postgres().create_backup(name).await
+	}
+
+	async fn download_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<BoxStream<'static, Result<Bytes>>> {
+		self.inner.postgres().download_backup(name, backup).await
+	}
+
+	async fn restore_backup(
+		&self,
+		name: &DatabaseName,
+		backup: u64,
+	) -> Result<()> {
+		self.inner.postgres().restore_backup(name, backup).await
+	}
 }
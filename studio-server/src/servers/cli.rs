@@ -32,7 +32,9 @@ pub async fn create_server(
 		name: cs.name,
 		domain: cs.domain,
 		api_token: cs.api_token,
+		api_token_name: None,
 		tls_cert: cert,
+		tls_pins: vec![],
 		created_on: DateTime::now(),
 	};
 	let client = state
@@ -41,8 +43,13 @@ pub async fn create_server(
 		.expect("failed to connect to the server");
 
 	// check if the information of the server works
-	let _info = client.info().await.expect("failed to get server info");
+	let info = client.info().await.expect("failed to get server info");
 	// seems to work else info would have failed now we can insert the server
+	let server = Server {
+		api_token_name: info.token.map(|t| t.name),
+		tls_pins: info.tls.map(|t| t.pins()).unwrap_or_default(),
+		..server
+	};
 
 	servers.insert(&server).await.unwrap();
 
@@ -80,7 +87,9 @@ pub async fn create_mock_server(
 		name: cms.name.clone(),
 		domain: cms.name.clone(),
 		api_token: ApiToken::new(),
+		api_token_name: None,
 		tls_cert: cms.name,
+		tls_pins: vec![],
 		created_on: DateTime::now(),
 	};
 	servers.insert(&server).await.unwrap();
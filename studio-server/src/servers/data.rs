@@ -15,7 +15,13 @@ pub struct Server {
 	pub name: String,
 	pub domain: String,
 	pub api_token: ApiToken,
+	/// The name of the token on the server, None if the server did not
+	/// tell it
+	pub api_token_name: Option<String>,
 	pub tls_cert: String,
+	/// The fingerprints of the public keys of the server, if not empty they
+	/// are used instead of `tls_cert`
+	pub tls_pins: Vec<String>,
 	pub created_on: DateTime,
 }
 
@@ -43,4 +49,17 @@ pub trait ServersTrait {
 	) -> Result<Option<Server>>;
 
 	async fn insert(&self, server: &Server) -> Result<()>;
+
+	async fn update_api_token(
+		&self,
+		id: &UniqueId,
+		api_token: &ApiToken,
+		api_token_name: &str,
+	) -> Result<()>;
+
+	async fn update_tls_pins(
+		&self,
+		id: &UniqueId,
+		tls_pins: &[String],
+	) -> Result<()>;
 }
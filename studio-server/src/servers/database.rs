@@ -2,7 +2,7 @@ use internal_api::requests::ApiToken;
 use pg::{
 	Connection, Database, FromRow, Result, ToRow, UniqueId,
 	db::Conn,
-	filter,
+	filter, row,
 	table::{Table, table::TableWithConn},
 	time::DateTime,
 	whr,
@@ -10,7 +10,8 @@ use pg::{
 
 use crate::servers::data::{self, Server, ServersBuilderTrait, ServersTrait};
 
-const MIGRATIONS: &[(&str, &str)] = migration_files!("create-servers");
+const MIGRATIONS: &[(&str, &str)] =
+	migration_files!("create-servers", "add-api-token-name", "add-tls-pins");
 
 #[derive(Debug, Clone)]
 pub struct ServersBuilder {
@@ -58,6 +59,8 @@ struct ServerRow {
 	api_token: ApiToken,
 	tls_cert: String,
 	created_on: DateTime,
+	api_token_name: Option<String>,
+	tls_pins: Vec<String>,
 }
 
 impl From<ServerRow> for Server {
@@ -68,7 +71,9 @@ impl From<ServerRow> for Server {
 			domain: row.addr,
 			name: row.name,
 			api_token: row.api_token,
+			api_token_name: row.api_token_name,
 			tls_cert: row.tls_cert,
+			tls_pins: row.tls_pins,
 			created_on: row.created_on,
 		}
 	}
@@ -117,8 +122,42 @@ impl ServersTrait for Servers<'_> {
 			api_token: server.api_token.clone(),
 			tls_cert: server.tls_cert.clone(),
 			created_on: server.created_on,
+			api_token_name: server.api_token_name.clone(),
+			tls_pins: server.tls_pins.clone(),
 		};
 
 		self.servers.insert(&row).await
 	}
+
+	async fn update_api_token(
+		&self,
+		id: &UniqueId,
+		api_token: &ApiToken,
+		api_token_name: &str,
+	) -> Result<()> {
+		self.servers
+			.update(
+				row! {
+					"api_token": api_token,
+					"api_token_name": api_token_name,
+				},
+				whr!(id),
+			)
+			.await
+	}
+
+	async fn update_tls_pins(
+		&self,
+		id: &UniqueId,
+		tls_pins: &[String],
+	) -> Result<()> {
+		self.servers
+			.update(
+				row! {
+					"tls_pins": tls_pins,
+				},
+				whr!(id),
+			)
+			.await
+	}
 }
@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN api_token_name TEXT;
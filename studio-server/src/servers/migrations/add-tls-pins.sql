@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN tls_pins TEXT[] NOT NULL DEFAULT '{}';
@@ -3,6 +3,7 @@ use std::{
 	sync::{Arc, RwLock},
 };
 
+use internal_api::requests::ApiToken;
 use pg::{Result, UniqueId, db::Conn};
 
 use super::data::{Server, ServersBuilderTrait, ServersTrait, ServersWithConn};
@@ -76,4 +77,30 @@ impl ServersTrait for Arc<Servers> {
 		inner.insert(server.id, server.clone());
 		Ok(())
 	}
+
+	async fn update_api_token(
+		&self,
+		id: &UniqueId,
+		api_token: &ApiToken,
+		api_token_name: &str,
+	) -> Result<()> {
+		let mut inner = self.servers.write().unwrap();
+		if let Some(server) = inner.get_mut(id) {
+			server.api_token = api_token.clone();
+			server.api_token_name = Some(api_token_name.into());
+		}
+		Ok(())
+	}
+
+	async fn update_tls_pins(
+		&self,
+		id: &UniqueId,
+		tls_pins: &[String],
+	) -> Result<()> {
+		let mut inner = self.servers.write().unwrap();
+		if let Some(server) = inner.get_mut(id) {
+			server.tls_pins = tls_pins.to_vec();
+		}
+		Ok(())
+	}
 }
@@ -1,7 +1,9 @@
-use axum::extract::State;
-use axum::routing::get;
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
 use axum::{Json, Router};
-use internal_api::requests::{ApiToken, InfoRes};
+use internal_api::requests::{
+	ApiToken, ApiTokenInfo, InfoRes, SystemInfoRes, TlsInfo,
+};
 use pg::UniqueId;
 use pg::time::DateTime;
 use serde::{Deserialize, Serialize};
@@ -11,7 +13,8 @@ use crate::AppState;
 use crate::error::Result;
 use crate::internal::ApiClient;
 use crate::servers::Servers;
-use crate::users::utils::RightsAny;
+use crate::servers::routes::utils::{LoadServer, load_server, update_tls_pins};
+use crate::users::utils::{RightsAdmin, RightsAny};
 use crate::utils::ConnOwned;
 use crate::{servers::data, users::utils::AuthedUser};
 
@@ -25,6 +28,10 @@ pub struct Server {
 	pub registry_domain: Option<String>,
 	/// if this is empty the server could not be reached
 	pub version: Option<String>,
+	/// The token the studio uses, if the server could be reached
+	pub api_token: Option<ApiTokenInfo>,
+	/// The certificate of the server, if the server could be reached
+	pub tls: Option<TlsInfo>,
 	pub created_on: DateTime,
 }
 
@@ -32,6 +39,8 @@ impl Server {
 	pub fn populate_from_info(&mut self, info: InfoRes) {
 		self.registry_domain = Some(info.registry_domain);
 		self.version = Some(info.version.to_string());
+		self.api_token = info.token;
+		self.tls = info.tls;
 	}
 }
 
@@ -44,6 +53,8 @@ impl From<data::Server> for Server {
 			domain: server.domain,
 			registry_domain: None,
 			version: None,
+			api_token: None,
+			tls: None,
 			created_on: server.created_on,
 		}
 	}
@@ -57,18 +68,29 @@ async fn all(
 ) -> Result<Json<Vec<Server>>> {
 	let servers = servers.with_conn(conn.conn());
 
-	let servers = servers.all(&user.team_for_filter()).await?;
-	let mut n_servers = Vec::with_capacity(servers.len());
+	let all = servers.all(&user.team_for_filter()).await?;
+	let mut n_servers = Vec::with_capacity(all.len());
 
-	for server in servers {
+	for server in all {
 		let client = api.connect(&server)?;
-		let mut server = Server::from(server);
 
-		match client.info().await {
-			Ok(i) => server.populate_from_info(i),
+		let info = match client.info().await {
+			Ok(i) => Some(i),
 			Err(e) => {
-				error!("Failed to get info for server {}: {e}", server.id)
+				error!("Failed to get info for server {}: {e}", server.id);
+				None
 			}
+		};
+
+		if let Some(info) = &info
+			&& let Err(e) = update_tls_pins(&server, info, &servers, &api).await
+		{
+			error!("Failed to update tls pins of server {}: {e}", server.id);
+		}
+
+		let mut server = Server::from(server);
+		if let Some(info) = info {
+			server.populate_from_info(info);
 		}
 
 		n_servers.push(server);
@@ -89,7 +111,7 @@ struct CreateServerReq {
 async fn create(
 	user: AuthedUser<RightsAny>,
 	State(servers): State<Servers>,
-	State(client): State<ApiClient>,
+	State(client_api): State<ApiClient>,
 	conn: ConnOwned,
 	Json(req): Json<CreateServerReq>,
 ) -> Result<Json<Server>> {
@@ -102,14 +124,23 @@ async fn create(
 		name: req.name,
 		domain: req.domain,
 		api_token: req.api_token,
+		api_token_name: None,
 		tls_cert: req.tls_cert,
+		tls_pins: vec![],
 		created_on: DateTime::now(),
 	};
-	let client = client.connect(&server)?;
+	let client = client_api.connect(&server)?;
 
 	// check if the information of the server works
 	let info = client.info().await?;
+	let server = data::Server {
+		api_token_name: info.token.as_ref().map(|t| t.name.clone()),
+		tls_pins: info.tls.as_ref().map(|t| t.pins()).unwrap_or_default(),
+		..server
+	};
 	servers.insert(&server).await?;
+	// the connection was created with the certificate
+	client_api.disconnect(&server.id);
 
 	let mut server = Server::from(server);
 	server.populate_from_info(info);
@@ -117,6 +148,60 @@ async fn create(
 	Ok(Json(server))
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RotateTokenRes {
+	token: ApiTokenInfo,
+	previous_valid_until: DateTime,
+}
+
+/// Replaces the token of the server, requests which are still using the old
+/// token keep working until `previous_valid_until`
+async fn rotate_token(
+	user: AuthedUser<RightsAdmin>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path(id): Path<UniqueId>,
+) -> Result<Json<RotateTokenRes>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	let res = api.rotate_token().await?;
+	servers
+		.update_api_token(&id, &res.token, &res.info.name)
+		.await?;
+	// the next request connects with the new token
+	api_client.disconnect(&id);
+
+	Ok(Json(RotateTokenRes {
+		token: res.info,
+		previous_valid_until: res.previous_valid_until,
+	}))
+}
+
+/// Information about the host, to see for example if a server is running out
+/// of disk space
+async fn system(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path(id): Path<UniqueId>,
+) -> Result<Json<SystemInfoRes>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	api.system().await.map(Json).map_err(Into::into)
+}
+
 pub fn routes() -> Router<AppState> {
-	Router::new().route("/", get(all).post(create))
+	Router::new()
+		.route("/", get(all).post(create))
+		.route("/{id}/rotate-token", post(rotate_token))
+		.route("/{id}/system", get(system))
 }
@@ -6,6 +6,7 @@ use futures::StreamExt;
 use internal_api::error::WithMessage;
 use internal_api::postgres::{
 	CreateDatabaseReq, CreateDatabaseRes, DatabaseName, NewPasswordRes,
+	PostgresBackup,
 };
 use pg::UniqueId;
 
@@ -126,6 +127,86 @@ pub async fn dump_database(
 	Ok(Body::from_stream(stream))
 }
 
+/// Returns the backups sorted by the newest first
+pub async fn backups(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path((id, name)): Path<(UniqueId, DatabaseName)>,
+) -> Result<Json<Vec<PostgresBackup>>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	api.postgres()
+		.backups(&name)
+		.await
+		.map(Json)
+		.map_err(Into::into)
+}
+
+pub async fn create_backup(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path((id, name)): Path<(UniqueId, DatabaseName)>,
+) -> Result<Json<PostgresBackup>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	api.postgres()
+		.create_backup(&name)
+		.await
+		.map(Json)
+		.map_err(Into::into)
+}
+
+pub async fn download_backup(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path((id, name, backup)): Path<(UniqueId, DatabaseName, u64)>,
+) -> Result<Body> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	let stream = api
+		.postgres()
+		.download_backup(&name, backup)
+		.await?
+		.map(|r| r.with_message("failed to download backup"));
+
+	Ok(Body::from_stream(stream))
+}
+
+pub async fn restore_backup(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path((id, name, backup)): Path<(UniqueId, DatabaseName, u64)>,
+) -> Result<()> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	// todo this operation needs a security check again
+
+	api.postgres()
+		.restore_backup(&name, backup)
+		.await
+		.map_err(Into::into)
+}
+
 pub fn routes() -> Router<AppState> {
 	Router::new()
 		.route(
@@ -141,4 +222,16 @@ pub fn routes() -> Router<AppState> {
 			put(restore_database),
 		)
 		.route("/{id}/postgres/databases/{name}/dump", get(dump_database))
+		.route(
+			"/{id}/postgres/databases/{name}/backups",
+			get(backups).post(create_backup),
+		)
+		.route(
+			"/{id}/postgres/databases/{name}/backups/{backup}",
+			get(download_backup),
+		)
+		.route(
+			"/{id}/postgres/databases/{name}/backups/{backup}/restore",
+			post(restore_backup),
+		)
 }
@@ -1,7 +1,11 @@
 use axum::extract::{Path, State};
-use axum::routing::{delete, get};
+use axum::routing::{delete, get, post};
 use axum::{Json, Router};
-use internal_api::registry::{CreateUserReq, CreateUserRes, RegistryUsername};
+use internal_api::apps::AppId;
+use internal_api::registry::{
+	CreateUserReq, CreateUserRes, RegistryGcReq, RegistryGcRes,
+	RegistryRepository, RegistryTag, RegistryUsername,
+};
 use pg::UniqueId;
 
 use crate::AppState;
@@ -10,7 +14,7 @@ use crate::internal::ApiClient;
 use crate::servers::Servers;
 use crate::servers::routes::utils::{LoadServer, load_server};
 use crate::users::utils::AuthedUser;
-use crate::users::utils::RightsAny;
+use crate::users::utils::{RightsAdmin, RightsAny};
 use crate::utils::ConnOwned;
 
 /// Returns all users of that server not only for this app
@@ -68,8 +72,69 @@ pub async fn delete_user(
 		.map_err(Into::into)
 }
 
+pub async fn repositories(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path(id): Path<UniqueId>,
+) -> Result<Json<Vec<RegistryRepository>>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	api.registry()
+		.repositories()
+		.await
+		.map(Json)
+		.map_err(Into::into)
+}
+
+pub async fn repository_tags(
+	user: AuthedUser<RightsAny>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path((id, app_id, service)): Path<(UniqueId, AppId, String)>,
+) -> Result<Json<Vec<RegistryTag>>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	api.registry()
+		.tags(&app_id, &service)
+		.await
+		.map(Json)
+		.map_err(Into::into)
+}
+
+/// Deletes images from the registry, this is why admin rights are required
+pub async fn gc(
+	user: AuthedUser<RightsAdmin>,
+	State(servers): State<Servers>,
+	State(api_client): State<ApiClient>,
+	conn: ConnOwned,
+	Path(id): Path<UniqueId>,
+	Json(req): Json<RegistryGcReq>,
+) -> Result<Json<RegistryGcRes>> {
+	let servers = servers.with_conn(conn.conn());
+
+	let LoadServer { api, .. } =
+		load_server(&id, &user, &servers, &api_client).await?;
+
+	api.registry().gc(&req).await.map(Json).map_err(Into::into)
+}
+
 pub fn routes() -> Router<AppState> {
 	Router::new()
 		.route("/{id}/registry/users", get(all_users).post(create_user))
 		.route("/{id}/registry/users/{username}", delete(delete_user))
+		.route("/{id}/registry/repositories", get(repositories))
+		.route(
+			"/{id}/registry/repositories/{app_id}/{service}/tags",
+			get(repository_tags),
+		)
+		.route("/{id}/registry/gc", post(gc))
 }
@@ -1,4 +1,6 @@
+use internal_api::requests::InfoRes;
 use pg::UniqueId;
+use tracing::error;
 
 use crate::{
 	error::{Error, Result},
@@ -22,6 +24,8 @@ pub async fn load_server<R>(
 		.connect(&server)
 		.map_err(|e| Error::InternalApiServer(e.to_string()))?;
 
+	refresh_tls_pins(&server, &api, servers, api_client).await;
+
 	Ok(LoadServer { server, api })
 }
 
@@ -30,3 +34,53 @@ pub struct LoadServer {
 	pub server: Server,
 	pub api: ApiServerClient,
 }
+
+/// Pins the public keys the server announced, so the connection keeps
+/// working after the server renewed its certificate
+///
+/// The info was received over a verified connection, so the keys can be
+/// trusted.
+pub async fn update_tls_pins(
+	server: &Server,
+	info: &InfoRes,
+	servers: &ServersWithConn<'_>,
+	api: &ApiClient,
+) -> Result<()> {
+	let Some(tls) = &info.tls else {
+		return Ok(());
+	};
+
+	let pins = tls.pins();
+	if pins == server.tls_pins {
+		return Ok(());
+	}
+
+	servers.update_tls_pins(&server.id, &pins).await?;
+	api.disconnect(&server.id);
+
+	Ok(())
+}
+
+/// Checks the pins from time to time, so they stay up to date even if the
+/// list of servers is never opened
+///
+/// A failure is only logged, the request itself can still succeed.
+pub async fn refresh_tls_pins(
+	server: &Server,
+	api: &ApiServerClient,
+	servers: &ServersWithConn<'_>,
+	api_client: &ApiClient,
+) {
+	if !api_client.pins_check_due(&server.id) {
+		return;
+	}
+
+	let res = match api.info().await {
+		Ok(info) => update_tls_pins(server, &info, servers, api_client).await,
+		Err(e) => Err(e.into()),
+	};
+
+	if let Err(e) = res {
+		error!("Failed to update tls pins of server {}: {e}", server.id);
+	}
+}
@@ -93,3 +93,40 @@ export async function create(data: CreateServerRequest): Promise<Server> {
 
 	return server;
 }
+
+export type MemoryUsage = {
+	total: number;
+	available: number;
+};
+
+export type DiskUsage = {
+	path: string;
+	total: number;
+	available: number;
+};
+
+/** below this share of free space a disk is running out of space */
+export const LOW_DISK_SPACE = 0.1;
+
+export function isDiskLow(disk: DiskUsage): boolean {
+	return disk.total > 0 && disk.available < disk.total * LOW_DISK_SPACE;
+}
+
+export type SystemInfo = {
+	os: string | null;
+	kernel: string;
+	/** in seconds */
+	uptime: number;
+	cpus: number;
+	loadAverage: [number, number, number];
+	memory: MemoryUsage;
+	hostdinghyDisk: DiskUsage;
+	dockerDisk: DiskUsage | null;
+	dockerVersion: string | null;
+	composeVersion: string | null;
+	postgresVersion: string | null;
+};
+
+export async function loadSystemInfo(id: string): Promise<SystemInfo> {
+	return await api.get(`/${id}/system`);
+}
@@ -0,0 +1,12 @@
+const UNITS = ['B', 'KB', 'MB', 'GB', 'TB'];
+
+/** returns the bytes in a human readable format like 1.5 GB */
+export function formatBytes(bytes: number): string {
+	let unit = 0;
+	while (bytes >= 1024 && unit < UNITS.length - 1) {
+		bytes /= 1024;
+		unit++;
+	}
+
+	return `${unit === 0 ? bytes : bytes.toFixed(1)} ${UNITS[unit]}`;
+}